@@ -0,0 +1,245 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::character::PlayerCharacter;
+use crate::game::GameState;
+use crate::schema::{AdventureSettings, CampaignContext, QuestConclusion};
+use crate::store::{Store, StoreError};
+
+/// Version of the campaign file format written by this build.
+const CAMPAIGN_VERSION: u32 = 1;
+
+/// A quest finished as part of a campaign.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedQuest {
+    pub title: String,
+    pub summary: String,
+}
+
+/// A series of quests for the same party in one persistent world. Each quest
+/// is a separate game started with the campaign's `context`, and `record`
+/// carries what happened on it forward to the next.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Campaign {
+    pub name: String,
+    /// The party as they ended the last quest.
+    pub party: Vec<PlayerCharacter>,
+    /// The roster entry each party member was played from, if any.
+    pub roster_ids: Vec<Option<String>>,
+    pub adventure: AdventureSettings,
+    /// Oldest first.
+    pub quests: Vec<CompletedQuest>,
+    pub hooks: Vec<String>,
+    pub allies: Vec<String>,
+    pub enemies: Vec<String>,
+}
+
+impl Campaign {
+    pub fn new(
+        name: String,
+        party: Vec<PlayerCharacter>,
+        roster_ids: Vec<Option<String>>,
+        adventure: AdventureSettings,
+    ) -> Self {
+        Self {
+            name,
+            party,
+            roster_ids,
+            adventure,
+            quests: Vec::new(),
+            hooks: Vec::new(),
+            allies: Vec::new(),
+            enemies: Vec::new(),
+        }
+    }
+
+    /// What the GM is told about the campaign when its next quest starts, see
+    /// `GameBuilder::with_campaign`.
+    pub fn context(&self) -> CampaignContext {
+        CampaignContext {
+            past_quests: self
+                .quests
+                .iter()
+                .map(|quest| format!("{}: {}", quest.title, quest.summary))
+                .collect(),
+            hooks: self.hooks.clone(),
+            allies: self.allies.clone(),
+            enemies: self.enemies.clone(),
+        }
+    }
+
+    /// Adds a finished quest, as it ended in `state` and as the GM concluded
+    /// it, and takes the party on as they left it.
+    pub fn record(&mut self, state: &GameState, conclusion: QuestConclusion) {
+        self.party = state.party.clone();
+        self.quests.push(CompletedQuest {
+            title: state.quest.title.clone(),
+            summary: conclusion.summary,
+        });
+        self.hooks = conclusion.hooks;
+        self.allies = conclusion.allies;
+        self.enemies = conclusion.enemies;
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CampaignFile {
+    campaign: Campaign,
+}
+
+#[derive(Debug, Clone)]
+pub struct CampaignEntry {
+    pub id: String,
+    pub campaign: Campaign,
+}
+
+/// A directory of campaigns, one JSON file per campaign.
+#[derive(Debug, Clone)]
+pub struct Campaigns {
+    store: Store,
+}
+
+impl Campaigns {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            store: Store::new(dir, CAMPAIGN_VERSION),
+        }
+    }
+
+    /// `<data dir>/uquest/campaigns`, e.g. `~/.local/share/uquest/campaigns`
+    /// on Linux.
+    pub fn open_default() -> Result<Self, StoreError> {
+        Store::open_default("campaigns", CAMPAIGN_VERSION).map(|store| Self { store })
+    }
+
+    /// All readable campaigns, sorted by name. Unreadable files are logged and
+    /// skipped.
+    pub fn list(&self) -> Result<Vec<CampaignEntry>, StoreError> {
+        let mut campaigns: Vec<CampaignEntry> = self
+            .store
+            .list()?
+            .into_iter()
+            .map(|(id, saved): (String, CampaignFile)| CampaignEntry {
+                id,
+                campaign: saved.campaign,
+            })
+            .collect();
+        campaigns.sort_by(|a, b| a.campaign.name.cmp(&b.campaign.name));
+        Ok(campaigns)
+    }
+
+    pub fn load(&self, id: &str) -> Result<CampaignEntry, StoreError> {
+        let saved: CampaignFile = self.store.load(id)?;
+        Ok(CampaignEntry {
+            id: id.to_owned(),
+            campaign: saved.campaign,
+        })
+    }
+
+    /// Adds a new entry for `campaign` with an id derived from its name.
+    pub fn add(&self, campaign: Campaign) -> Result<CampaignEntry, StoreError> {
+        let entry = CampaignEntry {
+            id: self.store.unused_id(&campaign.name, "campaign"),
+            campaign,
+        };
+        self.save(&entry)?;
+        Ok(entry)
+    }
+
+    pub fn save(&self, entry: &CampaignEntry) -> Result<(), StoreError> {
+        let saved = CampaignFile {
+            campaign: entry.campaign.clone(),
+        };
+        self.store.save(&entry.id, &saved)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), StoreError> {
+        self.store.delete(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+    use crate::character::PlayerCharacterBuilder;
+    use crate::progression::LevelTable;
+
+    fn finished_quest(campaign: &Campaign, title: &str, xp: u32) -> GameState {
+        let mut state = GameState::new(
+            campaign.party.clone(),
+            LevelTable::default(),
+            campaign.adventure.clone(),
+            Some(campaign.context()),
+        );
+        state.quest.title = title.to_owned();
+        state.party[0].award_xp(xp);
+        state
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn each_quest_starts_where_the_last_left_off() {
+        let ann = PlayerCharacterBuilder::new("Ann".to_owned())
+            .build(&Catalog::bundled())
+            .unwrap();
+        let mut campaign = Campaign::new(
+            "The Ash Hills".to_owned(),
+            vec![ann],
+            vec![Some("ann".to_owned())],
+            AdventureSettings::default(),
+        );
+        assert_eq!(campaign.context(), CampaignContext::default());
+
+        let state = finished_quest(&campaign, "The Lost Crown", 300);
+        campaign.record(
+            &state,
+            QuestConclusion {
+                summary: "Ann found the crown.".to_owned(),
+                hooks: strings(&["Who stole the crown?"]),
+                allies: strings(&["Bram, the gatekeeper"]),
+                enemies: strings(&["The Ash King"]),
+            },
+        );
+        assert_eq!(campaign.party[0].xp(), 300);
+        let context = campaign.context();
+        assert_eq!(
+            context.past_quests,
+            ["The Lost Crown: Ann found the crown."]
+        );
+        assert_eq!(context.hooks, ["Who stole the crown?"]);
+        assert_eq!(context.allies, ["Bram, the gatekeeper"]);
+        assert_eq!(context.enemies, ["The Ash King"]);
+
+        // The next quest is told about the first, and its conclusion lists
+        // everything so far.
+        let state = finished_quest(&campaign, "The Ash King", 600);
+        assert_eq!(state.campaign.as_ref(), Some(&context));
+        campaign.record(
+            &state,
+            QuestConclusion {
+                summary: "The Ash King fell.".to_owned(),
+                hooks: strings(&["Who stole the crown?", "The king's heir"]),
+                allies: strings(&["Bram, the gatekeeper", "The river folk"]),
+                enemies: Vec::new(),
+            },
+        );
+        assert_eq!(campaign.party[0].xp(), 900);
+        assert_eq!(campaign.roster_ids, [Some("ann".to_owned())]);
+        let context = campaign.context();
+        assert_eq!(
+            context.past_quests,
+            [
+                "The Lost Crown: Ann found the crown.",
+                "The Ash King: The Ash King fell."
+            ]
+        );
+        assert_eq!(context.hooks, ["Who stole the crown?", "The king's heir"]);
+        assert_eq!(context.allies, ["Bram, the gatekeeper", "The river folk"]);
+        assert!(context.enemies.is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use schemars::schema::{RootSchema, Schema};
+use serde::{Deserialize, Serialize};
+
+use crate::character::Ability;
+
+const BUNDLED_CATALOG: &str = include_str!("../data/catalog.json");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Race {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub ability_adjustments: BTreeMap<Ability, i8>,
+    #[serde(default)]
+    pub names: NameTable,
+}
+
+impl Race {
+    /// Draws a given name, and a family name if the table has any, or `None` if
+    /// the race has no given names.
+    pub fn random_name(&self, rng: &mut impl Rng) -> Option<String> {
+        let given = self.names.given.choose(rng)?;
+        Some(match self.names.family.choose(rng) {
+            Some(family) => format!("{} {}", given, family),
+            None => given.clone(),
+        })
+    }
+}
+
+/// Culture-flavoured names for a race.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NameTable {
+    #[serde(default)]
+    pub given: Vec<String>,
+    #[serde(default)]
+    pub family: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Class {
+    pub name: String,
+    pub description: String,
+    pub starting_hp: u32,
+    #[serde(default)]
+    pub starting_equipment: Vec<String>,
+}
+
+/// The races and classes a player character may be built from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    #[serde(default)]
+    races: Vec<Race>,
+    #[serde(default)]
+    classes: Vec<Class>,
+}
+
+impl Catalog {
+    /// The catalog shipped with uQuest.
+    pub fn bundled() -> Self {
+        serde_json::from_str(BUNDLED_CATALOG).expect("Bundled catalog is invalid")
+    }
+
+    /// The bundled catalog extended with every `*.json` and `*.toml` file in the
+    /// user catalog directory, in name order. User entries replace bundled
+    /// entries with the same name.
+    pub fn load() -> Self {
+        let mut catalog = Self::bundled();
+        if let Some(dir) = Self::user_dir() {
+            catalog.extend_from_dir(&dir);
+        }
+        catalog
+    }
+
+    /// `<config dir>/uquest/catalog`, e.g. `~/.config/uquest/catalog` on Linux.
+    pub fn user_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("uquest").join("catalog"))
+    }
+
+    fn extend_from_dir(&mut self, dir: &Path) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "json" || ext == "toml")
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            let toml = path.extension().is_some_and(|ext| ext == "toml");
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|content| {
+                    if toml {
+                        toml::from_str::<Catalog>(&content).map_err(|err| err.to_string())
+                    } else {
+                        serde_json::from_str::<Catalog>(&content).map_err(|err| err.to_string())
+                    }
+                });
+            match parsed {
+                Ok(other) => {
+                    info!("Loaded catalog {}", path.display());
+                    self.extend(other);
+                }
+                Err(err) => error!("Could not load catalog {}: {}", path.display(), err),
+            }
+        }
+    }
+
+    pub fn extend(&mut self, other: Catalog) {
+        for race in other.races {
+            self.races
+                .retain(|r| !r.name.eq_ignore_ascii_case(&race.name));
+            self.races.push(race);
+        }
+        for class in other.classes {
+            self.classes
+                .retain(|c| !c.name.eq_ignore_ascii_case(&class.name));
+            self.classes.push(class);
+        }
+    }
+
+    pub fn races(&self) -> &[Race] {
+        &self.races
+    }
+
+    pub fn classes(&self) -> &[Class] {
+        &self.classes
+    }
+
+    pub fn race(&self, name: &str) -> Option<&Race> {
+        self.races
+            .iter()
+            .find(|r| r.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn class(&self, name: &str) -> Option<&Class> {
+        self.classes
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn random_race(&self, rng: &mut impl Rng) -> Option<&Race> {
+        self.races.choose(rng)
+    }
+
+    pub fn random_class(&self, rng: &mut impl Rng) -> Option<&Class> {
+        self.classes.choose(rng)
+    }
+
+    /// Restricts the `race` and `class` properties of `PlayerCharacter` in `schema`
+    /// to the names in this catalog.
+    pub fn annotate_schema(&self, schema: &mut RootSchema) {
+        let Some(Schema::Object(pc)) = schema.definitions.get_mut("PlayerCharacter") else {
+            return;
+        };
+        let Some(object) = pc.object.as_mut() else {
+            return;
+        };
+        let options = [
+            (
+                "race",
+                self.races.iter().map(|r| &r.name).collect::<Vec<_>>(),
+            ),
+            ("class", self.classes.iter().map(|c| &c.name).collect()),
+        ];
+        for (property, names) in options {
+            if let Some(Schema::Object(prop)) = object.properties.get_mut(property) {
+                prop.enum_values = Some(names.into_iter().map(|n| n.clone().into()).collect());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_files_can_be_json_or_toml() {
+        let dir = std::env::temp_dir().join(format!("uquest-catalog-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("a.json"),
+            r#"{"classes": [{"name": "Bard", "description": "Sings.", "starting_hp": 8}]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("b.toml"),
+            r#"
+[[races]]
+name = "Dwarf"
+description = "Stout and stubborn."
+names = { given = ["Brom"] }
+
+[races.ability_adjustments]
+Constitution = 2
+Dexterity = -1
+
+[[classes]]
+name = "bard"
+description = "Sings louder."
+starting_hp = 9
+starting_equipment = ["Lute"]
+"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("c.txt"), "not a catalog").unwrap();
+
+        let mut catalog = Catalog::bundled();
+        let races = catalog.races().len();
+        catalog.extend_from_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dwarf = catalog.race("dwarf").unwrap();
+        assert_eq!(dwarf.description, "Stout and stubborn.");
+        assert_eq!(dwarf.ability_adjustments[&Ability::Constitution], 2);
+        assert_eq!(dwarf.ability_adjustments[&Ability::Dexterity], -1);
+        assert_eq!(dwarf.names.given, ["Brom"]);
+        // The TOML file comes after the JSON one, so its bard wins.
+        let bard = catalog.class("Bard").unwrap();
+        assert_eq!(bard.starting_hp, 9);
+        assert_eq!(bard.starting_equipment, ["Lute"]);
+        assert_eq!(
+            catalog
+                .classes()
+                .iter()
+                .filter(|c| c.name.eq_ignore_ascii_case("bard"))
+                .count(),
+            1
+        );
+        // The bundled dwarf is replaced rather than joined.
+        assert_eq!(catalog.races().len(), races);
+    }
+}
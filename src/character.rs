@@ -1,11 +1,180 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::catalog::Catalog;
+use crate::progression::{Improvement, LevelTable};
+
+#[derive(Debug, Clone)]
+pub enum CharacterError {
+    EmptyName,
+    UnknownRace(String),
+    UnknownClass(String),
+    /// Stats no character built from the catalog could reach, e.g. a level
+    /// without the XP for it.
+    Implausible(String),
+}
+
+impl std::fmt::Display for CharacterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CharacterError::EmptyName => write!(f, "The character needs a name"),
+            CharacterError::UnknownRace(race) => write!(f, "Unknown race \"{}\"", race),
+            CharacterError::UnknownClass(class) => write!(f, "Unknown class \"{}\"", class),
+            CharacterError::Implausible(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CharacterError {}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+pub enum Ability {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Wisdom,
+    Charisma,
+}
+
+impl Ability {
+    pub const ALL: [Ability; 6] = [
+        Ability::Strength,
+        Ability::Dexterity,
+        Ability::Constitution,
+        Ability::Intelligence,
+        Ability::Wisdom,
+        Ability::Charisma,
+    ];
+
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Ability::Strength => "STR",
+            Ability::Dexterity => "DEX",
+            Ability::Constitution => "CON",
+            Ability::Intelligence => "INT",
+            Ability::Wisdom => "WIS",
+            Ability::Charisma => "CHA",
+        }
+    }
+}
+
+impl std::fmt::Display for Ability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Ability::Strength => "Strength",
+            Ability::Dexterity => "Dexterity",
+            Ability::Constitution => "Constitution",
+            Ability::Intelligence => "Intelligence",
+            Ability::Wisdom => "Wisdom",
+            Ability::Charisma => "Charisma",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AbilityScores {
+    pub strength: u8,
+    pub dexterity: u8,
+    pub constitution: u8,
+    pub intelligence: u8,
+    pub wisdom: u8,
+    pub charisma: u8,
+}
+
+impl AbilityScores {
+    pub const MAX: u8 = 20;
+    /// The best score `roll` can give.
+    pub const ROLLED_MAX: u8 = 18;
+
+    /// Rolls each ability as the total of the highest three of four d6.
+    pub fn roll(rng: &mut impl Rng) -> Self {
+        let mut roll = || {
+            let mut dice: [u8; 4] = std::array::from_fn(|_| rng.gen_range(1..=6));
+            dice.sort_unstable();
+            dice[1..].iter().sum()
+        };
+        Self {
+            strength: roll(),
+            dexterity: roll(),
+            constitution: roll(),
+            intelligence: roll(),
+            wisdom: roll(),
+            charisma: roll(),
+        }
+    }
+
+    pub fn get(&self, ability: Ability) -> u8 {
+        match ability {
+            Ability::Strength => self.strength,
+            Ability::Dexterity => self.dexterity,
+            Ability::Constitution => self.constitution,
+            Ability::Intelligence => self.intelligence,
+            Ability::Wisdom => self.wisdom,
+            Ability::Charisma => self.charisma,
+        }
+    }
+
+    pub fn get_mut(&mut self, ability: Ability) -> &mut u8 {
+        match ability {
+            Ability::Strength => &mut self.strength,
+            Ability::Dexterity => &mut self.dexterity,
+            Ability::Constitution => &mut self.constitution,
+            Ability::Intelligence => &mut self.intelligence,
+            Ability::Wisdom => &mut self.wisdom,
+            Ability::Charisma => &mut self.charisma,
+        }
+    }
+
+    /// Adds `delta` to an ability, keeping the score between 1 and `MAX`.
+    pub fn adjust(&mut self, ability: Ability, delta: i8) {
+        let score = self.get_mut(ability);
+        *score = (*score as i16 + delta as i16).clamp(1, Self::MAX as i16) as u8;
+    }
+}
+
+impl Default for AbilityScores {
+    fn default() -> Self {
+        Self {
+            strength: 10,
+            dexterity: 10,
+            constitution: 10,
+            intelligence: 10,
+            wisdom: 10,
+            charisma: 10,
+        }
+    }
+}
+
+/// Optional descriptive details that give the GM something to work with beyond
+/// race and class.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CharacterDetails {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backstory: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub personality_traits: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ideals: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flaws: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appearance: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct PlayerCharacterBuilder {
     name: String,
     race: String,
     class: String,
+    abilities: AbilityScores,
+    details: CharacterDetails,
 }
 
 impl PlayerCharacterBuilder {
@@ -14,7 +183,48 @@ impl PlayerCharacterBuilder {
             name,
             race: "human".to_owned(),
             class: "fighter".to_owned(),
+            abilities: AbilityScores::default(),
+            details: CharacterDetails::default(),
+        }
+    }
+
+    /// A random race, class, name and ability scores drawn from `catalog`. The
+    /// same seed and catalog always produce the same character.
+    pub fn random(seed: u64, catalog: &Catalog) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut builder = Self::new("Jim".to_owned());
+        if let Some(race) = catalog.random_race(&mut rng) {
+            builder.race = race.name.clone();
+            if let Some(name) = race.random_name(&mut rng) {
+                builder.name = name;
+            }
         }
+        if let Some(class) = catalog.random_class(&mut rng) {
+            builder.class = class.name.clone();
+        }
+        builder.abilities = AbilityScores::roll(&mut rng);
+        builder
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn race(&self) -> &str {
+        &self.race
+    }
+
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    pub fn abilities(&self) -> &AbilityScores {
+        &self.abilities
+    }
+
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
     }
 
     pub fn with_race(mut self, race: String) -> Self {
@@ -27,20 +237,65 @@ impl PlayerCharacterBuilder {
         self
     }
 
-    pub fn build(self) -> PlayerCharacter {
-        PlayerCharacter {
-            name: self.name,
-            race: self.race,
-            class: self.class,
+    pub fn with_abilities(mut self, abilities: AbilityScores) -> Self {
+        self.abilities = abilities;
+        self
+    }
+
+    pub fn with_details(mut self, details: CharacterDetails) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// Builds the character, applying the race's ability adjustments and the
+    /// class's starting hit points and equipment from `catalog`.
+    pub fn build(self, catalog: &Catalog) -> Result<PlayerCharacter, CharacterError> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(CharacterError::EmptyName);
+        }
+        let race = catalog
+            .race(&self.race)
+            .ok_or_else(|| CharacterError::UnknownRace(self.race.clone()))?;
+        let class = catalog
+            .class(&self.class)
+            .ok_or_else(|| CharacterError::UnknownClass(self.class.clone()))?;
+
+        let mut abilities = self.abilities;
+        for (ability, delta) in race.ability_adjustments.iter() {
+            abilities.adjust(*ability, *delta);
         }
+
+        Ok(PlayerCharacter {
+            name: name.to_owned(),
+            race: race.name.clone(),
+            class: class.name.clone(),
+            level: 1,
+            xp: 0,
+            hp: class.starting_hp,
+            max_hp: class.starting_hp,
+            abilities,
+            features: Vec::new(),
+            inventory: class.starting_equipment.clone(),
+            details: self.details,
+        })
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct PlayerCharacter {
     name: String,
     race: String,
     class: String,
+    level: u32,
+    xp: u32,
+    hp: u32,
+    max_hp: u32,
+    abilities: AbilityScores,
+    features: Vec<String>,
+    inventory: Vec<String>,
+    #[serde(default)]
+    details: CharacterDetails,
 }
 
 impl PlayerCharacter {
@@ -55,4 +310,286 @@ impl PlayerCharacter {
     pub fn class(&self) -> &str {
         &self.class
     }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn xp(&self) -> u32 {
+        self.xp
+    }
+
+    pub fn hp(&self) -> u32 {
+        self.hp
+    }
+
+    pub fn max_hp(&self) -> u32 {
+        self.max_hp
+    }
+
+    pub fn abilities(&self) -> &AbilityScores {
+        &self.abilities
+    }
+
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    pub fn inventory(&self) -> &[String] {
+        &self.inventory
+    }
+
+    pub fn details(&self) -> &CharacterDetails {
+        &self.details
+    }
+
+    pub fn award_xp(&mut self, amount: u32) {
+        self.xp = self.xp.saturating_add(amount);
+    }
+
+    /// Applies damage (negative) or healing (positive), keeping hit points
+    /// between zero and the maximum.
+    pub fn change_hp(&mut self, change: i32) {
+        self.hp = (self.hp as i64 + change as i64).clamp(0, self.max_hp as i64) as u32;
+    }
+
+    pub fn add_item(&mut self, item: String) {
+        self.inventory.push(item);
+    }
+
+    /// Removes the first item matching `item`, ignoring case. Returns `false` if
+    /// the character isn't carrying it.
+    pub fn remove_item(&mut self, item: &str) -> bool {
+        match self
+            .inventory
+            .iter()
+            .position(|i| i.eq_ignore_ascii_case(item.trim()))
+        {
+            Some(index) => {
+                self.inventory.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of levels the character has earned but not yet taken.
+    pub fn pending_level_ups(&self, table: &LevelTable) -> u32 {
+        table.level_for(self.xp).saturating_sub(self.level)
+    }
+
+    /// Takes one pending level, applying the chosen improvement. Returns `false`
+    /// if no level is pending.
+    pub fn level_up(&mut self, improvement: &Improvement, table: &LevelTable) -> bool {
+        if self.pending_level_ups(table) == 0 {
+            return false;
+        }
+        self.level += 1;
+        match improvement {
+            Improvement::HitPoints => {
+                self.max_hp += table.hp_per_level();
+                self.hp += table.hp_per_level();
+            }
+            Improvement::AbilityBump(ability) => {
+                let score = self.abilities.get_mut(*ability);
+                *score = score
+                    .saturating_add(table.ability_bump())
+                    .min(AbilityScores::MAX);
+            }
+            Improvement::Feature(feature) => self.features.push(feature.clone()),
+        }
+        true
+    }
+
+    /// Checks a character built elsewhere, e.g. by a remote player, against
+    /// `catalog` and `table`: its race and class must exist, and its level,
+    /// hit points, abilities and features must be within what playing from
+    /// level 1 could give it. Items come from the GM, so any are allowed.
+    pub fn validate(&self, catalog: &Catalog, table: &LevelTable) -> Result<(), CharacterError> {
+        if self.name.trim().is_empty() {
+            return Err(CharacterError::EmptyName);
+        }
+        let race = catalog
+            .race(&self.race)
+            .ok_or_else(|| CharacterError::UnknownRace(self.race.clone()))?;
+        let class = catalog
+            .class(&self.class)
+            .ok_or_else(|| CharacterError::UnknownClass(self.class.clone()))?;
+        let implausible = |what: String| {
+            Err(CharacterError::Implausible(format!(
+                "{} {} isn't possible",
+                self.name, what
+            )))
+        };
+
+        if self.level == 0 || self.level > table.level_for(self.xp) {
+            return implausible(format!("at level {} with {} XP", self.level, self.xp));
+        }
+        let levels = self.level - 1;
+        let max_hp = class.starting_hp + levels * table.hp_per_level();
+        if self.max_hp > max_hp || self.hp > self.max_hp {
+            return implausible(format!(
+                "with {}/{} HP at level {}",
+                self.hp, self.max_hp, self.level
+            ));
+        }
+        for ability in Ability::ALL {
+            let adjustment = race.ability_adjustments.get(&ability).copied().unwrap_or(0);
+            let bumps = (levels * table.ability_bump() as u32).min(AbilityScores::MAX as u32);
+            let max = (AbilityScores::ROLLED_MAX as i32 + adjustment as i32 + bumps as i32)
+                .clamp(1, AbilityScores::MAX as i32) as u8;
+            let score = self.abilities.get(ability);
+            if score == 0 || score > max {
+                return implausible(format!(
+                    "with {} {} at level {}",
+                    ability, score, self.level
+                ));
+            }
+        }
+        if self.features.len() > levels as usize {
+            return implausible(format!(
+                "with {} features at level {}",
+                self.features.len(),
+                self.level
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_characters_repeat_for_a_seed() {
+        let catalog = Catalog::bundled();
+        let build = |seed| {
+            PlayerCharacterBuilder::random(seed, &catalog)
+                .build(&catalog)
+                .unwrap()
+        };
+        for seed in [0, 1, 42, u64::MAX] {
+            assert_eq!(build(seed), build(seed));
+        }
+        // Not a fixed character whatever the seed.
+        assert!((0..10).any(|seed| build(seed) != build(seed + 10)));
+    }
+
+    #[test]
+    fn xp_can_earn_several_levels_taken_one_at_a_time() {
+        let catalog = Catalog::bundled();
+        let table = LevelTable::default();
+        let mut ann = PlayerCharacterBuilder::new("Ann".to_owned())
+            .build(&catalog)
+            .unwrap();
+        assert_eq!(ann.pending_level_ups(&table), 0);
+        assert!(!ann.level_up(&Improvement::HitPoints, &table));
+        assert_eq!(ann.level, 1);
+
+        ann.award_xp(299);
+        assert_eq!(ann.pending_level_ups(&table), 0);
+        ann.award_xp(2700 - 299);
+        assert_eq!(ann.pending_level_ups(&table), 3);
+
+        let (hp, max_hp) = (ann.hp, ann.max_hp);
+        assert!(ann.level_up(&Improvement::HitPoints, &table));
+        assert_eq!(ann.max_hp, max_hp + table.hp_per_level());
+        assert_eq!(ann.hp, hp + table.hp_per_level());
+        assert!(ann.level_up(&Improvement::Feature("Sneak".to_owned()), &table));
+        assert_eq!(ann.features, ["Sneak"]);
+        assert!(ann.level_up(&Improvement::HitPoints, &table));
+        assert_eq!(ann.level, 4);
+        assert_eq!(ann.pending_level_ups(&table), 0);
+        assert!(!ann.level_up(&Improvement::HitPoints, &table));
+        assert_eq!(ann.level, 4);
+    }
+
+    #[test]
+    fn ability_bumps_stop_at_the_maximum() {
+        let catalog = Catalog::bundled();
+        let table = LevelTable::default();
+        let mut ann = PlayerCharacterBuilder::new("Ann".to_owned())
+            .with_abilities(AbilityScores {
+                strength: 17,
+                ..AbilityScores::default()
+            })
+            .build(&catalog)
+            .unwrap();
+        let strength = ann.abilities.get(Ability::Strength);
+        ann.award_xp(6500);
+        let bump = Improvement::AbilityBump(Ability::Strength);
+        assert!(ann.level_up(&bump, &table));
+        assert_eq!(
+            ann.abilities.get(Ability::Strength),
+            (strength + table.ability_bump()).min(AbilityScores::MAX)
+        );
+        assert!(ann.level_up(&bump, &table));
+        assert!(ann.level_up(&bump, &table));
+        assert_eq!(ann.abilities.get(Ability::Strength), AbilityScores::MAX);
+        // The level is still taken.
+        assert_eq!(ann.level, 4);
+    }
+
+    #[test]
+    fn validate_refuses_stats_play_could_not_give() {
+        let catalog = Catalog::bundled();
+        let table = LevelTable::default();
+        let ann = PlayerCharacterBuilder::new("Ann".to_owned())
+            .with_race("Elf".to_owned())
+            .with_class("Ranger".to_owned())
+            .build(&catalog)
+            .unwrap();
+        assert!(ann.validate(&catalog, &table).is_ok());
+
+        let mut veteran = ann.clone();
+        veteran.award_xp(900);
+        assert!(veteran.level_up(&Improvement::HitPoints, &table));
+        assert!(veteran.level_up(&Improvement::AbilityBump(Ability::Wisdom), &table));
+        assert!(veteran.validate(&catalog, &table).is_ok());
+
+        let implausible = [
+            PlayerCharacter {
+                level: 5,
+                ..ann.clone()
+            },
+            PlayerCharacter {
+                max_hp: ann.max_hp + 1,
+                ..ann.clone()
+            },
+            PlayerCharacter {
+                hp: ann.max_hp + 1,
+                ..ann.clone()
+            },
+            PlayerCharacter {
+                abilities: AbilityScores {
+                    strength: AbilityScores::MAX,
+                    ..ann.abilities.clone()
+                },
+                ..ann.clone()
+            },
+            PlayerCharacter {
+                features: vec!["Flight".to_owned()],
+                ..ann.clone()
+            },
+        ];
+        for pc in implausible {
+            assert!(
+                matches!(
+                    pc.validate(&catalog, &table),
+                    Err(CharacterError::Implausible(_))
+                ),
+                "{:?} should be refused",
+                pc
+            );
+        }
+        let unknown = PlayerCharacter {
+            race: "Dragon".to_owned(),
+            ..ann
+        };
+        assert!(matches!(
+            unknown.validate(&catalog, &table),
+            Err(CharacterError::UnknownRace(_))
+        ));
+    }
 }
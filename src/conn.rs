@@ -1,47 +1,133 @@
 use async_openai::{
     config::OpenAIConfig,
+    error::OpenAIError,
     types::{
         AssistantsApiResponseFormatOption, CreateAssistantRequestArgs, CreateMessageRequestArgs,
-        CreateRunRequestArgs, CreateThreadRequestArgs, MessageContent, MessageRole,
-        ModifyAssistantRequestArgs, ResponseFormat, ResponseFormatJsonSchema, RunStatus,
+        CreateRunRequest, CreateRunRequestArgs, CreateThreadRequestArgs, MessageContent,
+        MessageRole, ModifyAssistantRequestArgs, ResponseFormat, ResponseFormatJsonSchema,
+        RunStatus,
     },
     Client,
 };
 
-use log::{debug, error, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-use schemars::{schema_for, JsonSchema};
+use log::{debug, error, info, warn};
+use tokio::sync::Notify;
 
-use crate::game::GameError;
-use crate::schema::{AIInput, AIOutput};
+use schemars::schema_for;
+
+use crate::catalog::Catalog;
+use crate::game::{GameError, Verbosity};
+use crate::schema::{AIInput, AIOutput, AdventureSettings, ContentRating, Difficulty, Genre, Tone};
+
+/// Where the GM runs and how, as set on a `GameBuilder`.
+#[derive(Debug, Clone)]
+pub struct GmConfig {
+    pub api_key: String,
+    /// An OpenAI-compatible API base URL, instead of OpenAI's.
+    pub endpoint: Option<String>,
+    /// The model to run the GM on, instead of the assistant's.
+    pub model: Option<String>,
+    pub verbosity: Verbosity,
+    pub adventure: AdventureSettings,
+}
+
+/// Lets the run the GM is working on be cancelled from outside the game.
+#[derive(Debug, Default)]
+pub struct Cancel {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl Cancel {
+    /// Cancels the current run, or the next one if the GM isn't running yet.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    /// Forgets any request, e.g. one made after the run it was meant for.
+    pub fn reset(&self) {
+        self.requested.store(false, Ordering::SeqCst);
+    }
+
+    fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Completes once cancellation is requested.
+    async fn requested(&self) {
+        let notified = self.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if self.is_requested() {
+            return;
+        }
+        notified.await;
+    }
+}
 
 pub struct Connection {
     client: Client<OpenAIConfig>,
     assistant_id: String,
     thread_id: String,
+    model: Option<String>,
+    verbosity: Verbosity,
+    adventure: AdventureSettings,
+    cancel: Arc<Cancel>,
 }
 
 impl Connection {
-    pub async fn new(_api_key: String) -> Result<Self, Box<dyn std::error::Error>> {
-        let client = Client::new();
-        let assistant_id = Self::get_assistant(&client).await?;
-        let thread_request = CreateThreadRequestArgs::default().build()?;
-        let thread = client.threads().create(thread_request.clone()).await?;
-        let thread_id = thread.id.clone();
+    /// Connects to the GM on `thread_id`, or on a new thread if `None`.
+    pub async fn new(
+        config: GmConfig,
+        catalog: &Catalog,
+        thread_id: Option<String>,
+    ) -> Result<Self, OpenAIError> {
+        let mut openai_config = OpenAIConfig::new().with_api_key(config.api_key);
+        if let Some(endpoint) = config.endpoint {
+            openai_config = openai_config.with_api_base(endpoint);
+        }
+        let client = Client::with_config(openai_config);
+        let assistant_id = Self::get_assistant(&client, catalog).await?;
+        let thread_id = match thread_id {
+            // Fails early if the thread was deleted or belongs to another key.
+            Some(thread_id) => client.threads().retrieve(&thread_id).await?.id,
+            None => {
+                let thread_request = CreateThreadRequestArgs::default().build()?;
+                client.threads().create(thread_request).await?.id
+            }
+        };
         Ok(Self {
             client,
             assistant_id,
             thread_id,
+            model: config.model,
+            verbosity: config.verbosity,
+            adventure: config.adventure,
+            cancel: Arc::default(),
         })
     }
 
+    pub fn thread_id(&self) -> &str {
+        &self.thread_id
+    }
+
+    /// Cancels this connection's runs; see `run`.
+    pub fn cancel(&self) -> &Arc<Cancel> {
+        &self.cancel
+    }
+
     async fn get_assistant(
         client: &Client<OpenAIConfig>,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+        catalog: &Catalog,
+    ) -> Result<String, OpenAIError> {
         let list_assistant_query: [usize; 0] = [];
         let assistants = client.assistants();
         let assistant_list = assistants.list(&list_assistant_query).await?;
-        let instructions = Self::get_assistant_instructions();
+        let instructions = Self::get_assistant_instructions(catalog)?;
         if let Some(assistant) = assistant_list
             .data
             .iter()
@@ -58,7 +144,7 @@ impl Connection {
                             .model(AI_MODEL)
                             .instructions(instructions)
                             .response_format(AssistantsApiResponseFormatOption::Format(
-                                Self::get_assistant_response_format(),
+                                Self::get_assistant_response_format()?,
                             ))
                             .build()?,
                     )
@@ -74,7 +160,7 @@ impl Connection {
                         .model(AI_MODEL)
                         .instructions(instructions)
                         .response_format(AssistantsApiResponseFormatOption::Format(
-                            Self::get_assistant_response_format(),
+                            Self::get_assistant_response_format()?,
                         ))
                         .build()?,
                 )
@@ -83,96 +169,253 @@ impl Connection {
         }
     }
 
-    fn get_assistant_instructions() -> String {
-        let schema = schema_for!(AIInput);
-        let schema_value = serde_json::to_string(&schema).unwrap();
+    fn get_assistant_instructions(catalog: &Catalog) -> Result<String, OpenAIError> {
+        let mut schema = schema_for!(AIInput);
+        catalog.annotate_schema(&mut schema);
+        let schema_value = serde_json::to_string(&schema).map_err(schema_error)?;
         let mut inst = AI_INST.to_owned();
         inst.push_str(&schema_value);
         inst.push_str(AI_INST_PROLOGUE);
-        return inst;
+        Ok(inst)
     }
 
-    fn get_assistant_response_format() -> ResponseFormat {
-        let schema = schema_for!(AIOutput);
-        let schema_value = serde_json::to_value(&schema).unwrap();
-        debug!(
-            "Schema:\n{}",
-            serde_json::to_string_pretty(&schema).unwrap()
+    /// Reminds the GM of the adventure the players chose on every run, since
+    /// the Start command that carries the settings may be far back in the
+    /// thread.
+    fn adventure_instructions(adventure: &AdventureSettings) -> String {
+        let genre = match adventure.genre {
+            Genre::Fantasy => "a fantasy adventure of swords and sorcery",
+            Genre::SciFi => "a science fiction adventure of starships and strange worlds",
+            Genre::Noir => "a noir mystery of crime, corruption and rain-slicked streets",
+            Genre::Horror => "a horror story of dread and things best left unknown",
+            Genre::Western => "a western on the lawless frontier",
+        };
+        let tone = match adventure.tone {
+            Tone::Lighthearted => "Keep the tone lighthearted and playful.",
+            Tone::Heroic => "Keep the tone heroic, with bold deeds and clear stakes.",
+            Tone::Gritty => "Keep the tone gritty: choices are hard and victories cost something.",
+            Tone::Grim => "Keep the tone grim and unforgiving, with little hope to spare.",
+        };
+        let difficulty = match adventure.difficulty {
+            Difficulty::Easy => "Make challenges forgiving and keep damage light.",
+            Difficulty::Normal => "Make challenges fair, rewarding good ideas.",
+            Difficulty::Hard => "Make challenges tough, punishing careless play.",
+            Difficulty::Deadly => "Make challenges deadly: careless characters may die.",
+        };
+        let rating = match adventure.content_rating {
+            ContentRating::Family => {
+                "Keep all content suitable for children, with no gore or strong language."
+            }
+            ContentRating::Teen => {
+                "Violence and peril are fine but never graphic, and language stays mild."
+            }
+            ContentRating::Mature => {
+                "Graphic violence, strong language and disturbing themes are allowed."
+            }
+        };
+        let mut instructions = format!(
+            "This quest is {}. Reinterpret the characters' races, classes and items to fit it \
+             where needed. {} {} The quest should take about {} scenes to complete. {}",
+            genre,
+            tone,
+            difficulty,
+            adventure.length.scenes(),
+            rating
         );
-        ResponseFormat::JsonSchema {
+        if !adventure.lines.is_empty() {
+            instructions.push_str(&format!(
+                " The players have drawn lines that must never appear in the story, not even \
+                 mentioned: {}.",
+                adventure.lines.join("; ")
+            ));
+        }
+        if !adventure.veils.is_empty() {
+            instructions.push_str(&format!(
+                " The players have veiled these subjects, which may only happen off-screen: fade \
+                 to black rather than describe them: {}.",
+                adventure.veils.join("; ")
+            ));
+        }
+        instructions
+    }
+
+    fn get_assistant_response_format() -> Result<ResponseFormat, OpenAIError> {
+        let schema = schema_for!(AIOutput);
+        let schema_value = serde_json::to_value(&schema).map_err(schema_error)?;
+        debug!("Schema:\n{:#}", schema_value);
+        Ok(ResponseFormat::JsonSchema {
             json_schema: ResponseFormatJsonSchema {
                 description: Some(AI_RESPONSE_DESC.to_owned()),
                 name: "quest".to_owned(),
                 schema: Some(schema_value),
                 strict: Some(false),
             },
-        }
+        })
     }
 
+    /// Adds `command` to the thread and runs the GM on it.
     pub async fn send(&self, command: AIInput) -> Result<AIOutput, GameError> {
+        self.add_message(command).await?;
+        self.run().await.map(|reply| reply.output)
+    }
+
+    /// Adds `command` to the thread without running the GM, returning the
+    /// message id.
+    pub async fn add_message(&self, command: AIInput) -> Result<String, GameError> {
         debug!("Sending: {:?}", &command);
-        let command_str = serde_json::to_string(&command).unwrap();
+        let command_str = serde_json::to_string(&command)
+            .map_err(|err| GameError::send_failed("Could not encode message", err))?;
         let message = CreateMessageRequestArgs::default()
             .role(MessageRole::User)
             .content(command_str)
             .build()
-            .map_err(|_| GameError::SendFailed("Could not build message".to_owned()))?;
+            .map_err(|err| GameError::send_failed("Could not build message", err))?;
 
-        let _ = self
+        let message = self
             .client
             .threads()
             .messages(&self.thread_id)
             .create(message)
             .await
-            .map_err(|_| GameError::SendFailed("Could not add message to thread".to_owned()))?;
+            .map_err(|err| GameError::send_failed("Could not add message to thread", err))?;
+        Ok(message.id)
+    }
+
+    /// A run of the assistant with this connection's model, verbosity and
+    /// adventure settings.
+    fn run_request(&self) -> Result<CreateRunRequest, GameError> {
+        let mut request = CreateRunRequestArgs::default();
+        request.assistant_id(&self.assistant_id);
+        if let Some(model) = &self.model {
+            request.model(model);
+        }
+        let mut instructions = Self::adventure_instructions(&self.adventure);
+        match self.verbosity {
+            Verbosity::Brief => instructions.push_str(AI_INST_BRIEF),
+            Verbosity::Standard => {}
+            Verbosity::Detailed => instructions.push_str(AI_INST_DETAILED),
+        }
+        request.additional_instructions(instructions);
+        request
+            .build()
+            .map_err(|err| GameError::send_failed("Could not build run request", err))
+    }
+
+    /// Runs the GM on the thread as it stands and parses its response. If
+    /// cancelled, the run is stopped and anything it added to the thread is
+    /// deleted before returning `GameError::Cancelled`.
+    pub async fn run(&self) -> Result<Reply, GameError> {
+        if self.cancel.is_requested() {
+            return Err(GameError::Cancelled);
+        }
         let run = self
             .client
             .threads()
             .runs(&self.thread_id)
-            .create(
-                CreateRunRequestArgs::default()
-                    .assistant_id(&self.assistant_id)
-                    .build()
-                    .map_err(|_| GameError::SendFailed("Could not build run request".to_owned()))?,
-            )
+            .create(self.run_request()?)
             .await
-            .map_err(|_| GameError::SendFailed("Could not create run".to_owned()))?;
+            .map_err(|err| GameError::send_failed("Could not create run", err))?;
 
+        tokio::select! {
+            reply = self.wait_for_run(&run.id) => reply,
+            _ = self.cancel.requested() => {
+                self.cancel_run(&run.id).await;
+                Err(GameError::Cancelled)
+            }
+        }
+    }
+
+    /// Stops a run and deletes its messages, so the thread is ready for the
+    /// next one. Failures are logged, since the turn is over either way.
+    async fn cancel_run(&self, run_id: &str) {
+        info!("Cancelling run {}", run_id);
+        let threads = self.client.threads();
+        let runs = threads.runs(&self.thread_id);
+        if let Err(err) = runs.cancel(run_id).await {
+            // Most likely the run finished first.
+            warn!("Could not cancel run: {}", err);
+        }
+        for _ in 0..CANCEL_POLLS {
+            match runs.retrieve(run_id).await.map(|run| run.status) {
+                Ok(RunStatus::Queued | RunStatus::InProgress | RunStatus::Cancelling) => {
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await
+                }
+                Ok(_) => break,
+                Err(err) => {
+                    error!("Could not query cancelled run: {}", err);
+                    return;
+                }
+            }
+        }
+        let query = [("run_id", run_id)];
+        let messages = self
+            .client
+            .threads()
+            .messages(&self.thread_id)
+            .list(&query)
+            .await;
+        match messages {
+            Ok(messages) => {
+                let mut ids: Vec<_> = messages
+                    .data
+                    .into_iter()
+                    .map(|message| message.id)
+                    .collect();
+                if let Err(err) = self.delete_messages(&mut ids).await {
+                    error!("Could not delete cancelled reply: {}", err);
+                }
+            }
+            Err(err) => error!("Could not list cancelled reply: {}", err),
+        }
+    }
+
+    async fn wait_for_run(&self, run_id: &str) -> Result<Reply, GameError> {
         loop {
             let run = self
                 .client
                 .threads()
                 .runs(&self.thread_id)
-                .retrieve(&run.id)
+                .retrieve(run_id)
                 .await
-                .map_err(|_| GameError::SendFailed("Could not query run status".to_owned()))?;
+                .map_err(|err| GameError::send_failed("Could not query run status", err))?;
 
             match run.status {
                 RunStatus::Completed => {
-                    let query = [("limit", "1")];
+                    let query = [("run_id", run.id.as_str())];
                     let response = self
                         .client
                         .threads()
                         .messages(&self.thread_id)
                         .list(&query)
                         .await
-                        .map_err(|_| {
-                            GameError::SendFailed("Could not retrieve response".to_owned())
+                        .map_err(|err| {
+                            GameError::send_failed("Could not retrieve response", err)
                         })?;
-                    let message_id = response.data.first().unwrap().id.clone();
+                    // Listed newest first.
+                    let message_ids: Vec<String> = response
+                        .data
+                        .iter()
+                        .rev()
+                        .map(|message| message.id.clone())
+                        .collect();
+                    let message_id = message_ids.last().ok_or_else(|| GameError::SendFailed {
+                        message: "No messages in response".to_owned(),
+                        source: None,
+                    })?;
                     let message = self
                         .client
                         .threads()
                         .messages(&self.thread_id)
-                        .retrieve(&message_id)
+                        .retrieve(message_id)
                         .await
-                        .map_err(|_| {
-                            GameError::SendFailed("Could not retrieve message".to_owned())
-                        })?;
+                        .map_err(|err| GameError::send_failed("Could not retrieve message", err))?;
                     let content = message
                         .content
                         .first()
-                        .ok_or(GameError::SendFailed("No messages in response".to_owned()))?;
+                        .ok_or_else(|| GameError::SendFailed {
+                            message: "Empty message in response".to_owned(),
+                            source: None,
+                        })?;
 
                     debug!("Received: {:?}", &content);
 
@@ -190,22 +433,121 @@ impl Connection {
                     };
 
                     if let Err(ref error) = output {
-                        error!("{:?}", error);
+                        error!("{}", error);
                     }
 
-                    return output;
+                    return output.map(|output| Reply {
+                        output,
+                        message_ids,
+                    });
+                }
+                RunStatus::Failed => {
+                    return Err(GameError::SendFailed {
+                        message: "Run failed".to_owned(),
+                        source: None,
+                    })
                 }
-                RunStatus::Failed => return Err(GameError::SendFailed("Run failed".to_owned())),
                 _ => (),
             }
 
             tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
     }
+
+    /// Starts a new thread holding copies of `message_ids`, for a game that
+    /// carries on from that point independently. Returns the new connection
+    /// and the copies' ids, in the same order.
+    pub async fn branch(&self, message_ids: &[String]) -> Result<(Self, Vec<String>), GameError> {
+        let thread_request = CreateThreadRequestArgs::default()
+            .build()
+            .map_err(|err| GameError::send_failed("Could not build thread request", err))?;
+        let thread = self
+            .client
+            .threads()
+            .create(thread_request)
+            .await
+            .map_err(|err| GameError::send_failed("Could not create thread", err))?;
+        let branch = Self {
+            client: self.client.clone(),
+            assistant_id: self.assistant_id.clone(),
+            thread_id: thread.id,
+            model: self.model.clone(),
+            verbosity: self.verbosity,
+            adventure: self.adventure.clone(),
+            cancel: Arc::default(),
+        };
+
+        let mut copies = Vec::with_capacity(message_ids.len());
+        for message_id in message_ids {
+            let message = self
+                .client
+                .threads()
+                .messages(&self.thread_id)
+                .retrieve(message_id)
+                .await
+                .map_err(|err| GameError::send_failed("Could not retrieve message", err))?;
+            let content: Vec<_> = message
+                .content
+                .iter()
+                .filter_map(|content| match content {
+                    MessageContent::Text(text) => Some(text.text.value.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let copy = CreateMessageRequestArgs::default()
+                .role(message.role)
+                .content(content.join("\n"))
+                .build()
+                .map_err(|err| GameError::send_failed("Could not build message", err))?;
+            let copy = branch
+                .client
+                .threads()
+                .messages(&branch.thread_id)
+                .create(copy)
+                .await
+                .map_err(|err| GameError::send_failed("Could not copy message", err))?;
+            copies.push(copy.id);
+        }
+        Ok((branch, copies))
+    }
+
+    /// Deletes messages from the thread, so the GM no longer sees them. The
+    /// last is deleted first, and each is removed from `message_ids` once it's
+    /// gone, so after a failure only those still in the thread are left.
+    pub async fn delete_messages(&self, message_ids: &mut Vec<String>) -> Result<(), GameError> {
+        while let Some(message_id) = message_ids.last() {
+            self.client
+                .threads()
+                .messages(&self.thread_id)
+                .delete(message_id)
+                .await
+                .map_err(|err| GameError::send_failed("Could not delete message", err))?;
+            message_ids.pop();
+        }
+        Ok(())
+    }
+}
+
+/// The GM's response to a run, with the ids of the messages it added, oldest
+/// first.
+pub struct Reply {
+    pub output: AIOutput,
+    pub message_ids: Vec<String>,
+}
+
+/// A schema for the GM that can't be written as JSON, which can only happen
+/// if the schema types are broken.
+fn schema_error(err: serde_json::Error) -> OpenAIError {
+    OpenAIError::InvalidArgument(format!("Could not encode schema: {}", err))
 }
 
-const AI_NAME: &'static str = "uQuest GM";
-const AI_MODEL: &'static str = "gpt-4o";
-const AI_RESPONSE_DESC: &'static str = "A series of updates to the game state, including text to be output to the user. A QuestDefinition is sent as a response to a Start message.";
-const AI_INST: &'static str = "You are the game master for a text-based adventure game. You will run a session containing a simple quest for a single player character. You must not take any actions on behalf of the player character, the player character has full control over what they do. Suggest some possible actions to the user in each description. You will receive commands in JSON format according to the following schema:\n\n";
-const AI_INST_PROLOGUE: &'static str = "\n\nYou may respond to a command with multiple different 'updates'. Only the Description update will be presented to the user, so any description or dialogue intended for the user must be in a Description update.";
+/// How many seconds to wait for a cancelled run to stop.
+const CANCEL_POLLS: usize = 30;
+const AI_NAME: &str = "uQuest GM";
+const AI_MODEL: &str = "gpt-4o";
+const AI_RESPONSE_DESC: &str = "A series of updates to the game state, including text to be output to the user. A QuestDefinition is sent as a response to a Start message.";
+const AI_INST: &str = "You are the game master for a text-based adventure game. You will run a session containing a simple quest, in the genre and style given by the settings in the Start command, for a party of one or more player characters. You must not take any actions on behalf of the player characters, each player character has full control over what they do. Each UserInput lists the actions taken by one or more characters during that turn, naming the character taking each action. Each UserInput also says how far through the quest the party is, so pace the story to reach its climax and end around the target number of scenes. Suggest some possible actions to the user in each description. You will receive commands in JSON format according to the following schema:\n\n";
+const AI_INST_PROLOGUE: &str = "\n\nYou may respond to a command with multiple different 'updates'. Only the Description update will be presented to the user, so any description or dialogue intended for the user must be in a Description update. Use an AwardXp update to grant experience points when a player character overcomes a challenge, solves a problem or completes an objective. Use HitPoints updates when a player character is hurt (negative change) or healed (positive change), and ItemGained or ItemLost updates when their inventory changes. These updates name the character they apply to; AwardXp without a character applies to the whole party. When you receive a LevelUp command, briefly acknowledge the character's growth in a Description. When you receive a DraftBackstory command, respond only with a Backstory update that drafts the character's backstory, personality traits, ideals, flaws and appearance, fitting their race and class and building on any details already given. Keep each field to a few sentences. Description text is shown to the players as Markdown, so you may use headings, **bold**, *italic* and ~~strikethrough~~ text, `inline code`, bulleted and numbered lists and links to web pages. Tables, images, block quotes and HTML are not supported and must not be used.";
+const AI_INST_BRIEF: &str =
+    " Keep each Description brief: two or three sentences, with only the most important details.";
+const AI_INST_DETAILED: &str = " Make each Description rich and detailed: several paragraphs of vivid description, atmosphere and dialogue.";
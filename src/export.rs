@@ -0,0 +1,669 @@
+//! Turns a game into a storybook to read after the quest: the quest, a card
+//! for each character, then the story with the GM's narration as prose and the
+//! party's actions set off as dialogue. Unsent actions are left out.
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pulldown_cmark::{Event, Options, Parser};
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::character::{Ability, PlayerCharacter};
+use crate::game::{GamePlayer, GameState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[serde(alias = "md")]
+    Markdown,
+    Html,
+    Epub,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+        ExportFormat::Epub,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Epub => "epub",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Html => "text/html; charset=utf-8",
+            ExportFormat::Epub => "application/epub+zip",
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Html => "HTML",
+            ExportFormat::Epub => "EPUB",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "html" => Ok(ExportFormat::Html),
+            "epub" => Ok(ExportFormat::Epub),
+            _ => Err(ExportError::UnknownFormat(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ExportError {
+    UnknownFormat(String),
+    NoDirectory,
+    Io(String),
+    Epub(String),
+    /// The game stopped part way through changing its state.
+    StateUnavailable,
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::UnknownFormat(format) => write!(
+                f,
+                "Unknown export format \"{}\", expected md, html or epub",
+                format
+            ),
+            ExportError::NoDirectory => write!(f, "No directory available to export to"),
+            ExportError::Io(msg) => write!(f, "Export I/O error: {}", msg),
+            ExportError::Epub(msg) => write!(f, "Could not build EPUB: {}", msg),
+            ExportError::StateUnavailable => {
+                write!(f, "The game crashed, so its story can't be read")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// Renders `state` as a document in `format`.
+pub fn export(state: &GameState, format: ExportFormat) -> Result<Vec<u8>, ExportError> {
+    match format {
+        ExportFormat::Markdown => Ok(to_markdown(state).into_bytes()),
+        ExportFormat::Html => Ok(to_html(state).into_bytes()),
+        ExportFormat::Epub => to_epub(state),
+    }
+}
+
+/// Writes `state` to a new file in the documents directory, named as in
+/// `default_path` but numbered `-2`, `-3` and so on rather than overwriting an
+/// earlier export. Returns where it went.
+pub fn export_to_file(state: &GameState, format: ExportFormat) -> Result<PathBuf, ExportError> {
+    let path = default_path(state, format).ok_or(ExportError::NoDirectory)?;
+    write_new(&path, &export(state, format)?)
+}
+
+/// Writes `document` to `path`, or if that exists to the first of `path`
+/// numbered `-2`, `-3` and so on that doesn't.
+fn write_new(path: &Path, document: &[u8]) -> Result<PathBuf, ExportError> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let mut n = 1;
+    loop {
+        let path = if n == 1 {
+            path.to_owned()
+        } else {
+            path.with_file_name(format!("{}-{}.{}", stem, n, extension))
+        };
+        n += 1;
+        // Only creates the file if it's new, so a file made in the meantime
+        // isn't overwritten either.
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                file.write_all(document)
+                    .map_err(|err| ExportError::Io(err.to_string()))?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(ExportError::Io(err.to_string())),
+        }
+    }
+}
+
+/// A file named after the quest in the documents directory, or the home
+/// directory on platforms without one.
+pub fn default_path(state: &GameState, format: ExportFormat) -> Option<PathBuf> {
+    let dir = dirs::document_dir().or_else(dirs::home_dir)?;
+    Some(dir.join(format!("{}.{}", slug(state), format.extension())))
+}
+
+/// The quest's title in lowercase with dashes between words, or "quest" if
+/// it has none that fit in a file name.
+fn slug(state: &GameState) -> String {
+    let slug: String = title(state)
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "quest".to_owned()
+    } else {
+        slug
+    }
+}
+
+pub fn to_markdown(state: &GameState) -> String {
+    let quest = &state.quest;
+    let mut md = format!("# {}\n\n", title(state));
+    if !quest.description.is_empty() {
+        let _ = write!(md, "{}\n\n", quest.description);
+    }
+    if !quest.objective_summary.is_empty() {
+        let _ = write!(md, "**Objective:** {}\n\n", quest.objective_summary);
+    }
+
+    md.push_str("## The party\n\n");
+    for pc in state.party.iter() {
+        let _ = write!(md, "### {}\n\n*{}*\n\n", pc.name(), summary(pc));
+        let abilities = Ability::ALL.map(|ability| ability.abbreviation());
+        let scores = Ability::ALL.map(|ability| pc.abilities().get(ability).to_string());
+        let _ = write!(
+            md,
+            "| {} |\n|{}\n| {} |\n\n",
+            abilities.join(" | "),
+            "---|".repeat(abilities.len()),
+            scores.join(" | ")
+        );
+        let _ = write!(
+            md,
+            "**HP** {} / {}, **XP** {}\n\n",
+            pc.hp(),
+            pc.max_hp(),
+            pc.xp()
+        );
+        if !pc.features().is_empty() {
+            let _ = write!(md, "**Features:** {}\n\n", pc.features().join(", "));
+        }
+        if !pc.inventory().is_empty() {
+            let _ = write!(md, "**Inventory:** {}\n\n", pc.inventory().join(", "));
+        }
+        for (label, text) in details(pc) {
+            let _ = write!(md, "**{}:** {}\n\n", label, text);
+        }
+    }
+
+    md.push_str("## The adventure\n\n");
+    for entry in state.log.iter().filter(|entry| entry.in_story()) {
+        match entry.player {
+            GamePlayer::GM => {
+                let _ = write!(md, "{}\n\n", entry.content.trim());
+            }
+            GamePlayer::PC(id) => {
+                let quoted = entry.content.trim().replace('\n', "\n> ");
+                let _ = write!(md, "> **{}:** {}\n\n", speaker(state, id), quoted);
+            }
+            GamePlayer::System => {
+                let _ = write!(md, "*{}*\n\n", entry.content.trim());
+            }
+        }
+    }
+    md
+}
+
+/// A standalone HTML page, with its styling inline.
+pub fn to_html(state: &GameState) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(&title(state)),
+        STYLE,
+        body(state)
+    )
+}
+
+/// An EPUB 3 book with the story as a single chapter.
+pub fn to_epub(state: &GameState) -> Result<Vec<u8>, ExportError> {
+    let title = escape(&title(state));
+    let story = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" lang=\"en\" xml:lang=\"en\">\n<head>\n\
+         <meta charset=\"utf-8\"/>\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n<body>\n{}</body>\n</html>\n",
+        title,
+        body(state)
+    );
+    let nav = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"en\" xml:lang=\"en\">\n\
+         <head>\n<meta charset=\"utf-8\"/>\n<title>{}</title>\n</head>\n<body>\n\
+         <nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n<ol>\n\
+         <li><a href=\"story.xhtml#party\">The party</a></li>\n\
+         <li><a href=\"story.xhtml#adventure\">The adventure</a></li>\n\
+         </ol>\n</nav>\n</body>\n</html>\n",
+        title
+    );
+    let package = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"id\">\n\
+         <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+         <dc:identifier id=\"id\">urn:uuid:{}</dc:identifier>\n\
+         <dc:title>{}</dc:title>\n<dc:language>en</dc:language>\n\
+         <meta property=\"dcterms:modified\">{}</meta>\n</metadata>\n\
+         <manifest>\n\
+         <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+         <item id=\"story\" href=\"story.xhtml\" media-type=\"application/xhtml+xml\"/>\n\
+         <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n\
+         </manifest>\n<spine>\n<itemref idref=\"story\"/>\n</spine>\n</package>\n",
+        uuid(),
+        title,
+        timestamp(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs())
+        )
+    );
+
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let epub_err = |err: zip::result::ZipError| ExportError::Epub(err.to_string());
+    let io_err = |err: std::io::Error| ExportError::Epub(err.to_string());
+    // Readers identify the file by an uncompressed `mimetype` that comes first.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("mimetype", stored).map_err(epub_err)?;
+    zip.write_all(b"application/epub+zip").map_err(io_err)?;
+    for (name, content) in [
+        ("META-INF/container.xml", CONTAINER),
+        ("OEBPS/content.opf", &package),
+        ("OEBPS/nav.xhtml", &nav),
+        ("OEBPS/story.xhtml", &story),
+        ("OEBPS/style.css", STYLE),
+    ] {
+        zip.start_file(name, deflated).map_err(epub_err)?;
+        zip.write_all(content.as_bytes()).map_err(io_err)?;
+    }
+    Ok(zip.finish().map_err(epub_err)?.into_inner())
+}
+
+fn title(state: &GameState) -> String {
+    match state.quest.title.trim() {
+        "" => "An uQuest adventure".to_owned(),
+        title => title.to_owned(),
+    }
+}
+
+fn summary(pc: &PlayerCharacter) -> String {
+    format!("Level {} {} {}", pc.level(), pc.race(), pc.class())
+}
+
+fn speaker(state: &GameState, id: usize) -> &str {
+    state.party.get(id).map_or("Someone", |pc| pc.name())
+}
+
+fn details(pc: &PlayerCharacter) -> Vec<(&'static str, &str)> {
+    let details = pc.details();
+    [
+        ("Appearance", &details.appearance),
+        ("Backstory", &details.backstory),
+        ("Personality", &details.personality_traits),
+        ("Ideals", &details.ideals),
+        ("Flaws", &details.flaws),
+    ]
+    .into_iter()
+    .filter_map(|(label, text)| Some((label, text.as_deref()?)))
+    .collect()
+}
+
+/// The document body shared by HTML and EPUB, which must also be valid XHTML.
+fn body(state: &GameState) -> String {
+    let quest = &state.quest;
+    let mut html = format!("<h1>{}</h1>\n", escape(&title(state)));
+    if !quest.description.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p class=\"description\">{}</p>",
+            escape(&quest.description)
+        );
+    }
+    if !quest.objective_summary.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p class=\"objective\"><strong>Objective:</strong> {}</p>",
+            escape(&quest.objective_summary)
+        );
+    }
+
+    html.push_str("<h2 id=\"party\">The party</h2>\n");
+    for pc in state.party.iter() {
+        let _ = writeln!(
+            html,
+            "<section class=\"character\">\n<h3>{}</h3>\n<p class=\"summary\">{}</p>",
+            escape(pc.name()),
+            escape(&summary(pc))
+        );
+        html.push_str("<table>\n<tr>");
+        for ability in Ability::ALL {
+            let _ = write!(html, "<th>{}</th>", ability.abbreviation());
+        }
+        html.push_str("</tr>\n<tr>");
+        for ability in Ability::ALL {
+            let _ = write!(html, "<td>{}</td>", pc.abilities().get(ability));
+        }
+        html.push_str("</tr>\n</table>\n");
+        let _ = writeln!(
+            html,
+            "<p><strong>HP</strong> {} / {}, <strong>XP</strong> {}</p>",
+            pc.hp(),
+            pc.max_hp(),
+            pc.xp()
+        );
+        if !pc.features().is_empty() {
+            let _ = writeln!(
+                html,
+                "<p><strong>Features:</strong> {}</p>",
+                escape(&pc.features().join(", "))
+            );
+        }
+        if !pc.inventory().is_empty() {
+            let _ = writeln!(
+                html,
+                "<p><strong>Inventory:</strong> {}</p>",
+                escape(&pc.inventory().join(", "))
+            );
+        }
+        for (label, text) in details(pc) {
+            let _ = writeln!(html, "<p><strong>{}:</strong> {}</p>", label, escape(text));
+        }
+        html.push_str("</section>\n");
+    }
+
+    html.push_str("<h2 id=\"adventure\">The adventure</h2>\n");
+    for entry in state.log.iter().filter(|entry| entry.in_story()) {
+        match entry.player {
+            GamePlayer::GM => html.push_str(&narration(&entry.content)),
+            GamePlayer::PC(id) => {
+                let _ = writeln!(
+                    html,
+                    "<blockquote class=\"action\"><p><strong>{}:</strong> {}</p></blockquote>",
+                    escape(speaker(state, id)),
+                    escape(entry.content.trim()).replace('\n', "<br/>")
+                );
+            }
+            GamePlayer::System => html.push_str(&paragraphs(&entry.content, " class=\"system\"")),
+        }
+    }
+    html
+}
+
+/// Renders the GM's Markdown narration. Raw HTML is escaped rather than
+/// passed through, to keep EPUB chapters valid XHTML.
+fn narration(markdown: &str) -> String {
+    let events =
+        Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH).map(|event| match event {
+            Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+            event => event,
+        });
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events);
+    html
+}
+
+/// Splits `text` into `<p>`s at blank lines, keeping single line breaks.
+fn paragraphs(text: &str, attributes: &str) -> String {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| {
+            format!(
+                "<p{}>{}</p>\n",
+                attributes,
+                escape(paragraph).replace('\n', "<br/>")
+            )
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A random (version 4) UUID to identify the book.
+fn uuid() -> String {
+    let bytes = rand::random::<u128>() & !(0xf << 76) & !(0x3 << 62) | (0x4 << 76) | (0x2 << 62);
+    let hex = format!("{:032x}", bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// `secs` since the Unix epoch as a UTC `CCYY-MM-DDThh:mm:ssZ`, as EPUB wants
+/// for `dcterms:modified`.
+fn timestamp(secs: u64) -> String {
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // Civil date from days since 1970-01-01, after Howard Hinnant's algorithm.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+const CONTAINER: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+<rootfiles>\n\
+<rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>\n\
+</rootfiles>\n\
+</container>\n";
+
+const STYLE: &str = "body { font-family: Georgia, serif; line-height: 1.6; max-width: 40em; margin: 2em auto; padding: 0 1em; }
+h1, h2, h3 { font-family: sans-serif; }
+h1 { text-align: center; }
+.description { font-style: italic; }
+.character { border: 1px solid #ccc; border-radius: 4px; padding: 0 1em; margin-bottom: 1em; }
+.summary { margin-top: 0; font-style: italic; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: center; }
+blockquote.action { margin: 1em 0 1em 2em; padding-left: 1em; border-left: 3px solid #6fa8dc; }
+.system { color: #666; font-style: italic; text-align: center; }
+";
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use super::*;
+    use crate::catalog::Catalog;
+    use crate::character::PlayerCharacterBuilder;
+    use crate::game::GameLogEntry;
+    use crate::progression::LevelTable;
+    use crate::schema::AdventureSettings;
+
+    fn state() -> GameState {
+        let catalog = Catalog::bundled();
+        let ann = PlayerCharacterBuilder::new("Ann".to_owned())
+            .build(&catalog)
+            .unwrap();
+        let mut state = GameState::new(
+            vec![ann],
+            LevelTable::default(),
+            AdventureSettings::default(),
+            None,
+        );
+        state.quest.title = "The Crown of <Ash> & Smoke".to_owned();
+        state.quest.description = "A crown lost in the hills.".to_owned();
+        let mut unsent = GameLogEntry::new(GamePlayer::PC(0), "I run away".to_owned());
+        unsent.unsent = true;
+        state.log = vec![
+            GameLogEntry::new(
+                GamePlayer::GM,
+                "The gate is **shut**. <script>alert(1)</script>".to_owned(),
+            ),
+            GameLogEntry::new(GamePlayer::PC(0), "I knock & wait".to_owned()),
+            unsent,
+            GameLogEntry::new(GamePlayer::System, "Scene 2: The hall".to_owned()),
+        ];
+        state
+    }
+
+    #[test]
+    fn markdown_has_the_quest_party_and_story() {
+        let md = to_markdown(&state());
+        assert!(md.starts_with("# The Crown of <Ash> & Smoke\n\nA crown lost in the hills.\n"));
+        assert!(md.contains("## The party\n\n### Ann\n"));
+        assert!(md.contains("The gate is **shut**."));
+        assert!(md.contains("> **Ann:** I knock & wait\n"));
+        assert!(md.contains("*Scene 2: The hall*\n"));
+        assert!(!md.contains("I run away"));
+    }
+
+    #[test]
+    fn html_escapes_what_the_gm_and_players_wrote() {
+        let html = to_html(&state());
+        assert!(html.contains("<title>The Crown of &lt;Ash&gt; &amp; Smoke</title>"));
+        assert!(html.contains("<strong>shut</strong>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<strong>Ann:</strong> I knock &amp; wait"));
+        assert!(html.contains("<p class=\"system\">Scene 2: The hall</p>"));
+        assert!(!html.contains("I run away"));
+    }
+
+    #[test]
+    fn epub_starts_with_an_uncompressed_mimetype() {
+        let epub = to_epub(&state()).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(epub)).unwrap();
+        let names: Vec<_> = archive
+            .file_names()
+            .map(|name| name.unwrap().into_owned())
+            .collect();
+        for name in [
+            "META-INF/container.xml",
+            "OEBPS/content.opf",
+            "OEBPS/nav.xhtml",
+            "OEBPS/story.xhtml",
+            "OEBPS/style.css",
+        ] {
+            assert!(names.iter().any(|n| n == name), "{} is missing", name);
+        }
+
+        let mut mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name().unwrap(), "mimetype");
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        let mut content = String::new();
+        mimetype.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "application/epub+zip");
+        drop(mimetype);
+
+        let mut story = String::new();
+        archive
+            .by_name("OEBPS/story.xhtml")
+            .unwrap()
+            .read_to_string(&mut story)
+            .unwrap();
+        assert!(story.contains("&lt;script&gt;"));
+        assert!(story.contains("<h2 id=\"adventure\">"));
+    }
+
+    #[test]
+    fn uuids_are_random_version_4() {
+        let id = uuid();
+        let parts: Vec<_> = id.split('-').map(str::len).collect();
+        assert_eq!(parts, [8, 4, 4, 4, 12]);
+        assert!(id.chars().all(|c| c == '-' || c.is_ascii_hexdigit()));
+        assert_eq!(&id[14..15], "4");
+        assert!("89ab".contains(&id[19..20]), "bad variant in {}", id);
+        assert_ne!(uuid(), id);
+    }
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(timestamp(1709251199), "2024-02-29T23:59:59Z");
+        assert_eq!(timestamp(1735689600), "2025-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn exports_never_overwrite_each_other() {
+        let dir = std::env::temp_dir().join(format!("uquest-export-{:x}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = state();
+        let path = dir.join(format!("{}.md", slug(&state)));
+        assert_eq!(path.file_name().unwrap(), "the-crown-of-ash-smoke.md");
+
+        let written: Vec<_> = ["one", "two", "three"]
+            .into_iter()
+            .map(|document| write_new(&path, document.as_bytes()).unwrap())
+            .collect();
+        assert_eq!(
+            written,
+            [
+                path.clone(),
+                dir.join("the-crown-of-ash-smoke-2.md"),
+                dir.join("the-crown-of-ash-smoke-3.md"),
+            ]
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one");
+        assert_eq!(std::fs::read_to_string(&written[2]).unwrap(), "three");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,231 +1,1428 @@
-use std::sync::{Arc, RwLock};
-use tokio::sync::{mpsc, oneshot};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
 
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
 
-use crate::character::PlayerCharacter;
-use crate::conn::Connection;
-use crate::schema::{AIInput, QuestDefinition, QuestUpdate};
+use crate::catalog::Catalog;
+use crate::character::{CharacterDetails, PlayerCharacter};
+use crate::conn::{Cancel, Connection, GmConfig};
+use crate::export::{self, ExportError, ExportFormat};
+use crate::progression::{Improvement, LevelTable};
+use crate::schema::{
+    AIInput, AdventureSettings, CampaignContext, PartyAction, QuestConclusion, QuestDefinition,
+    QuestProgress, QuestUpdate,
+};
+
+/// The underlying cause of a `GameError`, shared so the error stays `Clone`.
+pub type ErrorSource = Arc<dyn std::error::Error + Send + Sync>;
 
 #[derive(Debug, Clone)]
 pub enum GameError {
-    ConnectionFailed,
-    SendFailed(String),
+    /// No API key was given to the builder or set in `OPENAI_API_KEY`.
+    MissingApiKey,
+    ConnectionFailed {
+        source: Option<ErrorSource>,
+    },
+    SendFailed {
+        message: String,
+        source: Option<ErrorSource>,
+    },
     UnexpectedResponse(String),
     RefusalResponse(String),
+    /// The game the handle belonged to has stopped.
+    GameStopped,
+    /// The turn was cancelled with `GameHandle::cancel` before the GM replied.
+    Cancelled,
     Custom(String),
 }
 
-#[derive(Debug)]
+impl GameError {
+    pub(crate) fn connection_failed(
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        GameError::ConnectionFailed {
+            source: Some(Arc::new(source)),
+        }
+    }
+
+    pub(crate) fn send_failed(
+        message: &str,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        GameError::SendFailed {
+            message: message.to_owned(),
+            source: Some(Arc::new(source)),
+        }
+    }
+}
+
+impl GameError {
+    /// A short, human-readable name for the kind of error, e.g. for a heading.
+    pub fn class(&self) -> &'static str {
+        match self {
+            GameError::MissingApiKey => "Missing API key",
+            GameError::ConnectionFailed { .. } => "Connection error",
+            GameError::SendFailed { .. } => "Communication error",
+            GameError::UnexpectedResponse(_) => "Unexpected response",
+            GameError::RefusalResponse(_) => "Refusal",
+            GameError::GameStopped => "Game stopped",
+            GameError::Cancelled => "Cancelled",
+            GameError::Custom(_) => "Error",
+        }
+    }
+}
+
+impl std::fmt::Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameError::MissingApiKey => write!(f, "No API key, set OPENAI_API_KEY"),
+            GameError::ConnectionFailed { .. } => write!(f, "Could not connect to the GM"),
+            GameError::SendFailed { message, .. } => write!(f, "{}", message),
+            GameError::UnexpectedResponse(msg) => write!(f, "Unexpected response: {}", msg),
+            GameError::RefusalResponse(msg) => write!(f, "The GM refused: {}", msg),
+            GameError::GameStopped => write!(f, "The game is no longer running"),
+            GameError::Cancelled => write!(f, "The turn was cancelled"),
+            GameError::Custom(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameError::ConnectionFailed { source } | GameError::SendFailed { source, .. } => {
+                source.as_deref().map(|source| source as _)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Index of a character in `GameState::party`.
+pub type CharacterId = usize;
+
+/// How much the GM writes in each response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// A few sentences, to keep the game moving.
+    Brief,
+    #[default]
+    Standard,
+    /// Several paragraphs, rich in detail.
+    Detailed,
+}
+
+impl Verbosity {
+    pub const ALL: [Verbosity; 3] = [Verbosity::Brief, Verbosity::Standard, Verbosity::Detailed];
+}
+
+impl std::fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verbosity::Brief => write!(f, "Brief"),
+            Verbosity::Standard => write!(f, "Standard"),
+            Verbosity::Detailed => write!(f, "Detailed"),
+        }
+    }
+}
+
+/// Who a log entry came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GamePlayer {
     GM,
-    PC,
+    PC(CharacterId),
+    System,
 }
 
 #[derive(Debug)]
-enum GameMessage {
+pub(crate) enum GameMessage {
     Start {
         respond_to: oneshot::Sender<Result<(), GameError>>,
     },
     Input {
         respond_to: oneshot::Sender<Result<(), GameError>>,
-        content: String,
+        actions: Vec<(CharacterId, String)>,
+    },
+    LevelUp {
+        respond_to: oneshot::Sender<Result<(), GameError>>,
+        character: CharacterId,
+        improvement: Improvement,
+    },
+    Undo {
+        respond_to: oneshot::Sender<Result<(), GameError>>,
+    },
+    Reroll {
+        respond_to: oneshot::Sender<Result<(), GameError>>,
+    },
+    XCard {
+        respond_to: oneshot::Sender<Result<(), GameError>>,
+    },
+    Branch {
+        respond_to: oneshot::Sender<Result<GameHandle, GameError>>,
+        entry: usize,
+    },
+    Save {
+        respond_to: oneshot::Sender<Result<SavedGame, GameError>>,
+    },
+    Conclude {
+        respond_to: oneshot::Sender<Result<QuestConclusion, GameError>>,
     },
 }
 
-#[derive(Debug)]
+/// How many events a subscriber can fall behind before it starts missing them.
+pub(crate) const EVENT_CAPACITY: usize = 64;
+
+/// Something that changed in a game, as seen by `GameHandle::subscribe`.
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    /// `entry` was added to `GameState::log` at `index`.
+    LogEntryAdded {
+        index: usize,
+        entry: GameLogEntry,
+    },
+    /// Existing log entries were changed or removed, e.g. marked unsent.
+    LogChanged,
+    QuestUpdated(QuestDefinition),
+    /// A party member's level, hit points, XP or inventory changed.
+    PartyUpdated(CharacterId),
+    /// A request has been sent to the GM.
+    TurnStarted,
+    /// A request has been handled, successfully or not.
+    TurnFinished,
+    /// A request failed; sent just before its `TurnFinished`.
+    Error(GameError),
+}
+
+/// The JSON form of a `GameEvent`, as streamed to headless and HTTP clients,
+/// e.g. `{"event": "party_updated", "character": 1}`.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum SerializedEvent<'a> {
+    LogEntryAdded {
+        index: usize,
+        entry: &'a GameLogEntry,
+    },
+    LogChanged,
+    QuestUpdated {
+        quest: &'a QuestDefinition,
+    },
+    PartyUpdated {
+        character: CharacterId,
+    },
+    TurnStarted,
+    TurnFinished,
+    Error {
+        message: String,
+    },
+}
+
+impl Serialize for GameEvent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            GameEvent::LogEntryAdded { index, entry } => SerializedEvent::LogEntryAdded {
+                index: *index,
+                entry,
+            },
+            GameEvent::LogChanged => SerializedEvent::LogChanged,
+            GameEvent::QuestUpdated(quest) => SerializedEvent::QuestUpdated { quest },
+            GameEvent::PartyUpdated(character) => SerializedEvent::PartyUpdated {
+                character: *character,
+            },
+            GameEvent::TurnStarted => SerializedEvent::TurnStarted,
+            GameEvent::TurnFinished => SerializedEvent::TurnFinished,
+            GameEvent::Error(error) => SerializedEvent::Error {
+                message: error.to_string(),
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+/// One line of the story, as shown to the players.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GameLogEntry {
     pub player: GamePlayer,
     pub content: String,
+    /// A PC action whose turn failed, so the GM may never have seen it. Unsent
+    /// entries are dropped when the next action is sent, e.g. a retry.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unsent: bool,
+    /// Lines and veils a GM entry mentions, found by
+    /// `AdventureSettings::screen`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flagged: Vec<String>,
+    /// Taken out of the story by the X-card. The GM was asked to replace it,
+    /// so it's kept only to show what was withdrawn.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub retconned: bool,
 }
 
 impl GameLogEntry {
     pub fn new(player: GamePlayer, content: String) -> Self {
-        Self { player, content }
+        Self {
+            player,
+            content,
+            unsent: false,
+            flagged: Vec::new(),
+            retconned: false,
+        }
+    }
+
+    /// Whether the entry is part of the story as the GM knows it, rather than
+    /// unsent or withdrawn with the X-card.
+    pub fn in_story(&self) -> bool {
+        !self.unsent && !self.retconned
     }
 }
 
+/// Sets up a game for a party. `build` connects to the GM; the quest itself
+/// begins with `GameHandle::start`.
 #[derive(Debug)]
 pub struct GameBuilder {
-    character: PlayerCharacter,
+    party: Vec<PlayerCharacter>,
     api_key: Option<String>,
+    level_table: LevelTable,
+    catalog: Catalog,
+    endpoint: Option<String>,
+    model: Option<String>,
+    verbosity: Verbosity,
+    adventure: AdventureSettings,
+    campaign: Option<CampaignContext>,
+    saved: Option<SavedGame>,
 }
 
 impl GameBuilder {
     pub fn new(character: PlayerCharacter) -> Self {
         Self {
-            character,
+            party: vec![character],
             api_key: None,
+            level_table: LevelTable::default(),
+            catalog: Catalog::bundled(),
+            endpoint: None,
+            model: None,
+            verbosity: Verbosity::default(),
+            adventure: AdventureSettings::default(),
+            campaign: None,
+            saved: None,
         }
     }
 
+    /// Carries on a game saved with `GameHandle::save`, on the GM thread it
+    /// was played on. The quest has already begun, so don't `start` it again.
+    pub fn resume(saved: SavedGame) -> Self {
+        Self {
+            party: saved.state.party.clone(),
+            api_key: None,
+            level_table: saved.state.level_table.clone(),
+            catalog: Catalog::bundled(),
+            endpoint: None,
+            model: None,
+            verbosity: Verbosity::default(),
+            adventure: saved.state.adventure.clone(),
+            campaign: saved.state.campaign.clone(),
+            saved: Some(saved),
+        }
+    }
+
+    /// Adds another player character to the party, for hot-seat play.
+    pub fn with_party_member(mut self, character: PlayerCharacter) -> Self {
+        self.party.push(character);
+        self
+    }
+
+    /// The catalog whose races and classes are offered to the GM.
+    pub fn with_catalog(mut self, catalog: Catalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    pub fn with_level_table(mut self, level_table: LevelTable) -> Self {
+        self.level_table = level_table;
+        self
+    }
+
+    /// The OpenAI API key to use instead of `OPENAI_API_KEY`.
     pub fn with_api_key(mut self, api_key: String) -> Self {
         self.api_key = Some(api_key);
         self
     }
 
+    /// An OpenAI-compatible API base URL to use instead of OpenAI's, e.g.
+    /// `http://localhost:8000/v1`.
+    pub fn with_endpoint(mut self, endpoint: String) -> Self {
+        self.endpoint = Some(endpoint);
+        self
+    }
+
+    /// The model to run the GM on, instead of the assistant's default.
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// The genre, tone and so on of the quest, which the GM is told when it
+    /// starts and reminded of every turn.
+    pub fn with_adventure(mut self, adventure: AdventureSettings) -> Self {
+        self.adventure = adventure;
+        self
+    }
+
+    /// Makes the quest the next in a campaign, continuing from `context`.
+    pub fn with_campaign(mut self, context: CampaignContext) -> Self {
+        self.campaign = Some(context);
+        self
+    }
+
     pub async fn build(self) -> Result<GameHandle, GameError> {
         GameHandle::new(self).await
     }
+
+    /// Asks the GM to draft a backstory, personality and appearance for the
+    /// first character in the party on a throwaway thread, without starting a
+    /// game. Details the character already has are passed along for the GM to
+    /// build on.
+    pub async fn draft_backstory(mut self) -> Result<CharacterDetails, GameError> {
+        let connection = Connection::new(self.gm_config()?, &self.catalog, None)
+            .await
+            .map_err(|error| {
+                error!("Connection failed: {}", error);
+                GameError::connection_failed(error)
+            })?;
+        let response = connection
+            .send(AIInput::DraftBackstory(Box::new(self.party.swap_remove(0))))
+            .await?;
+        response
+            .updates
+            .into_iter()
+            .find_map(|update| match update {
+                QuestUpdate::Backstory(details) => Some(details),
+                _ => None,
+            })
+            .ok_or(GameError::UnexpectedResponse(
+                "No backstory in response".to_owned(),
+            ))
+    }
+
+    /// How to connect to the GM. The API key falls back to the
+    /// `OPENAI_API_KEY` environment variable.
+    fn gm_config(&self) -> Result<GmConfig, GameError> {
+        let api_key = self
+            .api_key
+            .clone()
+            .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+            .filter(|key| !key.trim().is_empty())
+            .ok_or(GameError::MissingApiKey)?;
+        Ok(GmConfig {
+            api_key,
+            endpoint: self.endpoint.clone(),
+            model: self.model.clone(),
+            verbosity: self.verbosity,
+            adventure: self.adventure.clone(),
+        })
+    }
 }
 
+/// A running game. Handles are cheap to clone and all refer to the same game;
+/// each request resolves once the GM has responded and `state` is up to date.
 #[derive(Debug, Clone)]
 pub struct GameHandle {
     sender: mpsc::Sender<GameMessage>,
     state: Arc<RwLock<GameState>>,
+    events: broadcast::Sender<GameEvent>,
+    cancel: Arc<Cancel>,
 }
 
 impl GameHandle {
     async fn new(builder: GameBuilder) -> Result<Self, GameError> {
-        let api_key = if let Some(key) = builder.api_key {
-            key
-        } else {
-            std::env::var("OPENAI_API_KEY").unwrap()
-        };
+        // The GM refers to characters by name, see `GameState::member_id`.
+        let party = &builder.party;
+        if let Some(pc) = party.iter().enumerate().find_map(|(index, pc)| {
+            party[..index]
+                .iter()
+                .any(|other| same_name(other.name(), pc.name()))
+                .then_some(pc)
+        }) {
+            return Err(GameError::Custom(format!(
+                "More than one party member is called {}",
+                pc.name()
+            )));
+        }
+        let config = builder.gm_config()?;
         let (sender, receiver) = mpsc::channel(8);
-        let instance = GameInstance::new(receiver, api_key, builder.character).await?;
+        let state = GameState::new(
+            builder.party,
+            builder.level_table,
+            builder.adventure,
+            builder.campaign,
+        );
+        let instance =
+            GameInstance::new(receiver, config, state, &builder.catalog, builder.saved).await?;
         let state = instance.state.clone();
+        let events = instance.events.clone();
+        let cancel = instance.connection.cancel().clone();
         tokio::spawn(run_game(instance));
-        Ok(Self { sender, state })
+        Ok(Self {
+            sender,
+            state,
+            events,
+            cancel,
+        })
     }
 
+    /// Sends the party to the GM, who responds with the quest and its opening.
     pub async fn start(&self) -> Result<(), GameError> {
         let (send, recv) = oneshot::channel();
         let msg = GameMessage::Start { respond_to: send };
 
-        let _ = self.sender.send(msg).await;
-        recv.await.unwrap()
+        self.request(msg, recv).await
+    }
+
+    /// Wraps a channel to something that handles `GameMessage`s, and the
+    /// state and events it keeps up to date, e.g. a proxy for a game hosted
+    /// elsewhere.
+    pub(crate) fn from_parts(
+        sender: mpsc::Sender<GameMessage>,
+        state: Arc<RwLock<GameState>>,
+        events: broadcast::Sender<GameEvent>,
+        cancel: Arc<Cancel>,
+    ) -> Self {
+        Self {
+            sender,
+            state,
+            events,
+            cancel,
+        }
+    }
+
+    /// Stops waiting for the GM on the turn in progress, which then fails with
+    /// `GameError::Cancelled` and is left as if it had never been sent. Does
+    /// nothing in a game hosted elsewhere.
+    pub fn cancel(&self) {
+        self.cancel.request();
+    }
+
+    /// Sends an action taken by one of the party's characters to the GM.
+    pub async fn input(&self, character: CharacterId, content: String) -> Result<(), GameError> {
+        self.input_batch(vec![(character, content)]).await
     }
 
-    pub async fn input(&self, content: String) -> Result<(), GameError> {
+    /// Sends actions from several characters to the GM as a single turn.
+    pub async fn input_batch(&self, actions: Vec<(CharacterId, String)>) -> Result<(), GameError> {
         let (send, recv) = oneshot::channel();
         let msg = GameMessage::Input {
             respond_to: send,
-            content,
+            actions,
+        };
+
+        self.request(msg, recv).await
+    }
+
+    /// Takes a pending level for a character and tells the GM about it.
+    pub async fn level_up(
+        &self,
+        character: CharacterId,
+        improvement: Improvement,
+    ) -> Result<(), GameError> {
+        let (send, recv) = oneshot::channel();
+        let msg = GameMessage::LevelUp {
+            respond_to: send,
+            character,
+            improvement,
         };
 
-        let _ = self.sender.send(msg).await;
-        recv.await.unwrap()
+        self.request(msg, recv).await
     }
 
+    /// Takes back the party's last action: it and everything the GM said or
+    /// changed in response are removed from the state and from the GM's
+    /// memory. Level ups taken since then are undone too.
+    pub async fn undo(&self) -> Result<(), GameError> {
+        let (send, recv) = oneshot::channel();
+        let msg = GameMessage::Undo { respond_to: send };
+
+        self.request(msg, recv).await
+    }
+
+    /// Discards the GM's response to the party's last action and asks for a
+    /// new one. Only possible straight after the action, before a level up.
+    pub async fn reroll(&self) -> Result<(), GameError> {
+        let (send, recv) = oneshot::channel();
+        let msg = GameMessage::Reroll { respond_to: send };
+
+        self.request(msg, recv).await
+    }
+
+    /// Plays the X-card: the GM's entries from the last turn are marked
+    /// retconned, the party and quest go back to how they were before
+    /// them, and the GM is asked to replace the scene. Anyone can use it, and
+    /// nobody has to say why.
+    pub async fn x_card(&self) -> Result<(), GameError> {
+        let (send, recv) = oneshot::channel();
+        let msg = GameMessage::XCard { respond_to: send };
+
+        self.request(msg, recv).await
+    }
+
+    /// Ends the quest, asking the GM to sum it up and list the hooks, allies
+    /// and enemies a campaign's next quest can carry on with.
+    pub async fn conclude(&self) -> Result<QuestConclusion, GameError> {
+        let (send, recv) = oneshot::channel();
+        let msg = GameMessage::Conclude { respond_to: send };
+
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| GameError::GameStopped)?;
+        recv.await.map_err(|_| GameError::GameStopped)?
+    }
+
+    /// Forks the game at log entry `entry` into a new, independent game with
+    /// its own GM thread. The branch starts with the state as it was after
+    /// the turn that logged `entry`, and this game carries on unchanged.
+    pub async fn branch(&self, entry: usize) -> Result<GameHandle, GameError> {
+        let (send, recv) = oneshot::channel();
+        let msg = GameMessage::Branch {
+            respond_to: send,
+            entry,
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| GameError::GameStopped)?;
+        recv.await.map_err(|_| GameError::GameStopped)?
+    }
+
+    /// Snapshots the game for `GameBuilder::resume`. The GM's memory stays on
+    /// its thread, so a saved game can only be resumed with the same API key.
+    pub async fn save(&self) -> Result<SavedGame, GameError> {
+        let (send, recv) = oneshot::channel();
+        let msg = GameMessage::Save { respond_to: send };
+
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| GameError::GameStopped)?;
+        recv.await.map_err(|_| GameError::GameStopped)?
+    }
+
+    /// Renders the story so far as a document, see `export`.
+    pub fn export(&self, format: ExportFormat) -> Result<Vec<u8>, ExportError> {
+        let state = self
+            .state
+            .read()
+            .map_err(|_| ExportError::StateUnavailable)?;
+        export::export(&state, format)
+    }
+
+    /// The game's current state, updated as the GM responds.
     pub fn state(&self) -> &Arc<RwLock<GameState>> {
         &self.state
     }
+
+    /// A copy of the game's current state, for when holding the lock isn't
+    /// needed.
+    pub fn snapshot(&self) -> Result<GameState, GameError> {
+        self.state
+            .read()
+            .map(|state| state.clone())
+            .map_err(|_| GameError::GameStopped)
+    }
+
+    /// A stream of everything that happens in the game from now on. A
+    /// subscriber that falls too far behind skips the events it missed, and
+    /// should re-read `state` to catch up.
+    pub fn subscribe(&self) -> impl Stream<Item = GameEvent> + Send + 'static {
+        BroadcastStream::new(self.events.subscribe()).filter_map(|event| event.ok())
+    }
+
+    async fn request(
+        &self,
+        msg: GameMessage,
+        response: oneshot::Receiver<Result<(), GameError>>,
+    ) -> Result<(), GameError> {
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| GameError::GameStopped)?;
+        response.await.map_err(|_| GameError::GameStopped)?
+    }
 }
 
 struct GameInstance {
     receiver: mpsc::Receiver<GameMessage>,
     connection: Connection,
     state: Arc<RwLock<GameState>>,
+    events: broadcast::Sender<GameEvent>,
+    /// Turns since the quest started, oldest first, for undo and reroll.
+    history: Vec<Turn>,
+}
+
+/// A game as saved by `GameHandle::save`: its state, plus what's needed to
+/// carry on talking to the same GM and to take back turns played before the
+/// save.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub state: GameState,
+    thread_id: String,
+    history: Vec<SavedTurn>,
+}
+
+/// A `Turn` as saved. The log before each turn is the start of the game's
+/// log, so only its length is kept rather than a copy per turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedTurn {
+    /// The state before the turn, without its log if `log_len` is set.
+    before: GameState,
+    /// How long the log was before the turn. Missing from saves that kept the
+    /// whole log with every turn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    log_len: Option<usize>,
+    actions: Option<Vec<GameLogEntry>>,
+    input_message: String,
+    reply_messages: Vec<String>,
+}
+
+impl SavedTurn {
+    fn new(turn: &Turn) -> Self {
+        Self {
+            before: GameState {
+                log: Vec::new(),
+                ..turn.before.clone()
+            },
+            log_len: Some(turn.before.log.len()),
+            actions: turn.actions.clone(),
+            input_message: turn.input_message.clone(),
+            reply_messages: turn.reply_messages.clone(),
+        }
+    }
+
+    /// The turn, with its log taken from `log`, the game's log when saved.
+    fn into_turn(self, log: &[GameLogEntry]) -> Turn {
+        let mut before = self.before;
+        if let Some(len) = self.log_len {
+            before.log = log[..len.min(log.len())].to_vec();
+        }
+        Turn {
+            before,
+            actions: self.actions,
+            input_message: self.input_message,
+            reply_messages: self.reply_messages,
+        }
+    }
+}
+
+/// A turn the GM responded to, and how to take it back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Turn {
+    /// The state before the turn.
+    before: GameState,
+    /// The party's log entries for an action, or `None` for e.g. a level up.
+    actions: Option<Vec<GameLogEntry>>,
+    /// The thread message the turn was sent as.
+    input_message: String,
+    /// The thread messages the GM responded with.
+    reply_messages: Vec<String>,
 }
 
 impl GameInstance {
     async fn new(
         receiver: mpsc::Receiver<GameMessage>,
-        api_key: String,
-        character: PlayerCharacter,
+        config: GmConfig,
+        state: GameState,
+        catalog: &Catalog,
+        saved: Option<SavedGame>,
     ) -> Result<Self, GameError> {
-        let connection = Connection::new(api_key).await;
-        if let Err(error) = connection {
-            error!("Connection failed: {}", error);
-            return Err(GameError::ConnectionFailed);
-        }
+        let thread_id = saved.as_ref().map(|saved| saved.thread_id.clone());
+        let connection = Connection::new(config, catalog, thread_id)
+            .await
+            .map_err(|error| {
+                error!("Connection failed: {}", error);
+                GameError::connection_failed(error)
+            })?;
 
         info!("Connected!");
 
+        let (state, history) = match saved {
+            Some(saved) => {
+                let history = saved
+                    .history
+                    .into_iter()
+                    .map(|turn| turn.into_turn(&saved.state.log))
+                    .collect();
+                (saved.state, history)
+            }
+            None => (state, Vec::new()),
+        };
         Ok(Self {
             receiver,
-            connection: connection.unwrap(),
-            state: Arc::new(RwLock::new(GameState::new(character))),
+            connection,
+            state: Arc::new(RwLock::new(state)),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            history,
         })
     }
 
     async fn process_update(&mut self, update: &QuestUpdate) {
         match update {
             QuestUpdate::QuestDefinition(def) => {
-                let mut state = self.state.write().unwrap();
-                (*state).quest = def.clone();
+                let mut state = self.write_state();
+                state.quest = def.clone();
+                self.emit(GameEvent::QuestUpdated(def.clone()));
             }
             QuestUpdate::Description(desc) => {
-                let mut state = self.state.write().unwrap();
-                (*state)
-                    .log
-                    .push(GameLogEntry::new(GamePlayer::GM, desc.clone()));
+                let mut state = self.write_state();
+                let mut entry = GameLogEntry::new(GamePlayer::GM, desc.clone());
+                entry.flagged = state.adventure.screen(desc);
+                if !entry.flagged.is_empty() {
+                    warn!("GM may have crossed a line or veil: {:?}", entry.flagged);
+                }
+                self.push_log(&mut state, entry);
+            }
+            QuestUpdate::AwardXp {
+                character,
+                amount,
+                reason,
+            } => {
+                let mut state = self.write_state();
+                let state = &mut *state;
+                let ids: Vec<CharacterId> = match character {
+                    Some(name) => match state.member_id(name) {
+                        Some(id) => vec![id],
+                        None => {
+                            error!("XP award for unknown character {}", name);
+                            return;
+                        }
+                    },
+                    None => (0..state.party.len()).collect(),
+                };
+                for id in ids {
+                    let pc = &mut state.party[id];
+                    pc.award_xp(*amount);
+                    self.emit(GameEvent::PartyUpdated(id));
+                    let mut content = format!("{} gained {} XP: {}", pc.name(), amount, reason);
+                    if pc.pending_level_ups(&state.level_table) > 0 {
+                        content.push_str(" (level up available!)");
+                    }
+                    self.push_log(state, GameLogEntry::new(GamePlayer::System, content));
+                }
+            }
+            QuestUpdate::HitPoints {
+                character,
+                change,
+                reason,
+            } => {
+                let mut state = self.write_state();
+                let Some(id) = state.member_id(character) else {
+                    error!("HP change for unknown character {}", character);
+                    return;
+                };
+                let pc = &mut state.party[id];
+                pc.change_hp(*change);
+                self.emit(GameEvent::PartyUpdated(id));
+                let content = if *change < 0 {
+                    format!("{} lost {} HP: {}", pc.name(), -change, reason)
+                } else {
+                    format!("{} regained {} HP: {}", pc.name(), change, reason)
+                };
+                self.push_log(&mut state, GameLogEntry::new(GamePlayer::System, content));
+            }
+            QuestUpdate::ItemGained { character, item } => {
+                let mut state = self.write_state();
+                let Some(id) = state.member_id(character) else {
+                    error!("Item gained by unknown character {}", character);
+                    return;
+                };
+                let pc = &mut state.party[id];
+                pc.add_item(item.clone());
+                self.emit(GameEvent::PartyUpdated(id));
+                let content = format!("{} gained {}", pc.name(), item);
+                self.push_log(&mut state, GameLogEntry::new(GamePlayer::System, content));
+            }
+            QuestUpdate::ItemLost { character, item } => {
+                let mut state = self.write_state();
+                let Some(id) = state.member_id(character) else {
+                    error!("Item lost by unknown character {}", character);
+                    return;
+                };
+                let pc = &mut state.party[id];
+                if pc.remove_item(item) {
+                    self.emit(GameEvent::PartyUpdated(id));
+                    let content = format!("{} lost {}", pc.name(), item);
+                    self.push_log(&mut state, GameLogEntry::new(GamePlayer::System, content));
+                }
+            }
+            QuestUpdate::Backstory(_) => {
+                debug!("Ignoring backstory update during a game");
+            }
+            QuestUpdate::Conclusion(_) => {
+                debug!("Ignoring conclusion before the quest is over");
+            }
+            QuestUpdate::SceneChange { title } => {
+                let mut state = self.write_state();
+                state.scene += 1;
+                state.scene_title = title.clone();
+                let content = format!("Scene {}: {}", state.scene, title);
+                self.push_log(&mut state, GameLogEntry::new(GamePlayer::System, content));
             }
         }
     }
 
     async fn handle_message(&mut self, msg: GameMessage) {
         debug!("Handling message: {:?}", msg);
-        match msg {
-            GameMessage::Start { respond_to } => {
-                let initial_message = {
-                    let state = self.state.read().unwrap();
-                    let pc = &(*state).character;
-                    AIInput::Start(pc.clone())
-                    // format!("I am a {} {} called {}, what is my quest?", pc.race(), pc.class(), pc.name())
-                };
-                let result = self.connection.send(initial_message).await;
-                match result {
-                    Ok(response) => {
-                        for update in response.updates.iter() {
-                            self.process_update(&update).await;
-                        }
-                        let _ = respond_to.send(Ok(()));
-                    }
-                    Err(error) => {
-                        let _ = respond_to.send(Err(error));
-                    }
-                }
+        let (respond_to, result) = match msg {
+            GameMessage::Branch { respond_to, entry } => {
+                // Not a turn, this game doesn't change.
+                let _ = respond_to.send(self.branch(entry).await);
+                return;
             }
+            GameMessage::Save { respond_to } => {
+                let _ = respond_to.send(Ok(self.save()));
+                return;
+            }
+            GameMessage::Conclude { respond_to } => {
+                // The quest is over, so nothing is left to change.
+                let result = self.conclude().await;
+                self.connection.cancel().reset();
+                let _ = respond_to.send(result);
+                return;
+            }
+            GameMessage::Start { respond_to } => (respond_to, self.start().await),
             GameMessage::Input {
                 respond_to,
-                content,
-            } => {
-                {
-                    let mut state = self.state.write().unwrap();
-                    (*state)
-                        .log
-                        .push(GameLogEntry::new(GamePlayer::PC, content.clone()));
-                    drop(state);
+                actions,
+            } => (respond_to, self.input(actions).await),
+            GameMessage::LevelUp {
+                respond_to,
+                character,
+                improvement,
+            } => (respond_to, self.level_up(character, improvement).await),
+            GameMessage::Undo { respond_to } => (respond_to, self.undo().await),
+            GameMessage::Reroll { respond_to } => (respond_to, self.reroll().await),
+            GameMessage::XCard { respond_to } => (respond_to, self.x_card().await),
+        };
+        // Only once the run is over, so a cancel made while the message was
+        // still queued applies to it, and one made too late for it doesn't
+        // carry over to the next.
+        self.connection.cancel().reset();
+        if let Err(error) = &result {
+            self.emit(GameEvent::Error(error.clone()));
+        }
+        self.emit(GameEvent::TurnFinished);
+        let _ = respond_to.send(result);
+    }
+
+    async fn start(&mut self) -> Result<(), GameError> {
+        let initial_message = {
+            let state = self.read_state();
+            AIInput::Start {
+                party: state.party.clone(),
+                settings: state.adventure.clone(),
+                campaign: state.campaign.clone(),
+            }
+        };
+        let before = self.read_state().clone();
+        let (input_message, reply_messages) = self.send(initial_message).await?;
+        self.history.push(Turn {
+            before,
+            actions: None,
+            input_message,
+            reply_messages,
+        });
+        Ok(())
+    }
+
+    async fn input(&mut self, actions: Vec<(CharacterId, String)>) -> Result<(), GameError> {
+        let (actions, progress, before) = {
+            let mut state = self.write_state();
+            if actions.iter().any(|(id, _)| *id >= state.party.len()) {
+                return Err(GameError::Custom("No such character".to_owned()));
+            }
+            let before = state.log.len();
+            state.log.retain(|entry| !entry.unsent);
+            if state.log.len() != before {
+                self.emit(GameEvent::LogChanged);
+            }
+            let before = state.clone();
+            let actions = actions
+                .into_iter()
+                .map(|(id, content)| {
+                    let character = state.party[id].name().to_owned();
+                    self.push_log(
+                        &mut state,
+                        GameLogEntry::new(GamePlayer::PC(id), content.clone()),
+                    );
+                    PartyAction { character, content }
+                })
+                .collect();
+            state.turn += 1;
+            (actions, state.progress(), before)
+        };
+        match self.send(AIInput::UserInput { actions, progress }).await {
+            Ok((input_message, reply_messages)) => {
+                let entries = self.read_state().log[before.log.len()..]
+                    .iter()
+                    .filter(|entry| matches!(entry.player, GamePlayer::PC(_)))
+                    .cloned()
+                    .collect();
+                self.history.push(Turn {
+                    before,
+                    actions: Some(entries),
+                    input_message,
+                    reply_messages,
+                });
+                Ok(())
+            }
+            Err(error) => {
+                self.write_state().turn = before.turn;
+                self.mark_unsent(before.log.len());
+                Err(error)
+            }
+        }
+    }
+
+    /// Restores the state from before the last action, deleting it and every
+    /// turn since from the thread.
+    async fn undo(&mut self) -> Result<(), GameError> {
+        let Some(position) = self.history.iter().rposition(|turn| turn.actions.is_some()) else {
+            return Err(GameError::Custom("Nothing to undo".to_owned()));
+        };
+        let mut turns = self.history.split_off(position);
+        // Newest first, so a failure part way leaves the GM's memory a
+        // consistent prefix of the story, which the state is taken back to.
+        let mut restored = None;
+        let mut result = Ok(());
+        while let Some(mut turn) = turns.pop() {
+            let deleted = match self
+                .connection
+                .delete_messages(&mut turn.reply_messages)
+                .await
+            {
+                Ok(()) => {
+                    let mut input = vec![turn.input_message.clone()];
+                    self.connection.delete_messages(&mut input).await
                 }
-                let result = self.connection.send(AIInput::UserInput(content)).await;
-                match result {
-                    Ok(response) => {
-                        for update in response.updates.iter() {
-                            self.process_update(&update).await;
-                        }
-                        let _ = respond_to.send(Ok(()));
-                    }
-                    Err(error) => {
-                        let _ = respond_to.send(Err(error));
-                    }
+                Err(error) => Err(error),
+            };
+            if let Err(error) = deleted {
+                turns.push(turn);
+                self.history.append(&mut turns);
+                result = Err(error);
+                break;
+            }
+            restored = Some(turn.before);
+        }
+        if let Some(before) = restored {
+            self.restore(before);
+        }
+        result
+    }
+
+    /// Replaces the GM's response to the last action with a new one.
+    async fn reroll(&mut self) -> Result<(), GameError> {
+        let not_an_action = || {
+            GameError::Custom(
+                "Only the GM's response to the last action can be rerolled".to_owned(),
+            )
+        };
+        let mut turn = self.history.pop().ok_or_else(not_an_action)?;
+        let Some(actions) = turn.actions.clone() else {
+            self.history.push(turn);
+            return Err(not_an_action());
+        };
+        // Put back until the response is gone, so it can still be undone,
+        // rerolled or branched from.
+        if let Err(error) = self
+            .connection
+            .delete_messages(&mut turn.reply_messages)
+            .await
+        {
+            self.history.push(turn);
+            return Err(error);
+        }
+        let Turn {
+            before,
+            input_message,
+            ..
+        } = turn;
+        self.restore(before.clone());
+        {
+            let mut state = self.write_state();
+            for entry in actions.iter() {
+                self.push_log(&mut state, entry.clone());
+            }
+            // The action still counts, only the response is new.
+            state.turn += 1;
+        }
+
+        self.emit(GameEvent::TurnStarted);
+        match self.respond(&input_message).await {
+            Ok(reply_messages) => {
+                self.history.push(Turn {
+                    before,
+                    actions: Some(actions),
+                    input_message,
+                    reply_messages,
+                });
+                Ok(())
+            }
+            Err(error) => {
+                self.write_state().turn = before.turn;
+                self.mark_unsent(before.log.len());
+                Err(error)
+            }
+        }
+    }
+
+    /// Withdraws the last turn's GM and system entries, and everything they
+    /// did to the party and quest, then asks the GM for a replacement. The
+    /// GM's response is deleted from the thread, as for a reroll, so it
+    /// doesn't carry on from what was withdrawn.
+    async fn x_card(&mut self) -> Result<(), GameError> {
+        // The quest is only defined by the opening, so withdrawing that keeps
+        // it until the GM replaces it.
+        let opening = self.history.len() == 1;
+        let Some(turn) = self.history.last_mut() else {
+            return Err(GameError::Custom(
+                "There's no scene to X-card yet".to_owned(),
+            ));
+        };
+        self.connection
+            .delete_messages(&mut turn.reply_messages)
+            .await?;
+        let last = turn.before.clone();
+        let from = last.log.len();
+
+        let before = self.read_state().clone();
+        let mut retconned = before.clone();
+        retconned.party = last.party;
+        if !opening {
+            retconned.quest = last.quest;
+        }
+        retconned.scene = last.scene;
+        retconned.scene_title = last.scene_title;
+        for entry in retconned.log.iter_mut().skip(from) {
+            if !matches!(entry.player, GamePlayer::PC(_)) {
+                entry.retconned = true;
+            }
+        }
+        self.restore(retconned.clone());
+        // What was withdrawn stays withdrawn even if the GM doesn't respond,
+        // since they no longer remember it.
+        let (input_message, reply_messages) = self.send(AIInput::XCard).await?;
+        self.history.push(Turn {
+            before: retconned,
+            actions: None,
+            input_message,
+            reply_messages,
+        });
+        Ok(())
+    }
+
+    async fn level_up(
+        &mut self,
+        character: CharacterId,
+        improvement: Improvement,
+    ) -> Result<(), GameError> {
+        let (name, level, before) = {
+            let mut state = self.write_state();
+            let before = state.clone();
+            let state = &mut *state;
+            let Some(pc) = state.party.get_mut(character) else {
+                return Err(GameError::Custom("No such character".to_owned()));
+            };
+            if !pc.level_up(&improvement, &state.level_table) {
+                return Err(GameError::Custom("No level up available".to_owned()));
+            }
+            let name = pc.name().to_owned();
+            let level = pc.level();
+            self.emit(GameEvent::PartyUpdated(character));
+            self.push_log(
+                state,
+                GameLogEntry::new(
+                    GamePlayer::System,
+                    format!(
+                        "{} reached level {} and gained {}",
+                        name, level, improvement
+                    ),
+                ),
+            );
+            (name, level, before)
+        };
+        let sent = self
+            .send(AIInput::LevelUp {
+                character: name,
+                level,
+                improvement,
+            })
+            .await;
+        let (input_message, reply_messages) = match sent {
+            Ok(messages) => messages,
+            Err(error) => {
+                // The GM never heard of it, so the level is still there to take.
+                self.restore(before);
+                return Err(error);
+            }
+        };
+        self.history.push(Turn {
+            before,
+            actions: None,
+            input_message,
+            reply_messages,
+        });
+        Ok(())
+    }
+
+    /// Asks the GM how the quest ended, for the next quest in the campaign.
+    async fn conclude(&self) -> Result<QuestConclusion, GameError> {
+        let response = self.connection.send(AIInput::Conclude).await?;
+        response
+            .updates
+            .into_iter()
+            .find_map(|update| match update {
+                QuestUpdate::Conclusion(conclusion) => Some(conclusion),
+                _ => None,
+            })
+            .ok_or(GameError::UnexpectedResponse(
+                "No conclusion in response".to_owned(),
+            ))
+    }
+
+    /// Everything needed to carry on the game later, see `GameBuilder::resume`.
+    fn save(&self) -> SavedGame {
+        SavedGame {
+            state: self.read_state().clone(),
+            thread_id: self.connection.thread_id().to_owned(),
+            history: self.history.iter().map(SavedTurn::new).collect(),
+        }
+    }
+
+    /// Copies the game as it was after the turn that logged `entry` into a
+    /// new game, and starts running it.
+    async fn branch(&self, entry: usize) -> Result<GameHandle, GameError> {
+        let no_entry = || GameError::Custom(format!("No log entry {} to branch from", entry));
+        let mut state = {
+            let state = self.read_state();
+            if state.log.get(entry).ok_or_else(no_entry)?.unsent {
+                return Err(GameError::Custom(
+                    "Can't branch from an action the GM hasn't seen".to_owned(),
+                ));
+            }
+            state.clone()
+        };
+        // Each turn ends where the next begins.
+        let turn = self
+            .history
+            .iter()
+            .position(|turn| turn.before.log.len() > entry)
+            .unwrap_or(self.history.len());
+        let turn = turn.checked_sub(1).ok_or_else(no_entry)?;
+        if let Some(next) = self.history.get(turn + 1) {
+            state = next.before.clone();
+        }
+        state.log.retain(|entry| !entry.unsent);
+
+        let messages: Vec<_> = self.history[..=turn]
+            .iter()
+            .flat_map(|turn| std::iter::once(&turn.input_message).chain(&turn.reply_messages))
+            .cloned()
+            .collect();
+        let (connection, copies) = self.connection.branch(&messages).await?;
+        let mut copies = copies.into_iter();
+        let history = self.history[..=turn]
+            .iter()
+            .map(|turn| Turn {
+                before: turn.before.clone(),
+                actions: turn.actions.clone(),
+                input_message: copies.next().unwrap_or_default(),
+                reply_messages: copies.by_ref().take(turn.reply_messages.len()).collect(),
+            })
+            .collect();
+        info!("Branched at log entry {}", entry);
+
+        let (sender, receiver) = mpsc::channel(8);
+        let instance = GameInstance {
+            receiver,
+            connection,
+            state: Arc::new(RwLock::new(state)),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            history,
+        };
+        let handle = GameHandle::from_parts(
+            sender,
+            instance.state.clone(),
+            instance.events.clone(),
+            instance.connection.cancel().clone(),
+        );
+        tokio::spawn(run_game(instance));
+        Ok(handle)
+    }
+
+    /// Sends `input` to the GM and applies the updates they respond with,
+    /// returning the thread messages for the input and the response.
+    async fn send(&mut self, input: AIInput) -> Result<(String, Vec<String>), GameError> {
+        self.emit(GameEvent::TurnStarted);
+        let input_message = self.connection.add_message(input).await?;
+        let reply_messages = self.respond(&input_message).await?;
+        Ok((input_message, reply_messages))
+    }
+
+    /// Runs the GM on `input_message` and applies the updates they respond
+    /// with. If that fails the message is deleted again, so the GM never saw
+    /// an input the party sees as unsent.
+    async fn respond(&mut self, input_message: &str) -> Result<Vec<String>, GameError> {
+        match self.connection.run().await {
+            Ok(reply) => {
+                for update in reply.output.updates.iter() {
+                    self.process_update(update).await;
                 }
+                Ok(reply.message_ids)
+            }
+            Err(error) => {
+                let mut messages = vec![input_message.to_owned()];
+                if let Err(delete_error) = self.connection.delete_messages(&mut messages).await {
+                    error!("Could not delete unsent message: {}", delete_error);
+                }
+                Err(error)
             }
         }
     }
+
+    /// Marks the log entries from `from` onwards as never seen by the GM.
+    fn mark_unsent(&self, from: usize) {
+        let mut state = self.write_state();
+        for entry in state.log.iter_mut().skip(from) {
+            entry.unsent = true;
+        }
+        self.emit(GameEvent::LogChanged);
+    }
+
+    /// Replaces the whole state, e.g. to take back turns.
+    fn restore(&self, state: GameState) {
+        let quest = state.quest.clone();
+        let members = state.party.len();
+        *self.write_state() = state;
+        self.emit(GameEvent::LogChanged);
+        self.emit(GameEvent::QuestUpdated(quest));
+        for id in 0..members {
+            self.emit(GameEvent::PartyUpdated(id));
+        }
+    }
+
+    fn push_log(&self, state: &mut GameState, entry: GameLogEntry) {
+        state.log.push(entry.clone());
+        self.emit(GameEvent::LogEntryAdded {
+            index: state.log.len() - 1,
+            entry,
+        });
+    }
+
+    // A panic while the state was locked can only have come from this actor,
+    // which is still running, so the state is as good as it gets.
+    fn read_state(&self) -> RwLockReadGuard<'_, GameState> {
+        self.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_state(&self) -> RwLockWriteGuard<'_, GameState> {
+        self.state.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn emit(&self, event: GameEvent) {
+        // Nobody listening is fine.
+        let _ = self.events.send(event);
+    }
 }
 
-#[derive(Debug)]
+/// Everything about a game in progress: the party, the story so far and the
+/// quest they are on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
-    pub character: PlayerCharacter,
+    pub party: Vec<PlayerCharacter>,
     pub log: Vec<GameLogEntry>,
     pub quest: QuestDefinition,
+    pub level_table: LevelTable,
+    /// Saves from before adventure settings existed get the defaults.
+    #[serde(default)]
+    pub adventure: AdventureSettings,
+    /// Actions the party has sent the GM, batched actions counting as one.
+    #[serde(default)]
+    pub turn: u32,
+    /// The current scene, counting from 1, or 0 before the GM has set one.
+    #[serde(default)]
+    pub scene: u32,
+    #[serde(default)]
+    pub scene_title: String,
+    /// The campaign this quest continues, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub campaign: Option<CampaignContext>,
 }
 
 impl GameState {
-    fn new(character: PlayerCharacter) -> Self {
+    pub(crate) fn new(
+        party: Vec<PlayerCharacter>,
+        level_table: LevelTable,
+        adventure: AdventureSettings,
+        campaign: Option<CampaignContext>,
+    ) -> Self {
         Self {
-            character,
+            party,
             log: Vec::new(),
             quest: QuestDefinition::default(),
+            level_table,
+            adventure,
+            turn: 0,
+            scene: 0,
+            scene_title: String::new(),
+            campaign,
+        }
+    }
+
+    /// The scenes the quest is paced for, from the adventure's length.
+    pub fn target_scenes(&self) -> u32 {
+        self.adventure.length.scenes()
+    }
+
+    pub fn progress(&self) -> QuestProgress {
+        QuestProgress {
+            turn: self.turn,
+            scene: self.scene,
+            target_scenes: self.target_scenes(),
+        }
+    }
+
+    /// Finds a party member by name, ignoring case. With a single character in
+    /// the party the GM can't mean anyone else, so they are always returned.
+    pub fn member_id(&self, name: &str) -> Option<CharacterId> {
+        if self.party.len() == 1 {
+            return Some(0);
         }
+        self.party.iter().position(|pc| same_name(pc.name(), name))
     }
 }
 
+/// Whether two character names are the same to the GM, who may change their
+/// case or spacing.
+pub(crate) fn same_name(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
 /// Async context that passes each `GameMessage` through to the `GameInstance`.
-async fn run_game(mut instance: GameInstance) {
-    while let Some(msg) = instance.receiver.recv().await {
-        instance.handle_message(msg).await;
-    }
+/// Boxed because a game can spawn its branches from inside it.
+fn run_game(mut instance: GameInstance) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        while let Some(msg) = instance.receiver.recv().await {
+            instance.handle_message(msg).await;
+        }
+    })
 }
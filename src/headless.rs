@@ -0,0 +1,380 @@
+//! Headless automation over stdin/stdout, for scripts, bots and test harnesses.
+//!
+//! Each line on stdin is a `Command`; everything that happens is written to
+//! stdout as one JSON object per line, either an `Event` answering a command or
+//! a `GameEvent` from the game itself. Logging goes to stderr so it never mixes
+//! with the protocol.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+
+use micro_quest::catalog::Catalog;
+use micro_quest::character::{AbilityScores, PlayerCharacter, PlayerCharacterBuilder};
+use micro_quest::export::{self, ExportFormat};
+use micro_quest::game::{CharacterId, GameBuilder, GameError, GameHandle, GameState};
+use micro_quest::progression::Improvement;
+use micro_quest::roster::{Roster, RosterEntry};
+use micro_quest::save::Saves;
+use micro_quest::schema::AdventureSettings;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Adds a new character to the party. Anything left out is drawn at random
+    /// from `seed`, or from a random seed.
+    CreateCharacter {
+        name: Option<String>,
+        race: Option<String>,
+        class: Option<String>,
+        abilities: Option<AbilityScores>,
+        seed: Option<u64>,
+    },
+    /// Adds a character from the roster to the party.
+    LoadCharacter {
+        id: String,
+    },
+    /// Starts the quest with the party so far, with the default settings for
+    /// anything left out of `adventure`.
+    Start {
+        #[serde(default)]
+        adventure: AdventureSettings,
+    },
+    Input {
+        #[serde(default)]
+        character: CharacterId,
+        content: String,
+    },
+    LevelUp {
+        #[serde(default)]
+        character: CharacterId,
+        improvement: Improvement,
+    },
+    /// Carries on a saved game instead of starting a new one, the latest if
+    /// `id` is left out. The saved party replaces any added so far.
+    Resume {
+        id: Option<String>,
+    },
+    /// Writes the party back to the roster, adding any new characters, and
+    /// once the game has begun saves it too, over its last save if it has one.
+    Save,
+    /// Writes the story to `path`, or a file named after the quest in the
+    /// documents directory.
+    Export {
+        format: ExportFormat,
+        path: Option<PathBuf>,
+    },
+    Quit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    CharacterAdded {
+        character: CharacterId,
+        details: Box<PlayerCharacter>,
+    },
+    /// Sent once the GM has opened the quest. What the GM wrote comes as the
+    /// game's own events, like everything that happens in later turns.
+    Started,
+    /// Sent once a saved game has been loaded, with the story so far.
+    Resumed {
+        id: String,
+        state: Box<GameState>,
+    },
+    Saved {
+        /// The party's roster ids, in party order.
+        ids: Vec<String>,
+        /// The save the game was written to, if it has begun.
+        game: Option<String>,
+    },
+    Exported {
+        path: PathBuf,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Reads commands from stdin until `Quit` or the end of input.
+pub async fn run(catalog: Catalog) -> Result<(), GameError> {
+    let roster = Roster::open_default().unwrap_or_else(|error| {
+        log::error!("{}, using ./roster", error);
+        Roster::new("roster".into())
+    });
+    let saves = Saves::open_default().unwrap_or_else(|error| {
+        log::error!("{}, using ./saves", error);
+        Saves::new("saves".into())
+    });
+    let mut session = Session {
+        catalog,
+        roster,
+        saves,
+        party: Vec::new(),
+        game: None,
+        forwarder: None,
+        save_id: None,
+        campaign_id: None,
+    };
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|err| GameError::Custom(format!("Could not read stdin: {}", err)))?
+    {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let command = match serde_json::from_str(&line) {
+            Ok(command) => command,
+            Err(err) => {
+                emit(&Event::Error {
+                    message: format!("Invalid command: {}", err),
+                });
+                continue;
+            }
+        };
+        if let Command::Quit = command {
+            break;
+        }
+        if let Err(message) = session.handle(command).await {
+            emit(&Event::Error { message });
+        }
+    }
+    session.close().await;
+    Ok(())
+}
+
+fn emit(event: &impl Serialize) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = serde_json::to_writer(&mut stdout, event);
+    let _ = stdout.write_all(b"\n");
+    let _ = stdout.flush();
+}
+
+struct Session {
+    catalog: Catalog,
+    roster: Roster,
+    saves: Saves,
+    /// The party before the game starts, with each member's roster id.
+    party: Vec<(Option<String>, PlayerCharacter)>,
+    game: Option<GameHandle>,
+    /// Writes the game's events to stdout until the game stops.
+    forwarder: Option<JoinHandle<()>>,
+    /// The save the game was resumed from or last written to.
+    save_id: Option<String>,
+    /// The campaign a resumed game is part of, kept for its next save.
+    campaign_id: Option<String>,
+}
+
+impl Session {
+    async fn handle(&mut self, command: Command) -> Result<(), String> {
+        match command {
+            Command::CreateCharacter {
+                name,
+                race,
+                class,
+                abilities,
+                seed,
+            } => {
+                self.check_not_started()?;
+                let mut builder = PlayerCharacterBuilder::random(
+                    seed.unwrap_or_else(rand::random),
+                    &self.catalog,
+                );
+                if let Some(name) = name {
+                    builder = builder.with_name(name);
+                }
+                if let Some(race) = race {
+                    builder = builder.with_race(race);
+                }
+                if let Some(class) = class {
+                    builder = builder.with_class(class);
+                }
+                if let Some(abilities) = abilities {
+                    builder = builder.with_abilities(abilities);
+                }
+                let character = builder.build(&self.catalog).map_err(|e| e.to_string())?;
+                self.add_to_party(None, character);
+            }
+            Command::LoadCharacter { id } => {
+                self.check_not_started()?;
+                let entry = self.roster.load(&id).map_err(|e| e.to_string())?;
+                self.add_to_party(Some(entry.id), entry.character);
+            }
+            Command::Start { adventure } => {
+                self.check_not_started()?;
+                let mut party = self.party.iter().map(|(_, pc)| pc.clone());
+                let first = party.next().ok_or("Add a character before starting")?;
+                let mut builder = GameBuilder::new(first)
+                    .with_catalog(self.catalog.clone())
+                    .with_adventure(adventure);
+                for pc in party {
+                    builder = builder.with_party_member(pc);
+                }
+                let game = builder.build().await.map_err(|e| e.to_string())?;
+                self.forward(&game);
+                // Dropped if the GM can't open the quest, so `start` can be
+                // tried again. The game has already reported why.
+                if game.start().await.is_ok() {
+                    emit(&Event::Started);
+                    self.game = Some(game);
+                }
+            }
+            Command::Resume { id } => {
+                self.check_not_started()?;
+                let entry = match id {
+                    Some(id) => self.saves.load(&id).map_err(|e| e.to_string())?,
+                    None => self
+                        .saves
+                        .latest()
+                        .map_err(|e| e.to_string())?
+                        .ok_or("There are no saved games")?,
+                };
+                let game = GameBuilder::resume(entry.game)
+                    .with_catalog(self.catalog.clone())
+                    .build()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                self.forward(&game);
+                let state = game.state().read().unwrap().clone();
+                self.party = state
+                    .party
+                    .iter()
+                    .enumerate()
+                    .map(|(i, pc)| (entry.roster_ids.get(i).cloned().flatten(), pc.clone()))
+                    .collect();
+                self.save_id = Some(entry.id.clone());
+                self.campaign_id = entry.campaign_id;
+                self.game = Some(game);
+                emit(&Event::Resumed {
+                    id: entry.id,
+                    state: Box::new(state),
+                });
+            }
+            // Failed turns are reported by the game's own events.
+            Command::Input { character, content } => {
+                let game = self.game()?;
+                self.check_member(&game, character)?;
+                let _ = game.input(character, content).await;
+            }
+            Command::LevelUp {
+                character,
+                improvement,
+            } => {
+                let game = self.game()?;
+                self.check_member(&game, character)?;
+                let _ = game.level_up(character, improvement).await;
+            }
+            Command::Save => {
+                let party = match &self.game {
+                    Some(game) => game.state().read().unwrap().party.clone(),
+                    None => self.party.iter().map(|(_, pc)| pc.clone()).collect(),
+                };
+                let mut ids = Vec::new();
+                for ((id, _), character) in self.party.iter_mut().zip(party) {
+                    let entry = match id {
+                        Some(id) => {
+                            let entry = RosterEntry {
+                                id: id.clone(),
+                                character,
+                            };
+                            self.roster.save(&entry).map_err(|e| e.to_string())?;
+                            entry
+                        }
+                        None => self.roster.add(character).map_err(|e| e.to_string())?,
+                    };
+                    *id = Some(entry.id.clone());
+                    ids.push(entry.id);
+                }
+                let game = match &self.game {
+                    Some(game) => {
+                        let saved = game.save().await.map_err(|e| e.to_string())?;
+                        let roster_ids = self.party.iter().map(|(id, _)| id.clone()).collect();
+                        let entry = self
+                            .saves
+                            .save(
+                                self.save_id.clone(),
+                                roster_ids,
+                                self.campaign_id.clone(),
+                                saved,
+                            )
+                            .map_err(|e| e.to_string())?;
+                        self.save_id = Some(entry.id.clone());
+                        Some(entry.id)
+                    }
+                    None => None,
+                };
+                emit(&Event::Saved { ids, game });
+            }
+            Command::Export { format, path } => {
+                let game = self.game()?;
+                let state = game.state().read().unwrap();
+                let path = match path {
+                    Some(path) => {
+                        let document = export::export(&state, format).map_err(|e| e.to_string())?;
+                        std::fs::write(&path, document).map_err(|e| e.to_string())?;
+                        path
+                    }
+                    None => export::export_to_file(&state, format).map_err(|e| e.to_string())?,
+                };
+                emit(&Event::Exported { path });
+            }
+            Command::Quit => {}
+        }
+        Ok(())
+    }
+
+    fn add_to_party(&mut self, id: Option<String>, character: PlayerCharacter) {
+        emit(&Event::CharacterAdded {
+            character: self.party.len(),
+            details: Box::new(character.clone()),
+        });
+        self.party.push((id, character));
+    }
+
+    /// Writes `game`'s events to stdout from now until it stops.
+    fn forward(&mut self, game: &GameHandle) {
+        let events = game.subscribe();
+        self.forwarder = Some(tokio::spawn(async move {
+            tokio::pin!(events);
+            while let Some(event) = events.next().await {
+                emit(&event);
+            }
+        }));
+    }
+
+    /// Stops the game and waits until everything it sent has been written.
+    async fn close(mut self) {
+        self.game = None;
+        if let Some(forwarder) = self.forwarder.take() {
+            let _ = forwarder.await;
+        }
+    }
+
+    fn game(&self) -> Result<GameHandle, String> {
+        self.game
+            .clone()
+            .ok_or_else(|| "The game hasn't started".to_owned())
+    }
+
+    fn check_not_started(&self) -> Result<(), String> {
+        match self.game {
+            Some(_) => Err("The game has already started".to_owned()),
+            None => Ok(()),
+        }
+    }
+
+    fn check_member(&self, game: &GameHandle, character: CharacterId) -> Result<(), String> {
+        if character < game.state().read().unwrap().party.len() {
+            Ok(())
+        } else {
+            Err(format!("No party member {}", character))
+        }
+    }
+}
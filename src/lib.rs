@@ -0,0 +1,53 @@
+//! The uQuest game engine: an OpenAI-run GM for short D&D-esque quests.
+//!
+//! Build a party of `PlayerCharacter`s, hand them to a `GameBuilder`, and drive
+//! the resulting `GameHandle`:
+//!
+//! ```no_run
+//! use micro_quest::{Catalog, GameBuilder, PlayerCharacterBuilder};
+//!
+//! # async fn play() -> Result<(), Box<dyn std::error::Error>> {
+//! let catalog = Catalog::load();
+//! let pc = PlayerCharacterBuilder::new("Ann".to_owned())
+//!     .with_race("Elf".to_owned())
+//!     .with_class("Ranger".to_owned())
+//!     .build(&catalog)?;
+//! let game = GameBuilder::new(pc).with_catalog(catalog).build().await?;
+//! game.start().await?;
+//! game.input(0, "I search the room".to_owned()).await?;
+//! for entry in game.snapshot()?.log.iter() {
+//!     println!("{:?}: {}", entry.player, entry.content);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The messages exchanged with the GM are in `schema`; the GUI, terminal and
+//! automation frontends in the `micro-quest` binary are built on this API.
+
+pub mod campaign;
+pub mod catalog;
+pub mod character;
+mod conn;
+pub mod export;
+pub mod game;
+pub mod net;
+pub mod progression;
+pub mod roster;
+pub mod save;
+pub mod schema;
+mod store;
+
+pub use campaign::{Campaign, CampaignEntry, Campaigns};
+pub use catalog::Catalog;
+pub use character::{CharacterError, PlayerCharacter, PlayerCharacterBuilder};
+pub use export::{ExportError, ExportFormat};
+pub use game::{
+    CharacterId, GameBuilder, GameError, GameHandle, GameLogEntry, GamePlayer, GameState,
+    SavedGame, Verbosity,
+};
+pub use progression::{Improvement, LevelTable};
+pub use roster::{Roster, RosterEntry};
+pub use save::{SaveEntry, Saves};
+pub use schema::AdventureSettings;
+pub use store::StoreError;
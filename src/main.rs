@@ -1,13 +1,109 @@
-mod character;
-mod conn;
-mod game;
-mod schema;
+mod headless;
+mod tui;
 mod view;
 
+use micro_quest::net::{self, HostConfig};
+use micro_quest::Catalog;
+
+const USAGE: &str =
+    "Usage: micro-quest [--tui | --json | --serve [ADDR] | --host [ADDR] [--players N] [--turns free|round-robin|batch]]";
+
+/// How the binary should run, chosen from the command line.
+enum Mode {
+    Gui,
+    Tui,
+    Json,
+    Serve(String),
+    Host(HostConfig),
+}
+
 #[tokio::main]
 async fn main() -> Result<(), iced::Error> {
     env_logger::init();
-    view::main()
+
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Mode::Host(config)) => {
+            if let Err(error) = net::run_host(config, Catalog::load()).await {
+                log::error!("Host failed: {}", error);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Ok(Mode::Tui) => {
+            if let Err(error) = tui::run(Catalog::load()).await {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Ok(Mode::Json) => {
+            if let Err(error) = headless::run(Catalog::load()).await {
+                log::error!("{}", error);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Ok(Mode::Serve(addr)) => {
+            if let Err(error) = net::serve(addr, Catalog::load()).await {
+                log::error!("Server failed: {}", error);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Ok(Mode::Gui) => view::main(),
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Mode, String> {
+    let mut args = args.peekable();
+    let mut config: Option<HostConfig> = None;
+    let mut frontend = Mode::Gui;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => {
+                let mut host = HostConfig::default();
+                if let Some(addr) = args.next_if(|a| !a.starts_with("--")) {
+                    host.addr = addr;
+                }
+                config = Some(host);
+            }
+            "--tui" => frontend = Mode::Tui,
+            "--json" => frontend = Mode::Json,
+            "--serve" => {
+                let addr = args
+                    .next_if(|a| !a.starts_with("--"))
+                    .unwrap_or_else(|| net::DEFAULT_HTTP_ADDR.to_owned());
+                frontend = Mode::Serve(addr);
+            }
+            "--players" | "--turns" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                let host = config
+                    .as_mut()
+                    .ok_or_else(|| format!("{} only applies with --host", arg))?;
+                if arg == "--players" {
+                    host.players = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| format!("Invalid player count \"{}\"", value))?;
+                } else {
+                    host.turn_mode = value.parse()?;
+                }
+            }
+            _ => return Err(format!("Unknown argument \"{}\"", arg)),
+        }
+    }
+    match (config, frontend) {
+        (Some(config), Mode::Gui) => Ok(Mode::Host(config)),
+        (Some(_), _) => Err("--host can't be combined with --tui, --json or --serve".to_owned()),
+        (None, frontend) => Ok(frontend),
+    }
 }
 
 // OPENAI_API_KEY=xxxx cargo run
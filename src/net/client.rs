@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use log::{debug, error, info};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::character::PlayerCharacter;
+use crate::game::{
+    CharacterId, GameError, GameEvent, GameHandle, GameMessage, GameState, EVENT_CAPACITY,
+};
+
+use super::{ClientMessage, ServerMessage};
+
+/// Joins the game hosted at `addr` with `character`, waiting until the host
+/// starts the game. The returned handle forwards actions to the host and keeps
+/// its state in step with the host's snapshots; the id is the character's place
+/// in the party.
+pub async fn join(
+    addr: String,
+    character: PlayerCharacter,
+) -> Result<(GameHandle, CharacterId), GameError> {
+    let stream = TcpStream::connect(&addr).await.map_err(|err| {
+        error!("Could not connect to {}: {}", addr, err);
+        GameError::connection_failed(err)
+    })?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    write_message(
+        &mut writer,
+        &ClientMessage::Join {
+            character: Box::new(character),
+        },
+    )
+    .await?;
+
+    let mut character_id = None;
+    let state = loop {
+        match read_message(&mut lines).await? {
+            ServerMessage::Joined { character } => character_id = Some(character),
+            ServerMessage::Lobby { joined, expected } => {
+                info!("Waiting for players: {}/{} joined", joined.len(), expected)
+            }
+            ServerMessage::Snapshot { state, .. } => break *state,
+            ServerMessage::Error { message } => return Err(GameError::Custom(message)),
+            msg => debug!("Ignoring {:?} before the game started", msg),
+        }
+    };
+    let character_id = character_id
+        .ok_or_else(|| GameError::UnexpectedResponse("Host never confirmed join".to_owned()))?;
+
+    let state = Arc::new(RwLock::new(state));
+    let (sender, receiver) = mpsc::channel(8);
+    let (events, _) = broadcast::channel(EVENT_CAPACITY);
+    tokio::spawn(run_proxy(
+        receiver,
+        lines,
+        writer,
+        state.clone(),
+        events.clone(),
+        character_id,
+    ));
+    // Only the host can cancel the GM's turns.
+    let cancel = Arc::default();
+    Ok((
+        GameHandle::from_parts(sender, state, events, cancel),
+        character_id,
+    ))
+}
+
+/// Relays a `GameHandle`'s messages to the host, and the host's replies and
+/// snapshots back, until either side goes away.
+async fn run_proxy(
+    mut receiver: mpsc::Receiver<GameMessage>,
+    mut lines: Lines<BufReader<OwnedReadHalf>>,
+    mut writer: OwnedWriteHalf,
+    state: Arc<RwLock<GameState>>,
+    events: broadcast::Sender<GameEvent>,
+    character: CharacterId,
+) {
+    let mut pending: VecDeque<oneshot::Sender<Result<(), GameError>>> = VecDeque::new();
+    loop {
+        tokio::select! {
+            msg = receiver.recv() => {
+                let Some(msg) = msg else {
+                    break;
+                };
+                let (respond_to, request) = match msg {
+                    GameMessage::Start { respond_to } => {
+                        // The host started the game before we were handed a snapshot.
+                        let _ = respond_to.send(Ok(()));
+                        continue;
+                    }
+                    GameMessage::Input { respond_to, actions } => {
+                        let content = actions
+                            .into_iter()
+                            .map(|(_, content)| content)
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        (respond_to, ClientMessage::Submit { content })
+                    }
+                    GameMessage::LevelUp { respond_to, improvement, .. } => {
+                        (respond_to, ClientMessage::LevelUp { improvement })
+                    }
+                    GameMessage::XCard { respond_to } => (respond_to, ClientMessage::XCard),
+                    GameMessage::Undo { respond_to } | GameMessage::Reroll { respond_to } => {
+                        // Other players may already have acted on what the GM said.
+                        let _ = respond_to.send(Err(GameError::Custom(
+                            "Only the host can take back turns".to_owned(),
+                        )));
+                        continue;
+                    }
+                    GameMessage::Branch { respond_to, .. } => {
+                        let _ = respond_to.send(Err(GameError::Custom(
+                            "Only the host can branch the game".to_owned(),
+                        )));
+                        continue;
+                    }
+                    GameMessage::Save { respond_to } => {
+                        let _ = respond_to.send(Err(GameError::Custom(
+                            "Only the host can save the game".to_owned(),
+                        )));
+                        continue;
+                    }
+                    GameMessage::Conclude { respond_to } => {
+                        let _ = respond_to.send(Err(GameError::Custom(
+                            "Only the host can conclude the quest".to_owned(),
+                        )));
+                        continue;
+                    }
+                };
+                match write_message(&mut writer, &request).await {
+                    Ok(()) => pending.push_back(respond_to),
+                    Err(error) => {
+                        let _ = respond_to.send(Err(error));
+                    }
+                }
+            }
+            msg = read_message(&mut lines) => match msg {
+                Ok(ServerMessage::Snapshot { state: snapshot, .. }) => {
+                    // The host's snapshot replaces whatever a panic left behind.
+                    let mut current = state.write().unwrap_or_else(PoisonError::into_inner);
+                    let previous = std::mem::replace(&mut *current, *snapshot);
+                    emit_changes(&events, &previous, &current);
+                    drop(current);
+                    let _ = events.send(GameEvent::TurnFinished);
+                }
+                Ok(ServerMessage::TurnStarted { .. }) => {
+                    let _ = events.send(GameEvent::TurnStarted);
+                }
+                Ok(ServerMessage::TurnFinished { characters, error }) => {
+                    if !characters.contains(&character) {
+                        continue;
+                    }
+                    if let Some(error) = &error {
+                        let _ = events.send(GameEvent::Error(GameError::Custom(error.clone())));
+                    }
+                    for respond_to in pending.drain(..) {
+                        let result = match &error {
+                            Some(error) => Err(GameError::Custom(error.clone())),
+                            None => Ok(()),
+                        };
+                        let _ = respond_to.send(result);
+                    }
+                }
+                Ok(ServerMessage::Error { message }) => match pending.pop_front() {
+                    Some(respond_to) => {
+                        let _ = respond_to.send(Err(GameError::Custom(message)));
+                    }
+                    None => error!("Host error: {}", message),
+                },
+                Ok(msg) => debug!("Host: {:?}", msg),
+                Err(error) => {
+                    error!("Lost connection to host: {}", error);
+                    break;
+                }
+            }
+        }
+    }
+    for respond_to in pending {
+        let _ = respond_to.send(Err(GameError::ConnectionFailed { source: None }));
+    }
+}
+
+/// Sends the events that describe how the game went from `previous` to `current`.
+fn emit_changes(events: &broadcast::Sender<GameEvent>, previous: &GameState, current: &GameState) {
+    if current.log.starts_with(&previous.log) {
+        for (index, entry) in current.log.iter().enumerate().skip(previous.log.len()) {
+            let _ = events.send(GameEvent::LogEntryAdded {
+                index,
+                entry: entry.clone(),
+            });
+        }
+    } else {
+        let _ = events.send(GameEvent::LogChanged);
+    }
+    if current.quest != previous.quest {
+        let _ = events.send(GameEvent::QuestUpdated(current.quest.clone()));
+    }
+    for (id, pc) in current.party.iter().enumerate() {
+        if previous.party.get(id) != Some(pc) {
+            let _ = events.send(GameEvent::PartyUpdated(id));
+        }
+    }
+}
+
+async fn write_message(writer: &mut OwnedWriteHalf, msg: &ClientMessage) -> Result<(), GameError> {
+    let mut line = serde_json::to_string(msg)
+        .map_err(|err| GameError::send_failed("Could not encode message", err))?;
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|err| GameError::send_failed("Could not send to host", err))
+}
+
+async fn read_message(
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+) -> Result<ServerMessage, GameError> {
+    loop {
+        let line = lines
+            .next_line()
+            .await
+            .map_err(GameError::connection_failed)?
+            .ok_or(GameError::ConnectionFailed { source: None })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        return serde_json::from_str(&line).map_err(|err| {
+            GameError::UnexpectedResponse(format!("Invalid message from host: {}", err))
+        });
+    }
+}
@@ -0,0 +1,530 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::PoisonError;
+
+use log::{debug, error, info};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use crate::catalog::Catalog;
+use crate::character::PlayerCharacter;
+use crate::game::{same_name, CharacterId, GameBuilder, GameError, GameHandle};
+use crate::progression::LevelTable;
+
+use super::{ClientMessage, ServerMessage, TurnMode, DEFAULT_PORT};
+
+#[derive(Debug, Clone)]
+pub struct HostConfig {
+    pub addr: String,
+    pub players: usize,
+    pub turn_mode: TurnMode,
+    /// An OpenAI-compatible API base URL for the GM, instead of OpenAI's.
+    pub endpoint: Option<String>,
+}
+
+impl Default for HostConfig {
+    fn default() -> Self {
+        Self {
+            addr: format!("127.0.0.1:{}", DEFAULT_PORT),
+            players: 2,
+            turn_mode: TurnMode::default(),
+            endpoint: None,
+        }
+    }
+}
+
+type ClientId = usize;
+
+enum HostEvent {
+    Connected(ClientId, mpsc::UnboundedSender<ServerMessage>),
+    Message(ClientId, ClientMessage),
+    Disconnected(ClientId),
+    /// The GM has opened the quest, or failed to.
+    Started(Result<GameHandle, GameError>),
+    /// The GM is done with a turn involving `characters`, which began with
+    /// `before` entries in the log.
+    TurnFinished {
+        before: usize,
+        characters: Vec<CharacterId>,
+        result: Result<(), GameError>,
+    },
+}
+
+struct Client {
+    sender: mpsc::UnboundedSender<ServerMessage>,
+    character: Option<CharacterId>,
+}
+
+/// Listens on `config.addr`, waits for `config.players` characters to join, then
+/// runs the game until every client has left.
+pub async fn run_host(config: HostConfig, catalog: Catalog) -> Result<(), GameError> {
+    let listener = TcpListener::bind(&config.addr)
+        .await
+        .map_err(|err| GameError::Custom(format!("Could not bind {}: {}", config.addr, err)))?;
+    info!(
+        "Hosting on {}, waiting for {} players",
+        config.addr, config.players
+    );
+
+    let (events, mut receiver) = mpsc::unbounded_channel();
+    let mut host = Host::new(config, catalog, events.clone());
+    let mut next_client: ClientId = 0;
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    info!("Connection from {}", peer);
+                    tokio::spawn(run_connection(next_client, stream, events.clone()));
+                    next_client += 1;
+                }
+                Err(err) => error!("Accept failed: {}", err),
+            },
+            Some(event) = receiver.recv() => {
+                host.handle_event(event);
+                if host.game.is_some() && host.clients.is_empty() {
+                    info!("All players have left, shutting down");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Forwards lines from a client to the host as events, and the host's
+/// messages back to the client.
+async fn run_connection(id: ClientId, stream: TcpStream, events: mpsc::UnboundedSender<HostEvent>) {
+    let (reader, mut writer) = stream.into_split();
+    let (sender, mut outgoing) = mpsc::unbounded_channel::<ServerMessage>();
+    let _ = events.send(HostEvent::Connected(id, sender.clone()));
+
+    tokio::spawn(async move {
+        while let Some(msg) = outgoing.recv().await {
+            let mut line = match serde_json::to_string(&msg) {
+                Ok(line) => line,
+                Err(err) => {
+                    error!("Could not encode message: {}", err);
+                    continue;
+                }
+            };
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(msg) => {
+                let _ = events.send(HostEvent::Message(id, msg));
+            }
+            Err(err) => {
+                let _ = sender.send(ServerMessage::Error {
+                    message: format!("Invalid message: {}", err),
+                });
+            }
+        }
+    }
+    let _ = events.send(HostEvent::Disconnected(id));
+}
+
+struct Host {
+    config: HostConfig,
+    catalog: Catalog,
+    /// Where the GM's turns report back once they're over.
+    events: mpsc::UnboundedSender<HostEvent>,
+    clients: HashMap<ClientId, Client>,
+    lobby: Vec<PlayerCharacter>,
+    game: Option<GameHandle>,
+    /// Whether the GM is opening the quest or responding to a turn.
+    busy: bool,
+    next_turn: CharacterId,
+    batch: BTreeMap<CharacterId, String>,
+}
+
+impl Host {
+    fn new(config: HostConfig, catalog: Catalog, events: mpsc::UnboundedSender<HostEvent>) -> Self {
+        Self {
+            config,
+            catalog,
+            events,
+            clients: HashMap::new(),
+            lobby: Vec::new(),
+            game: None,
+            busy: false,
+            next_turn: 0,
+            batch: BTreeMap::new(),
+        }
+    }
+
+    fn handle_event(&mut self, event: HostEvent) {
+        match event {
+            HostEvent::Connected(id, sender) => {
+                self.clients.insert(
+                    id,
+                    Client {
+                        sender,
+                        character: None,
+                    },
+                );
+            }
+            HostEvent::Disconnected(id) => {
+                if let Some(character) = self.clients.remove(&id).and_then(|c| c.character) {
+                    info!("Character {} disconnected", character);
+                    if self.game.is_some() {
+                        self.batch.remove(&character);
+                        if self.next_turn == character {
+                            self.advance_turn();
+                        }
+                        self.try_run_batch();
+                    } else if !self.busy {
+                        self.prune_lobby();
+                    }
+                }
+            }
+            HostEvent::Message(id, msg) => {
+                debug!("Client {}: {:?}", id, msg);
+                match msg {
+                    ClientMessage::Join { character } => self.join(id, *character),
+                    ClientMessage::Submit { content } => self.submit(id, content),
+                    ClientMessage::LevelUp { improvement } => {
+                        let Some((game, character)) = self.ready(id) else {
+                            return;
+                        };
+                        self.spawn_turn(vec![character], async move {
+                            game.level_up(character, improvement).await
+                        });
+                    }
+                    ClientMessage::XCard => {
+                        let Some((game, character)) = self.ready(id) else {
+                            return;
+                        };
+                        info!("X-card played");
+                        self.spawn_turn(vec![character], async move { game.x_card().await });
+                    }
+                }
+            }
+            HostEvent::Started(result) => {
+                self.busy = false;
+                match result {
+                    Ok(game) => {
+                        self.lobby.clear();
+                        self.game = Some(game);
+                        // Whoever left while the quest was opening stays in
+                        // the party, but doesn't get a turn.
+                        if !self.connected_characters().contains(&self.next_turn) {
+                            self.advance_turn();
+                        }
+                        self.finish_turn(0, Vec::new(), Ok(()));
+                    }
+                    Err(error) => {
+                        error!("Could not start game: {}", error);
+                        self.broadcast(Self::error(&format!("Could not start game: {}", error)));
+                        self.prune_lobby();
+                    }
+                }
+            }
+            HostEvent::TurnFinished {
+                before,
+                characters,
+                result,
+            } => {
+                self.busy = false;
+                self.finish_turn(before, characters, result);
+            }
+        }
+    }
+
+    fn join(&mut self, id: ClientId, character: PlayerCharacter) {
+        if self.game.is_some() {
+            self.send(id, Self::error("The game has already started"));
+            return;
+        }
+        if self.busy {
+            self.send(id, Self::error("The game is starting"));
+            return;
+        }
+        if self.character(id).is_some() {
+            self.send(id, Self::error("Already joined"));
+            return;
+        }
+        if let Err(error) = character.validate(&self.catalog, &LevelTable::default()) {
+            self.send(id, Self::error(&error.to_string()));
+            return;
+        }
+        if self
+            .lobby
+            .iter()
+            .any(|pc| same_name(pc.name(), character.name()))
+        {
+            let message = format!("A character called {} has already joined", character.name());
+            self.send(id, Self::error(&message));
+            return;
+        }
+        info!("{} joined", character.name());
+        self.lobby.push(character);
+        let character_id = self.lobby.len() - 1;
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.character = Some(character_id);
+        }
+        self.send(
+            id,
+            ServerMessage::Joined {
+                character: character_id,
+            },
+        );
+        self.broadcast_lobby();
+
+        if self.lobby.len() >= self.config.players {
+            self.start_game();
+        }
+    }
+
+    /// Starts the game with the lobby as the party, reporting back with
+    /// `HostEvent::Started`. If the GM can't be reached the party stays in the
+    /// lobby, and the next join tries again.
+    fn start_game(&mut self) {
+        info!("Party complete, starting game");
+        let mut party = self.lobby.iter().cloned();
+        let Some(first) = party.next() else {
+            return;
+        };
+        let mut builder = GameBuilder::new(first).with_catalog(self.catalog.clone());
+        for pc in party {
+            builder = builder.with_party_member(pc);
+        }
+        if let Some(endpoint) = &self.config.endpoint {
+            builder = builder.with_endpoint(endpoint.clone());
+        }
+        self.busy = true;
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = match builder.build().await {
+                Ok(game) => game.start().await.map(|()| game),
+                Err(error) => Err(error),
+            };
+            let _ = events.send(HostEvent::Started(result));
+        });
+    }
+
+    /// Drops characters whose players have left the lobby. Nobody has seen
+    /// the party yet, so the others' ids can still move.
+    fn prune_lobby(&mut self) {
+        let kept = self.connected_characters();
+        if kept.len() == self.lobby.len() {
+            return;
+        }
+        let lobby = std::mem::take(&mut self.lobby);
+        self.lobby = kept
+            .iter()
+            .map(|character| lobby[*character].clone())
+            .collect();
+        for client in self.clients.values_mut() {
+            let Some(character) = client.character.as_mut() else {
+                continue;
+            };
+            let moved = kept.iter().position(|kept| kept == character);
+            if let Some(moved) = moved.filter(|moved| moved != character) {
+                *character = moved;
+                let _ = client
+                    .sender
+                    .send(ServerMessage::Joined { character: moved });
+            }
+        }
+        self.broadcast_lobby();
+    }
+
+    /// The game and the client's character, if they can act now. Otherwise
+    /// the client is told why not.
+    fn ready(&self, id: ClientId) -> Option<(GameHandle, CharacterId)> {
+        let (Some(game), Some(character)) = (self.game.clone(), self.character(id)) else {
+            self.send(id, Self::error("The game hasn't started"));
+            return None;
+        };
+        if self.busy {
+            self.send(id, Self::error("Wait for the GM to finish the turn"));
+            return None;
+        }
+        Some((game, character))
+    }
+
+    fn submit(&mut self, id: ClientId, content: String) {
+        let Some((game, character)) = self.ready(id) else {
+            return;
+        };
+        match self.config.turn_mode {
+            TurnMode::Free => self.run_turn(game, vec![(character, content)]),
+            TurnMode::RoundRobin => {
+                if character != self.next_turn {
+                    let name = {
+                        let state = game.state().read().unwrap_or_else(PoisonError::into_inner);
+                        state.party[self.next_turn].name().to_owned()
+                    };
+                    self.send(id, Self::error(&format!("It's {}'s turn", name)));
+                    return;
+                }
+                self.advance_turn();
+                self.run_turn(game, vec![(character, content)]);
+            }
+            TurnMode::Batch => {
+                self.batch.insert(character, content);
+                if !self.try_run_batch() {
+                    let waiting_for = self
+                        .connected_characters()
+                        .into_iter()
+                        .filter(|c| !self.batch.contains_key(c))
+                        .collect();
+                    self.broadcast(ServerMessage::Queued { waiting_for });
+                }
+            }
+        }
+    }
+
+    /// Runs the batched turn if every connected character has acted.
+    fn try_run_batch(&mut self) -> bool {
+        let Some(game) = self.game.clone() else {
+            return false;
+        };
+        if self.busy
+            || self.batch.is_empty()
+            || self
+                .connected_characters()
+                .iter()
+                .any(|c| !self.batch.contains_key(c))
+        {
+            return false;
+        }
+        let actions = std::mem::take(&mut self.batch).into_iter().collect();
+        self.run_turn(game, actions);
+        true
+    }
+
+    fn run_turn(&mut self, game: GameHandle, actions: Vec<(CharacterId, String)>) {
+        self.broadcast(ServerMessage::TurnStarted {
+            actions: actions.clone(),
+        });
+        let characters = actions.iter().map(|(character, _)| *character).collect();
+        self.spawn_turn(characters, async move { game.input_batch(actions).await });
+    }
+
+    /// Runs a GM turn involving `characters` in the background, reporting back
+    /// with `HostEvent::TurnFinished`, so clients are still heard meanwhile.
+    fn spawn_turn(
+        &mut self,
+        characters: Vec<CharacterId>,
+        turn: impl Future<Output = Result<(), GameError>> + Send + 'static,
+    ) {
+        self.busy = true;
+        let before = self.log_len();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = turn.await;
+            let _ = events.send(HostEvent::TurnFinished {
+                before,
+                characters,
+                result,
+            });
+        });
+    }
+
+    /// Sends everything logged since `before`, the turn's result and a fresh
+    /// snapshot to every client.
+    fn finish_turn(
+        &mut self,
+        before: usize,
+        characters: Vec<CharacterId>,
+        result: Result<(), GameError>,
+    ) {
+        let Some(game) = self.game.clone() else {
+            return;
+        };
+        let state = match game.snapshot() {
+            Ok(state) => state,
+            Err(error) => {
+                self.broadcast(ServerMessage::TurnFinished {
+                    characters,
+                    error: Some(error.to_string()),
+                });
+                return;
+            }
+        };
+        for entry in state.log.iter().skip(before) {
+            self.broadcast(ServerMessage::LogEntry {
+                entry: entry.clone(),
+            });
+        }
+        self.broadcast(ServerMessage::TurnFinished {
+            characters,
+            error: result.err().map(|error| error.to_string()),
+        });
+        let next_turn = (self.config.turn_mode == TurnMode::RoundRobin).then_some(self.next_turn);
+        self.broadcast(ServerMessage::Snapshot {
+            state: Box::new(state),
+            next_turn,
+        });
+    }
+
+    fn advance_turn(&mut self) {
+        let connected = self.connected_characters();
+        self.next_turn = connected
+            .iter()
+            .copied()
+            .find(|c| *c > self.next_turn)
+            .or_else(|| connected.first().copied())
+            .unwrap_or(0);
+    }
+
+    fn connected_characters(&self) -> Vec<CharacterId> {
+        let mut characters: Vec<_> = self
+            .clients
+            .values()
+            .filter_map(|client| client.character)
+            .collect();
+        characters.sort_unstable();
+        characters
+    }
+
+    fn character(&self, id: ClientId) -> Option<CharacterId> {
+        self.clients.get(&id).and_then(|client| client.character)
+    }
+
+    fn log_len(&self) -> usize {
+        self.game.as_ref().map_or(0, |game| {
+            game.state()
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .log
+                .len()
+        })
+    }
+
+    fn broadcast_lobby(&self) {
+        self.broadcast(ServerMessage::Lobby {
+            joined: self.lobby.iter().map(|pc| pc.name().to_owned()).collect(),
+            expected: self.config.players,
+        });
+    }
+
+    fn send(&self, id: ClientId, msg: ServerMessage) {
+        if let Some(client) = self.clients.get(&id) {
+            let _ = client.sender.send(msg);
+        }
+    }
+
+    fn broadcast(&self, msg: ServerMessage) {
+        for client in self.clients.values() {
+            let _ = client.sender.send(msg.clone());
+        }
+    }
+
+    fn error(message: &str) -> ServerMessage {
+        ServerMessage::Error {
+            message: message.to_owned(),
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::catalog::Catalog;
+use crate::character::PlayerCharacter;
+use crate::export::ExportFormat;
+use crate::game::{
+    CharacterId, GameBuilder, GameError, GameEvent, GameHandle, GameLogEntry, GameState,
+};
+use crate::progression::{Improvement, LevelTable};
+use crate::roster::{Roster, RosterEntry};
+use crate::schema::AdventureSettings;
+use crate::store::StoreError;
+
+pub const DEFAULT_HTTP_ADDR: &str = "127.0.0.1:8080";
+
+#[derive(Debug, Deserialize)]
+struct CreateSession {
+    /// Roster ids of characters to add to the party. Anything but a roster
+    /// slug like `ann-2` is refused with 400 Bad Request.
+    #[serde(default)]
+    roster: Vec<String>,
+    /// Characters to add to the party after those from the roster. Any that
+    /// fail `PlayerCharacter::validate` are refused with 400 Bad Request.
+    #[serde(default)]
+    characters: Vec<PlayerCharacter>,
+    /// The kind of quest to run, with defaults for anything left out.
+    #[serde(default)]
+    adventure: AdventureSettings,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionCreated {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InputRequest {
+    #[serde(default)]
+    character: CharacterId,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LevelUpRequest {
+    #[serde(default)]
+    character: CharacterId,
+    improvement: Improvement,
+}
+
+#[derive(Debug, Deserialize)]
+struct BranchRequest {
+    /// The log entry to carry on from.
+    entry: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    format: ExportFormat,
+}
+
+#[derive(Debug, Serialize)]
+struct TurnResponse {
+    entries: Vec<GameLogEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct SaveResponse {
+    ids: Vec<String>,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.1 });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+impl From<GameError> for ApiError {
+    fn from(error: GameError) -> Self {
+        let status = match error {
+            // Asking for something the game can't do in its current state.
+            GameError::Custom(_) | GameError::Cancelled => StatusCode::CONFLICT,
+            GameError::MissingApiKey => StatusCode::SERVICE_UNAVAILABLE,
+            GameError::GameStopped => StatusCode::GONE,
+            GameError::ConnectionFailed { .. }
+            | GameError::SendFailed { .. }
+            | GameError::UnexpectedResponse(_)
+            | GameError::RefusalResponse(_) => StatusCode::BAD_GATEWAY,
+        };
+        ApiError(status, error.to_string())
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(error: StoreError) -> Self {
+        let status = match error {
+            StoreError::InvalidId(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, error.to_string())
+    }
+}
+
+struct Server {
+    catalog: Catalog,
+    roster: Roster,
+    sessions: Mutex<HashMap<String, Arc<Session>>>,
+}
+
+struct Session {
+    game: GameHandle,
+    /// Roster ids of the party, in party order, once they have one.
+    ids: Mutex<Vec<Option<String>>>,
+    /// Held for a whole turn, so the events a turn collects are all its own.
+    turn: tokio::sync::Mutex<()>,
+}
+
+impl Session {
+    fn new(game: GameHandle, ids: Vec<Option<String>>) -> Self {
+        Session {
+            game,
+            ids: Mutex::new(ids),
+            turn: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Runs `turn` against the game, returning the log entries it added.
+    async fn run_turn<F>(
+        &self,
+        turn: impl FnOnce(GameHandle) -> F,
+    ) -> Result<Vec<GameLogEntry>, GameError>
+    where
+        F: std::future::Future<Output = Result<(), GameError>>,
+    {
+        let _turn = self.turn.lock().await;
+        let events = self.game.subscribe();
+        tokio::pin!(events);
+        turn(self.game.clone()).await?;
+
+        // Everything the turn sent is already queued, up to its
+        // `TurnFinished`. Falling behind only loses the oldest, never that.
+        let mut entries = Vec::new();
+        while let Some(event) = events.next().await {
+            match event {
+                GameEvent::LogEntryAdded { entry, .. } => entries.push(entry),
+                GameEvent::TurnFinished => break,
+                _ => {}
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Serves the HTTP API on `addr` until the process is stopped.
+pub async fn serve(addr: String, catalog: Catalog) -> Result<(), GameError> {
+    let roster = Roster::open_default().unwrap_or_else(|error| {
+        error!("{}, using ./roster", error);
+        Roster::new("roster".into())
+    });
+    let server = Arc::new(Server {
+        catalog,
+        roster,
+        sessions: Mutex::new(HashMap::new()),
+    });
+    let app = Router::new()
+        .route("/sessions", get(list_sessions).post(create_session))
+        .route("/sessions/{id}", get(get_state).delete(delete_session))
+        .route("/sessions/{id}/input", post(input))
+        .route("/sessions/{id}/level_up", post(level_up))
+        .route("/sessions/{id}/save", post(save))
+        .route("/sessions/{id}/branch", post(branch))
+        .route("/sessions/{id}/export", get(export))
+        .route("/sessions/{id}/events", get(events))
+        .with_state(server);
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(|err| GameError::Custom(format!("Could not bind {}: {}", addr, err)))?;
+    info!("Serving the HTTP API on http://{}", addr);
+    axum::serve(listener, app)
+        .await
+        .map_err(|err| GameError::Custom(format!("Server failed: {}", err)))
+}
+
+fn session(server: &Server, id: &str) -> Result<Arc<Session>, ApiError> {
+    server
+        .sessions
+        .lock()
+        .unwrap()
+        .get(id)
+        .cloned()
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("No session \"{}\"", id)))
+}
+
+fn check_member(session: &Session, character: CharacterId) -> Result<(), ApiError> {
+    if character < session.game.snapshot()?.party.len() {
+        Ok(())
+    } else {
+        Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("No party member {}", character),
+        ))
+    }
+}
+
+async fn list_sessions(State(server): State<Arc<Server>>) -> Json<Vec<String>> {
+    Json(server.sessions.lock().unwrap().keys().cloned().collect())
+}
+
+/// Builds the game and starts the quest in the background; follow
+/// `/sessions/{id}/events` to see it begin.
+async fn create_session(
+    State(server): State<Arc<Server>>,
+    Json(request): Json<CreateSession>,
+) -> Result<(StatusCode, Json<SessionCreated>), ApiError> {
+    let mut ids = Vec::new();
+    let mut party = Vec::new();
+    for id in request.roster {
+        // An id that can't be loaded is the client's mistake, including one the
+        // store refuses because it could reach outside the roster.
+        let entry = server
+            .roster
+            .load(&id)
+            .map_err(|error| ApiError(StatusCode::BAD_REQUEST, error.to_string()))?;
+        ids.push(Some(entry.id));
+        party.push(entry.character);
+    }
+    for character in request.characters {
+        character
+            .validate(&server.catalog, &LevelTable::default())
+            .map_err(|error| ApiError(StatusCode::BAD_REQUEST, error.to_string()))?;
+        ids.push(None);
+        party.push(character);
+    }
+
+    let mut party = party.into_iter();
+    let first = party.next().ok_or_else(|| {
+        ApiError(
+            StatusCode::BAD_REQUEST,
+            "The party needs at least one character".to_owned(),
+        )
+    })?;
+    let mut builder = GameBuilder::new(first)
+        .with_catalog(server.catalog.clone())
+        .with_adventure(request.adventure);
+    for pc in party {
+        builder = builder.with_party_member(pc);
+    }
+    let game = builder.build().await?;
+
+    let session = Arc::new(Session::new(game, ids));
+    let id = format!("{:016x}", rand::random::<u64>());
+    server
+        .sessions
+        .lock()
+        .unwrap()
+        .insert(id.clone(), session.clone());
+    info!("Created session {}", id);
+
+    tokio::spawn(async move {
+        if let Err(error) = session
+            .run_turn(|game| async move { game.start().await })
+            .await
+        {
+            error!("Could not start quest: {}", error);
+        }
+    });
+    Ok((StatusCode::CREATED, Json(SessionCreated { id })))
+}
+
+async fn get_state(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+) -> Result<Json<GameState>, ApiError> {
+    let session = session(&server, &id)?;
+    let state = session.game.snapshot()?;
+    Ok(Json(state))
+}
+
+async fn delete_session(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match server.sessions.lock().unwrap().remove(&id) {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("No session \"{}\"", id),
+        )),
+    }
+}
+
+async fn input(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+    Json(request): Json<InputRequest>,
+) -> Result<Json<TurnResponse>, ApiError> {
+    let session = session(&server, &id)?;
+    check_member(&session, request.character)?;
+    let entries = session
+        .run_turn(|game| async move { game.input(request.character, request.content).await })
+        .await?;
+    Ok(Json(TurnResponse { entries }))
+}
+
+async fn level_up(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+    Json(request): Json<LevelUpRequest>,
+) -> Result<Json<TurnResponse>, ApiError> {
+    let session = session(&server, &id)?;
+    check_member(&session, request.character)?;
+    let entries = session
+        .run_turn(|game| async move { game.level_up(request.character, request.improvement).await })
+        .await?;
+    Ok(Json(TurnResponse { entries }))
+}
+
+/// Forks the session at a log entry into a new one, which starts where the
+/// original was after that turn and carries on independently.
+async fn branch(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+    Json(request): Json<BranchRequest>,
+) -> Result<(StatusCode, Json<SessionCreated>), ApiError> {
+    let parent = session(&server, &id)?;
+    let game = parent.game.branch(request.entry).await?;
+    let ids = parent.ids.lock().unwrap().clone();
+    let session = Arc::new(Session::new(game, ids));
+    let id = format!("{:016x}", rand::random::<u64>());
+    server.sessions.lock().unwrap().insert(id.clone(), session);
+    info!("Branched session {} at entry {}", id, request.entry);
+    Ok((StatusCode::CREATED, Json(SessionCreated { id })))
+}
+
+/// Renders the story as a document, e.g. `?format=epub`.
+async fn export(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let session = session(&server, &id)?;
+    let document = session
+        .game
+        .export(query.format)
+        .map_err(|error| ApiError(StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, query.format.mime_type())], document).into_response())
+}
+
+/// Writes the party back to the roster, adding characters that aren't in it yet.
+async fn save(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+) -> Result<Json<SaveResponse>, ApiError> {
+    let session = session(&server, &id)?;
+    let party = session.game.snapshot()?.party;
+    let mut ids = session.ids.lock().unwrap();
+    let mut saved = Vec::new();
+    for (id, character) in ids.iter_mut().zip(party) {
+        let entry = match id {
+            Some(id) => {
+                let entry = RosterEntry {
+                    id: id.clone(),
+                    character,
+                };
+                server.roster.save(&entry).map(|_| entry)
+            }
+            None => server.roster.add(character),
+        }?;
+        *id = Some(entry.id.clone());
+        saved.push(entry.id);
+    }
+    Ok(Json(SaveResponse { ids: saved }))
+}
+
+async fn events(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let session = session(&server, &id)?;
+    // A lagging client skips what it missed; `GET /sessions/{id}` catches up.
+    let stream = session.game.subscribe().map(|event| {
+        Ok(Event::default()
+            .json_data(&event)
+            .unwrap_or_else(|_| Event::default()))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
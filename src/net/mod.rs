@@ -0,0 +1,106 @@
+//! Hosting a game for players on other machines, and joining one.
+//!
+//! Host and clients exchange newline-delimited JSON over TCP: each line is one
+//! `ClientMessage` or `ServerMessage`. Separately, `serve` exposes any number
+//! of sessions over HTTP for web frontends.
+
+use serde::{Deserialize, Serialize};
+
+use crate::character::PlayerCharacter;
+use crate::game::{CharacterId, GameLogEntry, GameState};
+use crate::progression::Improvement;
+
+mod client;
+mod host;
+mod http;
+#[cfg(test)]
+mod tests;
+
+pub use client::join;
+pub use host::{run_host, HostConfig};
+pub use http::{serve, DEFAULT_HTTP_ADDR};
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// How the host turns players' actions into GM turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TurnMode {
+    /// Every action is its own GM turn, in the order they arrive.
+    #[default]
+    Free,
+    /// Characters take turns in party order.
+    RoundRobin,
+    /// One action is collected from every connected character, then they are
+    /// all sent to the GM as a single turn.
+    Batch,
+}
+
+impl std::str::FromStr for TurnMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "free" => Ok(TurnMode::Free),
+            "round-robin" => Ok(TurnMode::RoundRobin),
+            "batch" => Ok(TurnMode::Batch),
+            _ => Err(format!(
+                "Unknown turn mode \"{}\", expected free, round-robin or batch",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Join {
+        character: Box<PlayerCharacter>,
+    },
+    Submit {
+        content: String,
+    },
+    LevelUp {
+        improvement: Improvement,
+    },
+    /// Plays the X-card on the last turn, for everyone.
+    XCard,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Sent to a client once its character has a place in the party.
+    Joined {
+        character: CharacterId,
+    },
+    /// Who has joined so far, while waiting for the party to fill.
+    Lobby {
+        joined: Vec<String>,
+        expected: usize,
+    },
+    /// The full game state, sent when the game starts and after every turn.
+    Snapshot {
+        state: Box<GameState>,
+        next_turn: Option<CharacterId>,
+    },
+    LogEntry {
+        entry: GameLogEntry,
+    },
+    TurnStarted {
+        actions: Vec<(CharacterId, String)>,
+    },
+    /// An action was accepted but the turn waits for other characters.
+    Queued {
+        waiting_for: Vec<CharacterId>,
+    },
+    /// A turn involving `characters` is over.
+    TurnFinished {
+        characters: Vec<CharacterId>,
+        error: Option<String>,
+    },
+    Error {
+        message: String,
+    },
+}
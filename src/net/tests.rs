@@ -0,0 +1,377 @@
+//! The multiplayer protocol over loopback: the real host with a GM that can't
+//! run turns, and the real client against a scripted host.
+
+use std::time::Duration;
+
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_stream::StreamExt;
+
+use crate::catalog::Catalog;
+use crate::character::{PlayerCharacter, PlayerCharacterBuilder};
+use crate::game::{GameEvent, GameLogEntry, GamePlayer, GameState};
+use crate::progression::LevelTable;
+use crate::schema::{AdventureSettings, QuestDefinition};
+
+use super::*;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Held by tests that set or clear `OPENAI_API_KEY`, which every test shares.
+static API_KEY: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn character(name: &str) -> PlayerCharacter {
+    PlayerCharacterBuilder::new(name.to_owned())
+        .with_race("Elf".to_owned())
+        .with_class("Ranger".to_owned())
+        .build(&Catalog::bundled())
+        .unwrap()
+}
+
+fn snapshot(party: Vec<PlayerCharacter>, log: Vec<GameLogEntry>) -> ServerMessage {
+    ServerMessage::Snapshot {
+        state: Box::new(GameState {
+            party,
+            log,
+            quest: QuestDefinition::default(),
+            level_table: LevelTable::default(),
+            adventure: AdventureSettings::default(),
+            turn: 0,
+            scene: 0,
+            scene_title: String::new(),
+            campaign: None,
+        }),
+        next_turn: None,
+    }
+}
+
+/// One end of a connection, speaking one JSON message per line.
+struct Peer {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Peer {
+    fn new(stream: TcpStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    /// Connects to `addr`, waiting for a host that is still starting up.
+    async fn connect(addr: &str) -> Self {
+        for _ in 0..50 {
+            if let Ok(stream) = TcpStream::connect(addr).await {
+                return Self::new(stream);
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Nothing listening on {}", addr);
+    }
+
+    async fn send(&mut self, msg: &impl Serialize) {
+        let mut line = serde_json::to_string(msg).unwrap();
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await.unwrap();
+    }
+
+    async fn recv<T: DeserializeOwned>(&mut self) -> T {
+        let line = tokio::time::timeout(TIMEOUT, self.lines.next_line())
+            .await
+            .expect("Timed out waiting for a message")
+            .unwrap()
+            .expect("Connection closed");
+        serde_json::from_str(&line).unwrap()
+    }
+
+    /// Skips messages until an error, returning its message.
+    async fn recv_error(&mut self) -> String {
+        loop {
+            if let ServerMessage::Error { message } = self.recv().await {
+                return message;
+            }
+        }
+    }
+
+    async fn join(&mut self, name: &str) {
+        self.send(&ClientMessage::Join {
+            character: Box::new(character(name)),
+        })
+        .await;
+    }
+}
+
+/// An address nothing is listening on yet.
+async fn free_addr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+/// A GM that can be connected to but can't run turns: the assistant and
+/// thread are created, and messages sent to it are not found or, if `stall`,
+/// never answered.
+async fn serve_broken_gm(stall: bool) -> String {
+    let assistant = json!({
+        "id": "asst_gm",
+        "object": "assistant",
+        "created_at": 0,
+        "model": "gpt-4o",
+        "tools": [],
+    });
+    let mut app = Router::new()
+        .route(
+            "/v1/assistants",
+            get(|| async { Json(json!({"object": "list", "data": [], "has_more": false})) })
+                .post(|| async { Json(assistant) }),
+        )
+        .route(
+            "/v1/threads",
+            post(|| async { Json(json!({"id": "thread_1", "object": "thread", "created_at": 0})) }),
+        );
+    if stall {
+        app = app.route(
+            "/v1/threads/{thread}/messages",
+            post(std::future::pending::<Json<Value>>),
+        );
+    }
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{}/v1", addr)
+}
+
+#[test]
+fn turn_modes_parse() {
+    assert_eq!("free".parse(), Ok(TurnMode::Free));
+    assert_eq!("round-robin".parse(), Ok(TurnMode::RoundRobin));
+    assert_eq!("batch".parse(), Ok(TurnMode::Batch));
+    assert!("Batch".parse::<TurnMode>().is_err());
+    assert!("".parse::<TurnMode>().is_err());
+    // The command line and the wire agree on names.
+    for mode in [TurnMode::Free, TurnMode::RoundRobin, TurnMode::Batch] {
+        let name = serde_json::to_string(&mode).unwrap();
+        assert_eq!(name.trim_matches('"').parse(), Ok(mode));
+    }
+}
+
+#[tokio::test]
+async fn host_keeps_its_lobby_when_the_game_cannot_start() {
+    let _key = API_KEY.lock().await;
+    // Without a key the game fails to build before reaching the network.
+    std::env::remove_var("OPENAI_API_KEY");
+    let addr = free_addr().await;
+    let config = HostConfig {
+        addr: addr.clone(),
+        players: 2,
+        turn_mode: TurnMode::RoundRobin,
+        endpoint: None,
+    };
+    tokio::spawn(run_host(config.clone(), Catalog::bundled()));
+
+    let mut ann = Peer::connect(&addr).await;
+    ann.join("Ann").await;
+    assert!(matches!(
+        ann.recv().await,
+        ServerMessage::Joined { character: 0 }
+    ));
+    match ann.recv().await {
+        ServerMessage::Lobby { joined, expected } => {
+            assert_eq!(joined, ["Ann"]);
+            assert_eq!(expected, 2);
+        }
+        msg => panic!("Expected the lobby, got {:?}", msg),
+    }
+
+    ann.send(&ClientMessage::Submit {
+        content: "I wait".to_owned(),
+    })
+    .await;
+    assert_eq!(ann.recv_error().await, "The game hasn't started");
+    ann.join("Ann").await;
+    assert_eq!(ann.recv_error().await, "Already joined");
+    // Remote characters are checked against the host's catalog.
+    let mut dee = Peer::connect(&addr).await;
+    let mut cheat = serde_json::to_value(character("Dee")).unwrap();
+    cheat["level"] = json!(20);
+    dee.send(&json!({"type": "join", "character": cheat})).await;
+    assert_eq!(
+        dee.recv_error().await,
+        "Dee at level 20 with 0 XP isn't possible"
+    );
+
+    let mut bo = Peer::connect(&addr).await;
+    bo.join("Bo").await;
+    assert!(matches!(
+        bo.recv().await,
+        ServerMessage::Joined { character: 1 }
+    ));
+    assert!(bo.recv_error().await.starts_with("Could not start game"));
+    assert!(ann.recv_error().await.starts_with("Could not start game"));
+
+    // Still hosting, with the party still waiting.
+    let mut cy = Peer::connect(&addr).await;
+    cy.join("Cy").await;
+    assert!(matches!(
+        cy.recv().await,
+        ServerMessage::Joined { character: 2 }
+    ));
+    match cy.recv().await {
+        ServerMessage::Lobby { joined, .. } => assert_eq!(joined, ["Ann", "Bo", "Cy"]),
+        msg => panic!("Expected the lobby, got {:?}", msg),
+    }
+
+    // With a key the game is built, but the GM fails to open the quest.
+    std::env::set_var("OPENAI_API_KEY", "test");
+    let addr = free_addr().await;
+    let config = HostConfig {
+        addr: addr.clone(),
+        endpoint: Some(serve_broken_gm(false).await),
+        ..config
+    };
+    tokio::spawn(run_host(config, Catalog::bundled()));
+    let mut ann = Peer::connect(&addr).await;
+    ann.join("Ann").await;
+    let mut bo = Peer::connect(&addr).await;
+    bo.join("Bo").await;
+    assert!(bo.recv_error().await.starts_with("Could not start game"));
+    assert!(ann.recv_error().await.starts_with("Could not start game"));
+    ann.send(&ClientMessage::Submit {
+        content: "I wait".to_owned(),
+    })
+    .await;
+    assert_eq!(ann.recv_error().await, "The game hasn't started");
+    let mut cy = Peer::connect(&addr).await;
+    cy.join("Cy").await;
+    assert!(matches!(
+        cy.recv().await,
+        ServerMessage::Joined { character: 2 }
+    ));
+}
+
+#[tokio::test]
+async fn host_answers_players_while_the_gm_is_busy() {
+    let _key = API_KEY.lock().await;
+    std::env::set_var("OPENAI_API_KEY", "test");
+    let addr = free_addr().await;
+    let config = HostConfig {
+        addr: addr.clone(),
+        players: 1,
+        turn_mode: TurnMode::Free,
+        endpoint: Some(serve_broken_gm(true).await),
+    };
+    tokio::spawn(run_host(config, Catalog::bundled()));
+
+    let mut ann = Peer::connect(&addr).await;
+    ann.join("Ann").await;
+    assert!(matches!(
+        ann.recv().await,
+        ServerMessage::Joined { character: 0 }
+    ));
+    // The GM never finishes opening the quest, but the host isn't stuck.
+    let mut bo = Peer::connect(&addr).await;
+    bo.join("Bo").await;
+    assert_eq!(bo.recv_error().await, "The game is starting");
+    ann.send(&ClientMessage::Submit {
+        content: "I wait".to_owned(),
+    })
+    .await;
+    assert_eq!(ann.recv_error().await, "The game hasn't started");
+}
+
+#[tokio::test]
+async fn client_joins_submits_and_follows_snapshots() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let host = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut client = Peer::new(stream);
+        let ClientMessage::Join { character: ann } = client.recv().await else {
+            panic!("Expected a join");
+        };
+        let party = vec![*ann, character("Bo")];
+        client.send(&ServerMessage::Joined { character: 0 }).await;
+        client
+            .send(&ServerMessage::Lobby {
+                joined: vec!["Ann".to_owned(), "Bo".to_owned()],
+                expected: 2,
+            })
+            .await;
+        client.send(&snapshot(party.clone(), Vec::new())).await;
+
+        let ClientMessage::Submit { content } = client.recv().await else {
+            panic!("Expected an action");
+        };
+        assert_eq!(content, "I open the door");
+        let log = vec![
+            GameLogEntry::new(GamePlayer::PC(0), content.clone()),
+            GameLogEntry::new(GamePlayer::GM, "The door creaks open.".to_owned()),
+        ];
+        client
+            .send(&ServerMessage::TurnStarted {
+                actions: vec![(0, content)],
+            })
+            .await;
+        for entry in log.iter() {
+            client
+                .send(&ServerMessage::LogEntry {
+                    entry: entry.clone(),
+                })
+                .await;
+        }
+        client
+            .send(&ServerMessage::TurnFinished {
+                characters: vec![0],
+                error: None,
+            })
+            .await;
+        client.send(&snapshot(party, log)).await;
+
+        let ClientMessage::Submit { .. } = client.recv().await else {
+            panic!("Expected an action");
+        };
+        client
+            .send(&ServerMessage::Error {
+                message: "It's Bo's turn".to_owned(),
+            })
+            .await;
+    });
+
+    let (game, id) = tokio::time::timeout(TIMEOUT, join(addr, character("Ann")))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(id, 0);
+    {
+        let state = game.state().read().unwrap();
+        assert_eq!(state.party.len(), 2);
+        assert_eq!(state.party[1].name(), "Bo");
+        assert!(state.log.is_empty());
+    }
+
+    let mut events = game.subscribe();
+    game.input(id, "I open the door".to_owned()).await.unwrap();
+    // The snapshot follows the end of the turn.
+    tokio::time::timeout(TIMEOUT, async {
+        while !matches!(events.next().await, Some(GameEvent::TurnFinished)) {}
+    })
+    .await
+    .unwrap();
+    {
+        let state = game.state().read().unwrap();
+        assert_eq!(state.log.len(), 2);
+        assert_eq!(state.log[1].player, GamePlayer::GM);
+        assert_eq!(state.log[1].content, "The door creaks open.");
+    }
+
+    let error = game.input(id, "I go first".to_owned()).await.unwrap_err();
+    assert_eq!(error.to_string(), "It's Bo's turn");
+    host.await.unwrap();
+}
@@ -0,0 +1,114 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::character::Ability;
+
+/// Experience thresholds for each level, starting at level 1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelTable {
+    thresholds: Vec<u32>,
+    hp_per_level: u32,
+    ability_bump: u8,
+}
+
+impl LevelTable {
+    /// Creates a level table from the XP required to reach each level. The first
+    /// threshold is for level 1 and should normally be zero.
+    pub fn new(thresholds: Vec<u32>) -> Self {
+        Self {
+            thresholds,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_hp_per_level(mut self, hp_per_level: u32) -> Self {
+        self.hp_per_level = hp_per_level;
+        self
+    }
+
+    pub fn with_ability_bump(mut self, ability_bump: u8) -> Self {
+        self.ability_bump = ability_bump;
+        self
+    }
+
+    /// The level a character with `xp` experience points has earned.
+    pub fn level_for(&self, xp: u32) -> u32 {
+        let level = self.thresholds.iter().take_while(|t| **t <= xp).count() as u32;
+        level.max(1)
+    }
+
+    /// XP needed to reach the level after `level`, or `None` at the maximum level.
+    pub fn next_threshold(&self, level: u32) -> Option<u32> {
+        self.thresholds.get(level as usize).copied()
+    }
+
+    pub fn hp_per_level(&self) -> u32 {
+        self.hp_per_level
+    }
+
+    pub fn ability_bump(&self) -> u8 {
+        self.ability_bump
+    }
+}
+
+impl Default for LevelTable {
+    fn default() -> Self {
+        Self {
+            thresholds: vec![
+                0, 300, 900, 2700, 6500, 14000, 23000, 34000, 48000, 64000, 85000, 100000, 120000,
+                140000, 165000, 195000, 225000, 265000, 305000, 355000,
+            ],
+            hp_per_level: 6,
+            ability_bump: 2,
+        }
+    }
+}
+
+/// An improvement chosen by the player when their character gains a level.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub enum Improvement {
+    HitPoints,
+    AbilityBump(Ability),
+    Feature(String),
+}
+
+impl std::fmt::Display for Improvement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Improvement::HitPoints => write!(f, "more hit points"),
+            Improvement::AbilityBump(ability) => write!(f, "a better {}", ability),
+            Improvement::Feature(feature) => write!(f, "the feature \"{}\"", feature),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_start_at_each_threshold() {
+        let table = LevelTable::default();
+        assert_eq!(table.level_for(0), 1);
+        assert_eq!(table.level_for(299), 1);
+        assert_eq!(table.level_for(300), 2);
+        assert_eq!(table.level_for(899), 2);
+        assert_eq!(table.level_for(900), 3);
+        assert_eq!(table.level_for(355000), 20);
+        assert_eq!(table.level_for(u32::MAX), 20);
+
+        assert_eq!(table.next_threshold(1), Some(300));
+        assert_eq!(table.next_threshold(19), Some(355000));
+        assert_eq!(table.next_threshold(20), None);
+    }
+
+    #[test]
+    fn custom_tables_never_go_below_level_1() {
+        let table = LevelTable::new(vec![100, 200]);
+        assert_eq!(table.level_for(0), 1);
+        assert_eq!(table.level_for(100), 1);
+        assert_eq!(table.level_for(200), 2);
+        assert_eq!(LevelTable::new(Vec::new()).level_for(1000), 1);
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::character::PlayerCharacter;
+use crate::store::{Store, StoreError};
+
+/// Version of the roster file format written by this build.
+const ROSTER_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedCharacter {
+    character: PlayerCharacter,
+}
+
+#[derive(Debug, Clone)]
+pub struct RosterEntry {
+    pub id: String,
+    pub character: PlayerCharacter,
+}
+
+/// A directory of saved player characters, one JSON file per character.
+#[derive(Debug, Clone)]
+pub struct Roster {
+    store: Store,
+}
+
+impl Roster {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            store: Store::new(dir, ROSTER_VERSION),
+        }
+    }
+
+    /// `<data dir>/uquest/roster`, e.g. `~/.local/share/uquest/roster` on Linux.
+    pub fn open_default() -> Result<Self, StoreError> {
+        Store::open_default("roster", ROSTER_VERSION).map(|store| Self { store })
+    }
+
+    /// All readable entries, sorted by character name. Unreadable files are
+    /// logged and skipped.
+    pub fn list(&self) -> Result<Vec<RosterEntry>, StoreError> {
+        let mut roster: Vec<RosterEntry> = self
+            .store
+            .list()?
+            .into_iter()
+            .map(|(id, saved): (String, SavedCharacter)| RosterEntry {
+                id,
+                character: saved.character,
+            })
+            .collect();
+        roster.sort_by(|a, b| a.character.name().cmp(b.character.name()));
+        Ok(roster)
+    }
+
+    pub fn load(&self, id: &str) -> Result<RosterEntry, StoreError> {
+        let saved: SavedCharacter = self.store.load(id)?;
+        Ok(RosterEntry {
+            id: id.to_owned(),
+            character: saved.character,
+        })
+    }
+
+    /// Adds a new entry for `character` with an id derived from its name.
+    pub fn add(&self, character: PlayerCharacter) -> Result<RosterEntry, StoreError> {
+        let entry = RosterEntry {
+            id: self.store.unused_id(character.name(), "character"),
+            character,
+        };
+        self.save(&entry)?;
+        Ok(entry)
+    }
+
+    pub fn save(&self, entry: &RosterEntry) -> Result<(), StoreError> {
+        let saved = SavedCharacter {
+            character: entry.character.clone(),
+        };
+        self.store.save(&entry.id, &saved)
+    }
+
+    pub fn duplicate(&self, id: &str) -> Result<RosterEntry, StoreError> {
+        let entry = self.load(id)?;
+        self.add(entry.character)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), StoreError> {
+        self.store.delete(id)
+    }
+}
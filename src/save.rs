@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::SavedGame;
+use crate::store::{Store, StoreError};
+
+/// Version of the save file format written by this build. Version 2 stopped
+/// copying the log into every turn of the history.
+const SAVE_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveFile {
+    saved_at: u64,
+    roster_ids: Vec<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    campaign_id: Option<String>,
+    game: SavedGame,
+}
+
+#[derive(Debug, Clone)]
+pub struct SaveEntry {
+    pub id: String,
+    /// When the game was saved, in seconds since the Unix epoch.
+    pub saved_at: u64,
+    /// The roster entry each party member was played from, if any, so the
+    /// party can be written back to the roster when the quest ends.
+    pub roster_ids: Vec<Option<String>>,
+    /// The campaign the quest is part of, if any.
+    pub campaign_id: Option<String>,
+    pub game: SavedGame,
+}
+
+impl SaveEntry {
+    fn new(id: String, saved: SaveFile) -> Self {
+        Self {
+            id,
+            saved_at: saved.saved_at,
+            roster_ids: saved.roster_ids,
+            campaign_id: saved.campaign_id,
+            game: saved.game,
+        }
+    }
+}
+
+/// A directory of saved games, one JSON file per save.
+#[derive(Debug, Clone)]
+pub struct Saves {
+    store: Store,
+}
+
+impl Saves {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            store: Store::new(dir, SAVE_VERSION),
+        }
+    }
+
+    /// `<data dir>/uquest/saves`, e.g. `~/.local/share/uquest/saves` on Linux.
+    pub fn open_default() -> Result<Self, StoreError> {
+        Store::open_default("saves", SAVE_VERSION).map(|store| Self { store })
+    }
+
+    /// All readable saves, newest first. Unreadable files are logged and
+    /// skipped.
+    pub fn list(&self) -> Result<Vec<SaveEntry>, StoreError> {
+        let mut saves: Vec<SaveEntry> = self
+            .store
+            .list()?
+            .into_iter()
+            .map(|(id, saved)| SaveEntry::new(id, saved))
+            .collect();
+        saves.sort_by_key(|entry| std::cmp::Reverse(entry.saved_at));
+        Ok(saves)
+    }
+
+    /// The most recent save, for continuing where the player left off.
+    pub fn latest(&self) -> Result<Option<SaveEntry>, StoreError> {
+        Ok(self.list()?.into_iter().next())
+    }
+
+    pub fn load(&self, id: &str) -> Result<SaveEntry, StoreError> {
+        Ok(SaveEntry::new(id.to_owned(), self.store.load(id)?))
+    }
+
+    /// Saves `game` under `id`, or under a new id derived from the quest
+    /// title if `None`, stamped with the current time.
+    pub fn save(
+        &self,
+        id: Option<String>,
+        roster_ids: Vec<Option<String>>,
+        campaign_id: Option<String>,
+        game: SavedGame,
+    ) -> Result<SaveEntry, StoreError> {
+        let id = id.unwrap_or_else(|| self.store.unused_id(&game.state.quest.title, "quest"));
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let saved = SaveFile {
+            saved_at,
+            roster_ids,
+            campaign_id,
+            game,
+        };
+        self.store.save(&id, &saved)?;
+        Ok(SaveEntry::new(id, saved))
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), StoreError> {
+        self.store.delete(id)
+    }
+}
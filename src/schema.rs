@@ -1,13 +1,304 @@
-use schemars::{schema_for, JsonSchema};
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+//! The messages exchanged with the GM. Their JSON schema is part of the GM's
+//! instructions, so doc comments here are read by the GM too.
 
-use crate::character::PlayerCharacter;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::character::{CharacterDetails, PlayerCharacter};
+use crate::progression::Improvement;
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum AIInput {
-    Start(PlayerCharacter),
-    UserInput(String),
+    /// Begins a quest for the party, run the way the settings ask for.
+    Start {
+        party: Vec<PlayerCharacter>,
+        settings: AdventureSettings,
+        /// The campaign so far, if this quest continues one. Set it in the
+        /// same world, bring back allies and enemies where they fit and build
+        /// on the unresolved hooks.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        campaign: Option<CampaignContext>,
+    },
+    UserInput {
+        actions: Vec<PartyAction>,
+        progress: QuestProgress,
+    },
+    LevelUp {
+        character: String,
+        level: u32,
+        improvement: Improvement,
+    },
+    DraftBackstory(Box<PlayerCharacter>),
+    /// A player used the X-card: something in the last scene made them
+    /// uncomfortable. Without asking why, retcon the scene: respond with a
+    /// Description that replaces what happened since the party's last action,
+    /// leaving out whatever may have caused it, and carry on from there.
+    XCard,
+    /// The quest is over, and the campaign it is part of carries on. Respond
+    /// only with a Conclusion update.
+    Conclude,
+}
+
+/// What happened on a campaign's earlier quests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct CampaignContext {
+    /// A summary of each earlier quest, oldest first.
+    pub past_quests: Vec<String>,
+    /// Plot threads left unresolved so far.
+    pub hooks: Vec<String>,
+    /// People and factions on the party's side.
+    pub allies: Vec<String>,
+    /// People and factions against the party.
+    pub enemies: Vec<String>,
+}
+
+/// What kind of adventure the players want, chosen before the quest starts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdventureSettings {
+    pub genre: Genre,
+    pub tone: Tone,
+    pub difficulty: Difficulty,
+    pub length: QuestLength,
+    pub content_rating: ContentRating,
+    /// Lines: subjects that must never appear in the story, not even
+    /// mentioned.
+    pub lines: Vec<String>,
+    /// Veils: subjects that may be part of the story but only happen
+    /// off-screen. Fade to black instead of describing them.
+    pub veils: Vec<String>,
+}
+
+impl AdventureSettings {
+    /// The lines and veils that `text` mentions, as whole words or simple
+    /// plural and suffixed forms, ignoring case. A cheap local check, so it
+    /// can't tell a mention from a violation.
+    pub fn screen(&self, text: &str) -> Vec<String> {
+        let text = text.to_lowercase();
+        self.lines
+            .iter()
+            .chain(&self.veils)
+            .filter(|term| mentions(&text, &term.to_lowercase()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Endings a mention of a term may have, so e.g. "spider" matches "spiders".
+const SUFFIXES: [&str; 5] = ["", "s", "es", "ed", "ing"];
+
+/// Whether lowercase `text` contains `term` as a whole word, or one of its
+/// simple plural or suffixed forms. A plural term also matches the singular,
+/// so "spiders" matches "spider".
+fn mentions(text: &str, term: &str) -> bool {
+    let term = term.trim();
+    if term.is_empty() {
+        return false;
+    }
+    let stems = ["es", "s"]
+        .into_iter()
+        .filter_map(|ending| term.strip_suffix(ending))
+        .filter(|stem| stem.len() >= 3);
+    std::iter::once(term).chain(stems).any(|stem| {
+        text.match_indices(stem).any(|(start, _)| {
+            let before = text[..start].chars().next_back();
+            let rest = &text[start + stem.len()..];
+            let ending = &rest[..rest
+                .find(|c: char| !c.is_alphanumeric())
+                .unwrap_or(rest.len())];
+            !before.is_some_and(char::is_alphanumeric) && SUFFIXES.contains(&ending)
+        })
+    })
+}
+
+/// The setting and style of story. Races and classes are reinterpreted to fit,
+/// e.g. a wizard in a sci-fi quest may be a scientist or psychic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Genre {
+    /// Swords and sorcery.
+    #[default]
+    Fantasy,
+    /// Starships, strange worlds and advanced technology.
+    SciFi,
+    /// Crime and intrigue in rain-soaked city streets.
+    Noir,
+    /// Dread, mystery and things best left alone.
+    Horror,
+    /// Frontier towns, outlaws and the open range.
+    Western,
+}
+
+impl Genre {
+    pub const ALL: [Genre; 5] = [
+        Genre::Fantasy,
+        Genre::SciFi,
+        Genre::Noir,
+        Genre::Horror,
+        Genre::Western,
+    ];
+}
+
+impl std::fmt::Display for Genre {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Genre::Fantasy => write!(f, "Fantasy"),
+            Genre::SciFi => write!(f, "Sci-fi"),
+            Genre::Noir => write!(f, "Noir"),
+            Genre::Horror => write!(f, "Horror"),
+            Genre::Western => write!(f, "Western"),
+        }
+    }
+}
+
+/// The mood of the story.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Tone {
+    /// Playful and funny, where even danger has a light touch.
+    Lighthearted,
+    /// Bold deeds and clear stakes, where the heroes can win the day.
+    #[default]
+    Heroic,
+    /// Hard choices and costly victories.
+    Gritty,
+    /// Bleak and unforgiving, where hope is scarce.
+    Grim,
+}
+
+impl Tone {
+    pub const ALL: [Tone; 4] = [Tone::Lighthearted, Tone::Heroic, Tone::Gritty, Tone::Grim];
+}
+
+impl std::fmt::Display for Tone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tone::Lighthearted => write!(f, "Lighthearted"),
+            Tone::Heroic => write!(f, "Heroic"),
+            Tone::Gritty => write!(f, "Gritty"),
+            Tone::Grim => write!(f, "Grim"),
+        }
+    }
+}
+
+/// How hard the challenges are and how much they hurt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Difficulty {
+    /// Forgiving challenges, with little lasting harm.
+    Easy,
+    #[default]
+    Normal,
+    /// Tough challenges that punish careless play.
+    Hard,
+    /// Characters can easily die.
+    Deadly,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Deadly,
+    ];
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Deadly => write!(f, "Deadly"),
+        }
+    }
+}
+
+/// Roughly how many scenes the quest should take to complete.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum QuestLength {
+    /// Around three scenes.
+    #[default]
+    Short,
+    /// Around six scenes.
+    Medium,
+    /// Around ten scenes.
+    Long,
+}
+
+impl QuestLength {
+    pub const ALL: [QuestLength; 3] = [QuestLength::Short, QuestLength::Medium, QuestLength::Long];
+
+    /// The number of scenes the quest is paced for.
+    pub fn scenes(&self) -> u32 {
+        match self {
+            QuestLength::Short => 3,
+            QuestLength::Medium => 6,
+            QuestLength::Long => 10,
+        }
+    }
+}
+
+impl std::fmt::Display for QuestLength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuestLength::Short => write!(f, "Short"),
+            QuestLength::Medium => write!(f, "Medium"),
+            QuestLength::Long => write!(f, "Long"),
+        }
+    }
+}
+
+/// The most mature content the story may include.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ContentRating {
+    /// Suitable for all ages: no gore, no strong language.
+    Family,
+    /// Peril and violence without graphic detail, mild language.
+    #[default]
+    Teen,
+    /// Graphic violence, strong language and disturbing themes are allowed.
+    Mature,
+}
+
+impl ContentRating {
+    pub const ALL: [ContentRating; 3] = [
+        ContentRating::Family,
+        ContentRating::Teen,
+        ContentRating::Mature,
+    ];
+}
+
+impl std::fmt::Display for ContentRating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentRating::Family => write!(f, "Family"),
+            ContentRating::Teen => write!(f, "Teen"),
+            ContentRating::Mature => write!(f, "Mature"),
+        }
+    }
+}
+
+/// How far through the quest the party is, sent with every turn. Build toward
+/// the climax as `scene` nears `target_scenes`, and bring the quest to its end
+/// soon after reaching it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuestProgress {
+    /// Turns the party has taken, counting this one.
+    pub turn: u32,
+    /// The current scene, counting from 1, or 0 before the first SceneChange.
+    pub scene: u32,
+    /// How many scenes the quest should take.
+    pub target_scenes: u32,
+}
+
+/// An action taken by one party member during a turn.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PartyAction {
+    pub character: String,
+    pub content: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -20,13 +311,85 @@ pub struct AIOutput {
 #[serde(deny_unknown_fields)]
 pub enum QuestUpdate {
     QuestDefinition(QuestDefinition),
+    /// Narration for the players, in Markdown.
     Description(String),
+    AwardXp {
+        character: Option<String>,
+        amount: u32,
+        reason: String,
+    },
+    HitPoints {
+        character: String,
+        change: i32,
+        reason: String,
+    },
+    ItemGained {
+        character: String,
+        item: String,
+    },
+    ItemLost {
+        character: String,
+        item: String,
+    },
+    Backstory(CharacterDetails),
+    /// The story moves to a new scene: a new place, time or situation. Send
+    /// one for the first scene too, before its Description.
+    SceneChange {
+        title: String,
+    },
+    Conclusion(QuestConclusion),
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+/// How a quest ended, carried into the campaign's next quest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuestConclusion {
+    /// A paragraph on what the party did and how the quest ended.
+    pub summary: String,
+    /// Every plot thread still unresolved, including earlier ones, that a
+    /// later quest could pick up.
+    pub hooks: Vec<String>,
+    /// Every ally the party has made so far, each with a few words on who
+    /// they are.
+    pub allies: Vec<String>,
+    /// Every enemy the party has made so far, each with a few words on who
+    /// they are.
+    pub enemies: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct QuestDefinition {
     pub title: String,
     pub description: String,
     pub objective_summary: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_match_plurals_and_suffixes() {
+        assert!(mentions("a spider drops down", "spider"));
+        assert!(mentions("spiders swarm the walls", "spider"));
+        assert!(mentions("a spider drops down", "spiders"));
+        assert!(mentions("the foxes bolt", "fox"));
+        assert!(mentions("the dog was drowned", "drown"));
+        assert!(mentions("the spider's web", "spiders"));
+        assert!(!mentions("a spiderweb", "spider"));
+        assert!(!mentions("an arachnid", "spider"));
+        assert!(!mentions("catalogue", "cat"));
+    }
+
+    #[test]
+    fn screen_ignores_case() {
+        let settings = AdventureSettings {
+            lines: vec!["Spiders".to_owned()],
+            veils: vec!["torture".to_owned()],
+            ..AdventureSettings::default()
+        };
+        assert_eq!(settings.screen("A SPIDER waits."), vec!["Spiders"]);
+        assert!(settings.screen("All is calm.").is_empty());
+    }
+}
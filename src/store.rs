@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+
+use log::error;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone)]
+pub enum StoreError {
+    NoDirectory,
+    Io(String),
+    Parse(String),
+    UnsupportedVersion(u32),
+    /// Not an id this store could have made, see `Store::unused_id`.
+    InvalidId(String),
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::NoDirectory => write!(f, "No data directory available"),
+            StoreError::Io(msg) => write!(f, "I/O error: {}", msg),
+            StoreError::Parse(msg) => write!(f, "Invalid file: {}", msg),
+            StoreError::UnsupportedVersion(version) => {
+                write!(f, "File version {} is not supported", version)
+            }
+            StoreError::InvalidId(id) => write!(f, "Invalid id: {:?}", id),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+/// The on-disk form of an entry: its fields next to the format version.
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: u32,
+    #[serde(flatten)]
+    data: T,
+}
+
+/// A directory of JSON files, one per entry, each named `<id>.json` and
+/// stamped with the format version it was written with. The roster, saves
+/// and campaigns are all kept this way.
+#[derive(Debug, Clone)]
+pub(crate) struct Store {
+    dir: PathBuf,
+    /// Version written by this build; newer files are refused.
+    version: u32,
+}
+
+impl Store {
+    pub(crate) fn new(dir: PathBuf, version: u32) -> Self {
+        Self { dir, version }
+    }
+
+    /// `<data dir>/uquest/<name>`.
+    pub(crate) fn open_default(name: &str, version: u32) -> Result<Self, StoreError> {
+        dirs::data_dir()
+            .map(|dir| Self::new(dir.join("uquest").join(name), version))
+            .ok_or(StoreError::NoDirectory)
+    }
+
+    /// All readable entries with their ids, in no particular order.
+    /// Unreadable files are logged and skipped.
+    pub(crate) fn list<T: DeserializeOwned>(&self) -> Result<Vec<(String, T)>, StoreError> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(io_error(&self.dir, err)),
+        };
+        Ok(entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let id = path.file_stem()?.to_string_lossy().into_owned();
+                match self.load(&id) {
+                    Ok(data) => Some((id, data)),
+                    Err(err) => {
+                        error!("Skipping {}: {}", path.display(), err);
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    pub(crate) fn load<T: DeserializeOwned>(&self, id: &str) -> Result<T, StoreError> {
+        let path = self.path(id)?;
+        let content = std::fs::read_to_string(&path).map_err(|err| io_error(&path, err))?;
+        let value: serde_json::Value =
+            serde_json::from_str(&content).map_err(|err| StoreError::Parse(err.to_string()))?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        if version == 0 || version > self.version {
+            return Err(StoreError::UnsupportedVersion(version));
+        }
+        let saved: Versioned<T> =
+            serde_json::from_value(value).map_err(|err| StoreError::Parse(err.to_string()))?;
+        Ok(saved.data)
+    }
+
+    pub(crate) fn save<T: Serialize>(&self, id: &str, data: &T) -> Result<(), StoreError> {
+        let path = self.path(id)?;
+        std::fs::create_dir_all(&self.dir).map_err(|err| io_error(&self.dir, err))?;
+        let saved = Versioned {
+            version: self.version,
+            data,
+        };
+        let content = serde_json::to_string_pretty(&saved)
+            .map_err(|err| StoreError::Parse(err.to_string()))?;
+        std::fs::write(&path, content).map_err(|err| io_error(&path, err))
+    }
+
+    pub(crate) fn delete(&self, id: &str) -> Result<(), StoreError> {
+        let path = self.path(id)?;
+        std::fs::remove_file(&path).map_err(|err| io_error(&path, err))
+    }
+
+    /// A lowercase slug of `name` that no entry uses yet, numbered `-2`, `-3`
+    /// and so on if taken, or `fallback` if `name` has no usable characters.
+    pub(crate) fn unused_id(&self, name: &str, fallback: &str) -> String {
+        let slug = slug(name);
+        let slug = if slug.is_empty() { fallback } else { &slug };
+        (1..)
+            .map(|n| {
+                if n == 1 {
+                    slug.to_owned()
+                } else {
+                    format!("{}-{}", slug, n)
+                }
+            })
+            .find(|id| self.path(id).is_ok_and(|path| !path.exists()))
+            .unwrap()
+    }
+
+    /// Ids can come from outside the program, so anything that could name a
+    /// file outside the directory is refused.
+    fn path(&self, id: &str) -> Result<PathBuf, StoreError> {
+        if !valid_id(id) {
+            return Err(StoreError::InvalidId(id.to_owned()));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+}
+
+/// `name` lowercased with anything but ASCII letters and digits turned into
+/// `-`, trimmed of leading and trailing dashes.
+pub(crate) fn slug(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    slug.trim_matches('-').to_owned()
+}
+
+/// Whether `id` is a slug like those `Store::unused_id` makes: ASCII letters,
+/// digits, `-` and `_`. That leaves no room for path separators, `..` or
+/// hidden files.
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> StoreError {
+    StoreError::Io(format!("{}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_stay_inside_the_directory() {
+        for id in [
+            "",
+            ".",
+            "..",
+            "../../etc/passwd",
+            "a/b",
+            "a\\b",
+            ".hidden",
+            "a..b",
+        ] {
+            assert!(!valid_id(id), "{:?} should be rejected", id);
+        }
+        for id in ["ann", "ann-2", "the_quest", "Quest-1"] {
+            assert!(valid_id(id), "{:?} should be accepted", id);
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! A line-based terminal frontend, for playing where the iced GUI can't start
+//! (e.g. over SSH). It drives the same `GameBuilder`/`GameHandle` as the GUI.
+
+use std::future::Future;
+use std::io::Write;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, BufReader, Lines, Stdin};
+
+use micro_quest::catalog::Catalog;
+use micro_quest::character::{Ability, AbilityScores, PlayerCharacter, PlayerCharacterBuilder};
+use micro_quest::export::{self, ExportFormat};
+use micro_quest::game::{CharacterId, GameBuilder, GameError, GameHandle, GamePlayer, GameState};
+use micro_quest::progression::Improvement;
+use micro_quest::roster::{Roster, RosterEntry};
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+const HELP: &str = "Commands: /as NAME to act as another party member, /status, /undo to \
+    take back the last action, /reroll for a new response to it, /xcard to have the GM rewrite the last scene, /export md|html|epub to save \
+    the story, /quit to end the quest";
+
+type Input = Lines<BufReader<Stdin>>;
+
+/// Runs the terminal frontend until the player ends the quest or closes stdin.
+pub async fn run(catalog: Catalog) -> Result<(), GameError> {
+    let roster = Roster::open_default().unwrap_or_else(|error| {
+        log::error!("{}, using ./roster", error);
+        Roster::new("roster".into())
+    });
+    let mut input = BufReader::new(tokio::io::stdin()).lines();
+
+    println!("uQuest\n");
+    let (ids, game) = loop {
+        let Some(party) = choose_party(&mut input, &roster, &catalog).await else {
+            return Ok(());
+        };
+        let (ids, party): (Vec<_>, Vec<_>) = party.into_iter().unzip();
+        if let Some(game) = start_game(&mut input, &catalog, &party).await {
+            break (ids, game);
+        }
+    };
+
+    {
+        let state = game.state().read().unwrap();
+        print_header(&state);
+    }
+    let mut printed = print_log(&game, 0);
+    let mut active: CharacterId = 0;
+    println!("{}\n", HELP);
+
+    loop {
+        let level_ups = {
+            let state = game.state().read().unwrap();
+            state.party[active].pending_level_ups(&state.level_table)
+        };
+        if level_ups > 0 {
+            let Some(improvement) = choose_improvement(&mut input, &game, active).await else {
+                break;
+            };
+            let result = wait("Levelling up", game.level_up(active, improvement)).await;
+            printed = print_log(&game, printed);
+            if let Err(error) = result {
+                println!("! {}", error);
+            }
+            continue;
+        }
+
+        let name = game.state().read().unwrap().party[active].name().to_owned();
+        let Some(line) = prompt(&mut input, &format!("{}> ", name)).await else {
+            break;
+        };
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(command) = line.strip_prefix('/') {
+            let (command, arg) = command.split_once(' ').unwrap_or((command, ""));
+            match command {
+                "quit" | "q" => break,
+                "status" => print_status(&game.state().read().unwrap()),
+                "undo" => match wait("Undoing", game.undo()).await {
+                    Ok(()) => {
+                        printed = game.state().read().unwrap().log.len();
+                        println!("  * The last action was taken back\n");
+                    }
+                    Err(error) => println!("! {}", error),
+                },
+                "reroll" => match wait("The GM is thinking again", game.reroll()).await {
+                    Ok(()) => {
+                        // Print only the new response, after the action it
+                        // answers.
+                        let from = game
+                            .state()
+                            .read()
+                            .unwrap()
+                            .log
+                            .iter()
+                            .rposition(|entry| matches!(entry.player, GamePlayer::PC(_)))
+                            .map_or(0, |i| i + 1);
+                        printed = print_log(&game, from);
+                    }
+                    Err(error) => {
+                        printed = game.state().read().unwrap().log.len();
+                        println!("! {}", error);
+                    }
+                },
+                "xcard" => match wait("The GM is rewriting the scene", game.x_card()).await {
+                    Ok(()) => {
+                        println!("  * The last scene was withdrawn\n");
+                        printed = print_log(&game, printed);
+                    }
+                    Err(error) => println!("! {}", error),
+                },
+                "export" => {
+                    let result = arg.parse::<ExportFormat>().and_then(|format| {
+                        export::export_to_file(&game.state().read().unwrap(), format)
+                    });
+                    match result {
+                        Ok(path) => println!("  * Exported to {}\n", path.display()),
+                        Err(error) => println!("! {}", error),
+                    }
+                }
+                "as" => match game.state().read().unwrap().member_id(arg) {
+                    Some(id) => active = id,
+                    None => println!("No party member called \"{}\"", arg.trim()),
+                },
+                _ => println!("{}", HELP),
+            }
+            continue;
+        }
+
+        let result = wait("The GM is thinking", game.input(active, line)).await;
+        printed = print_log(&game, printed);
+        if let Err(error) = result {
+            println!("! {}", error);
+        }
+    }
+
+    let party = game.state().read().unwrap().party.clone();
+    for (id, character) in ids.into_iter().zip(party) {
+        let Some(id) = id else {
+            continue;
+        };
+        if let Err(error) = roster.save(&RosterEntry { id, character }) {
+            log::error!("Could not save character: {}", error);
+        }
+    }
+    println!("Farewell, adventurers.");
+    Ok(())
+}
+
+/// Builds and starts a game for `party`, offering to try again if it fails.
+/// `None` if the player would rather choose another party, e.g. after one
+/// the builder refused.
+async fn start_game(
+    input: &mut Input,
+    catalog: &Catalog,
+    party: &[PlayerCharacter],
+) -> Option<GameHandle> {
+    let (first, rest) = party.split_first()?;
+    // Kept once built, so a retry only asks the GM to open the quest again.
+    let mut built = None;
+    loop {
+        let game = match built.clone() {
+            Some(game) => Ok(game),
+            None => {
+                let mut builder = GameBuilder::new(first.clone()).with_catalog(catalog.clone());
+                for pc in rest {
+                    builder = builder.with_party_member(pc.clone());
+                }
+                wait("Preparing the quest", builder.build()).await
+            }
+        };
+        let result = match game {
+            Ok(game) => {
+                built = Some(game.clone());
+                wait("The GM is thinking", game.start())
+                    .await
+                    .map(|()| game)
+            }
+            Err(error) => Err(error),
+        };
+        match result {
+            Ok(game) => return Some(game),
+            Err(error) => println!("! {}", error),
+        }
+        let line = prompt(
+            input,
+            "Enter r to retry, or anything else to choose again: ",
+        )
+        .await?;
+        if !line.eq_ignore_ascii_case("r") {
+            return None;
+        }
+    }
+}
+
+/// Awaits `future`, animating a spinner after `label` until it completes.
+async fn wait<T>(label: &str, future: impl Future<Output = T>) -> T {
+    tokio::pin!(future);
+    let mut ticker = tokio::time::interval(Duration::from_millis(150));
+    let mut frame = 0;
+    let result = loop {
+        tokio::select! {
+            result = &mut future => break result,
+            _ = ticker.tick() => {
+                print!("\r{} {}", label, SPINNER[frame % SPINNER.len()]);
+                let _ = std::io::stdout().flush();
+                frame += 1;
+            }
+        }
+    };
+    print!("\r{}\r", " ".repeat(label.len() + 2));
+    let _ = std::io::stdout().flush();
+    result
+}
+
+/// Prints `message` and reads a trimmed line, or `None` once stdin is closed.
+async fn prompt(input: &mut Input, message: &str) -> Option<String> {
+    print!("{}", message);
+    let _ = std::io::stdout().flush();
+    match input.next_line().await {
+        Ok(Some(line)) => Some(line.trim().to_owned()),
+        _ => None,
+    }
+}
+
+/// Asks for a 1-based choice from `options`, re-prompting until one is valid.
+async fn choose(input: &mut Input, message: &str, options: &[String]) -> Option<usize> {
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}) {}", i + 1, option);
+    }
+    loop {
+        let line = prompt(input, message).await?;
+        match line.parse::<usize>() {
+            Ok(n) if (1..=options.len()).contains(&n) => return Some(n - 1),
+            _ => println!("Enter a number from 1 to {}", options.len()),
+        }
+    }
+}
+
+/// Picks the party from the roster, or a newly created character. Each member
+/// comes with their roster id, `None` if they couldn't be saved to the roster.
+async fn choose_party(
+    input: &mut Input,
+    roster: &Roster,
+    catalog: &Catalog,
+) -> Option<Vec<(Option<String>, PlayerCharacter)>> {
+    let entries = roster.list().unwrap_or_else(|error| {
+        println!("! {}", error);
+        Vec::new()
+    });
+    if entries.is_empty() {
+        println!("Your roster is empty, let's create a character.");
+        return Some(vec![create_character(input, roster, catalog).await?]);
+    }
+
+    println!("Roster:");
+    for (i, entry) in entries.iter().enumerate() {
+        let pc = &entry.character;
+        println!(
+            "  {}) {} - Level {} {} {}",
+            i + 1,
+            pc.name(),
+            pc.level(),
+            pc.race(),
+            pc.class()
+        );
+    }
+    println!("  n) New character");
+    loop {
+        let line = prompt(input, "Choose your party (e.g. 1,3): ").await?;
+        if line.eq_ignore_ascii_case("n") {
+            return Some(vec![create_character(input, roster, catalog).await?]);
+        }
+        let chosen: Option<Vec<_>> = line
+            .split(',')
+            .map(|part| {
+                part.trim()
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|i| entries.get(i))
+                    .map(|entry| (Some(entry.id.clone()), entry.character.clone()))
+            })
+            .collect();
+        match chosen {
+            Some(party) if !party.is_empty() => return Some(party),
+            _ => println!("Enter roster numbers separated by commas, or n"),
+        }
+    }
+}
+
+async fn create_character(
+    input: &mut Input,
+    roster: &Roster,
+    catalog: &Catalog,
+) -> Option<(Option<String>, PlayerCharacter)> {
+    loop {
+        let name = prompt(input, "Name: ").await?;
+
+        println!("Race:");
+        let races: Vec<_> = catalog
+            .races()
+            .iter()
+            .map(|race| format!("{} - {}", race.name, race.description))
+            .collect();
+        let race = &catalog.races()[choose(input, "Race: ", &races).await?];
+
+        println!("Class:");
+        let classes: Vec<_> = catalog
+            .classes()
+            .iter()
+            .map(|class| format!("{} - {}", class.name, class.description))
+            .collect();
+        let class = &catalog.classes()[choose(input, "Class: ", &classes).await?];
+
+        let abilities = AbilityScores::roll(&mut rand::thread_rng());
+        let character = PlayerCharacterBuilder::new(name)
+            .with_race(race.name.clone())
+            .with_class(class.name.clone())
+            .with_abilities(abilities)
+            .build(catalog);
+        match character {
+            Ok(character) => {
+                print_character(&character);
+                return match roster.add(character.clone()) {
+                    Ok(entry) => Some((Some(entry.id), entry.character)),
+                    Err(error) => {
+                        println!("! Could not save character: {}", error);
+                        Some((None, character))
+                    }
+                };
+            }
+            Err(error) => println!("! {}", error),
+        }
+    }
+}
+
+async fn choose_improvement(
+    input: &mut Input,
+    game: &GameHandle,
+    character: CharacterId,
+) -> Option<Improvement> {
+    let (options, name, level) = {
+        let state = game.state().read().unwrap();
+        let pc = &state.party[character];
+        let mut options = vec![format!("+{} HP", state.level_table.hp_per_level())];
+        options.extend(
+            Ability::ALL
+                .iter()
+                .map(|ability| format!("+{} {}", state.level_table.ability_bump(), ability)),
+        );
+        options.push("Learn a new feature".to_owned());
+        (options, pc.name().to_owned(), pc.level() + 1)
+    };
+    println!(
+        "Level up! Choose an improvement for {}'s level {}:",
+        name, level
+    );
+    let choice = choose(input, "Improvement: ", &options).await?;
+    Some(match choice {
+        0 => Improvement::HitPoints,
+        n if n <= Ability::ALL.len() => Improvement::AbilityBump(Ability::ALL[n - 1]),
+        _ => loop {
+            let feature = prompt(input, "Feature: ").await?;
+            if !feature.is_empty() {
+                break Improvement::Feature(feature);
+            }
+        },
+    })
+}
+
+fn print_header(state: &GameState) {
+    let quest = &state.quest;
+    let rule = "=".repeat(quest.title.chars().count().max(20));
+    println!("\n{}\n{}\n{}", rule, quest.title, rule);
+    println!("{}\n", quest.description);
+    println!("Objective: {}\n", quest.objective_summary);
+}
+
+/// Prints log entries from `from` onwards, returning the new log length.
+fn print_log(game: &GameHandle, from: usize) -> usize {
+    let state = game.state().read().unwrap();
+    for entry in state.log.iter().skip(from) {
+        match entry.player {
+            GamePlayer::GM => println!("GM: {}\n", entry.content),
+            GamePlayer::PC(id) => println!("{}: {}\n", state.party[id].name(), entry.content),
+            GamePlayer::System => println!("  * {}\n", entry.content),
+        }
+    }
+    state.log.len()
+}
+
+fn print_status(state: &GameState) {
+    println!(
+        "Scene {} of ~{}, turn {}",
+        state.scene,
+        state.target_scenes(),
+        state.turn
+    );
+    for pc in state.party.iter() {
+        print_character(pc);
+        let xp = match state.level_table.next_threshold(pc.level()) {
+            Some(next) => format!("{} / {}", pc.xp(), next),
+            None => pc.xp().to_string(),
+        };
+        println!("  XP: {}", xp);
+    }
+    println!();
+}
+
+fn print_character(pc: &PlayerCharacter) {
+    println!(
+        "{} - Level {} {} {}, HP {} / {}",
+        pc.name(),
+        pc.level(),
+        pc.race(),
+        pc.class(),
+        pc.hp(),
+        pc.max_hp()
+    );
+    let abilities: Vec<_> = Ability::ALL
+        .iter()
+        .map(|ability| {
+            format!(
+                "{} {}",
+                ability.abbreviation(),
+                pc.abilities().get(*ability)
+            )
+        })
+        .collect();
+    println!("  {}", abilities.join("  "));
+    if !pc.inventory().is_empty() {
+        println!("  Inventory: {}", pc.inventory().join(", "));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use iced::alignment::Horizontal;
+use iced::widget::{
+    button, column, container, horizontal_space, row, scrollable, text, vertical_space, Column,
+};
+use iced::{color, Center, Element, Fill};
+
+use micro_quest::campaign::{CampaignEntry, Campaigns};
+use micro_quest::save::{SaveEntry, Saves};
+
+#[derive(Debug, Clone)]
+pub(super) enum Message {
+    Select(String),
+    NextQuest,
+    Continue,
+    Delete,
+    Back,
+}
+
+pub(super) enum Action {
+    NextQuest(Box<CampaignEntry>),
+    /// Carries on the campaign's quest in progress.
+    Load(Box<SaveEntry>),
+    Back,
+}
+
+/// Every campaign, with the story so far of the one selected.
+#[derive(Debug)]
+pub(super) struct CampaignScreen {
+    campaigns: Campaigns,
+    saves: Saves,
+    /// Sorted by name.
+    entries: Vec<CampaignEntry>,
+    selected: Option<String>,
+    /// The newest save of each campaign's quest in progress, by campaign id.
+    in_progress: HashMap<String, SaveEntry>,
+    error: Option<String>,
+}
+
+impl CampaignScreen {
+    pub(super) fn new(campaigns: Campaigns, saves: Saves, selected: Option<String>) -> Self {
+        let mut screen = Self {
+            campaigns,
+            saves,
+            entries: Vec::new(),
+            selected: None,
+            in_progress: HashMap::new(),
+            error: None,
+        };
+        screen.refresh();
+        let selected = selected.or_else(|| screen.entries.first().map(|entry| entry.id.clone()));
+        screen.select(selected);
+        screen
+    }
+
+    /// Reads the campaigns and saves once, rather than every time one is
+    /// selected.
+    fn refresh(&mut self) {
+        match self.campaigns.list() {
+            Ok(entries) => self.entries = entries,
+            Err(error) => self.error = Some(error.to_string()),
+        }
+        self.in_progress.clear();
+        match self.saves.list() {
+            Ok(saves) => {
+                // Newest first, so the first of each campaign is kept.
+                for save in saves {
+                    if let Some(id) = save.campaign_id.clone() {
+                        self.in_progress.entry(id).or_insert(save);
+                    }
+                }
+            }
+            Err(error) => self.error = Some(error.to_string()),
+        }
+    }
+
+    fn select(&mut self, id: Option<String>) {
+        self.selected = id;
+    }
+
+    /// The newest save of the selected campaign's quest in progress.
+    fn in_progress(&self) -> Option<&SaveEntry> {
+        self.in_progress.get(self.selected.as_ref()?)
+    }
+
+    fn selected(&self) -> Option<&CampaignEntry> {
+        let id = self.selected.as_ref()?;
+        self.entries.iter().find(|entry| &entry.id == id)
+    }
+
+    pub(super) fn update(&mut self, message: Message) -> Option<Action> {
+        self.error = None;
+        match message {
+            Message::Select(id) => self.select(Some(id)),
+            Message::NextQuest => {
+                return self
+                    .selected()
+                    .map(|entry| Action::NextQuest(Box::new(entry.clone())))
+            }
+            Message::Continue => {
+                return self
+                    .in_progress()
+                    .map(|save| Action::Load(Box::new(save.clone())))
+            }
+            Message::Delete => {
+                if let Some(id) = self.selected.take() {
+                    if let Err(error) = self.campaigns.delete(&id) {
+                        self.error = Some(error.to_string());
+                    }
+                }
+                self.refresh();
+                self.select(self.entries.first().map(|entry| entry.id.clone()));
+            }
+            Message::Back => return Some(Action::Back),
+        }
+        None
+    }
+
+    pub(super) fn view(&self) -> Element<'_, Message> {
+        let list = Column::with_children(self.entries.iter().map(|entry| {
+            let style = if self.selected.as_ref() == Some(&entry.id) {
+                button::primary
+            } else {
+                button::secondary
+            };
+            button(text(entry.campaign.name.clone()).width(Fill))
+                .width(Fill)
+                .style(style)
+                .on_press(Message::Select(entry.id.clone()))
+                .into()
+        }))
+        .spacing(5)
+        .width(240);
+        let details = match self.selected() {
+            Some(entry) => self.view_campaign(entry),
+            None => text("No campaigns yet. Start one from the adventure setup of a new game.")
+                .color(color!(0x999999))
+                .into(),
+        };
+        column![
+            row![
+                button("Back")
+                    .style(button::secondary)
+                    .on_press(Message::Back),
+                horizontal_space(),
+                text("Campaigns").size(24),
+                horizontal_space(),
+            ]
+            .align_y(Center),
+            vertical_space().height(20),
+            row![scrollable(list), details].spacing(20).height(Fill),
+            text(self.error.clone().unwrap_or_default()).color(color!(0xcc4444)),
+        ]
+        .padding(20)
+        .into()
+    }
+
+    fn view_campaign<'a>(&'a self, entry: &'a CampaignEntry) -> Element<'a, Message> {
+        let campaign = &entry.campaign;
+        let party: Vec<_> = campaign
+            .party
+            .iter()
+            .map(|pc| format!("{} (level {} {})", pc.name(), pc.level(), pc.class()))
+            .collect();
+        let adventure = &campaign.adventure;
+        let quests =
+            Column::with_children(campaign.quests.iter().enumerate().map(|(index, quest)| {
+                column![
+                    text(format!("{}. {}", index + 1, quest.title)).size(18),
+                    text(quest.summary.clone()),
+                ]
+                .spacing(5)
+                .into()
+            }))
+            .spacing(10);
+        let play = match self.in_progress() {
+            Some(_) => button("Continue Quest").on_press(Message::Continue),
+            None => button("Next Quest").on_press(Message::NextQuest),
+        };
+        scrollable(
+            column![
+                text(campaign.name.clone()).size(28),
+                text(party.join(", ")),
+                text(format!(
+                    "{} - {} - {} - {} quests",
+                    adventure.genre, adventure.tone, adventure.difficulty, adventure.length
+                ))
+                .color(color!(0x999999)),
+                vertical_space().height(10),
+                text("The story so far").size(20),
+                if campaign.quests.is_empty() {
+                    Element::from(text("No quests finished yet.").color(color!(0x999999)))
+                } else {
+                    quests.into()
+                },
+                view_list("Unresolved hooks", &campaign.hooks),
+                view_list("Allies", &campaign.allies),
+                view_list("Enemies", &campaign.enemies),
+                vertical_space().height(10),
+                row![
+                    play,
+                    button("Delete")
+                        .style(button::danger)
+                        .on_press(Message::Delete),
+                ]
+                .spacing(20),
+            ]
+            .spacing(10)
+            .width(Fill)
+            .align_x(Horizontal::Left),
+        )
+        .width(Fill)
+        .into()
+    }
+}
+
+fn view_list<'a>(heading: &'a str, items: &'a [String]) -> Element<'a, Message> {
+    if items.is_empty() {
+        return vertical_space().height(0).into();
+    }
+    container(
+        column![text(heading).size(20)]
+            .extend(
+                items
+                    .iter()
+                    .map(|item| text(format!("\u{2022} {}", item)).into()),
+            )
+            .spacing(5),
+    )
+    .padding([5, 0])
+    .into()
+}
@@ -2,37 +2,99 @@ use iced::alignment::{Horizontal, Vertical};
 use iced::color;
 use iced::task::Task;
 use iced::widget::{
-    button, column, markdown, row, scrollable, text, text_input, vertical_space, Column, Row,
+    button, column, container, row, scrollable, text, text_editor, text_input, vertical_space, Row,
 };
 use iced::Center;
 use iced::Element;
 use iced::Fill;
+use iced::Font;
+use iced_aw::style::selection_list;
+use iced_aw::widgets::spinner::Spinner;
+use iced_aw::widgets::SelectionList;
 
-use crate::character::{PlayerCharacter, PlayerCharacterBuilder};
+use micro_quest::catalog::{Catalog, Class, Race};
+use micro_quest::character::{
+    Ability, AbilityScores, CharacterDetails, PlayerCharacter, PlayerCharacterBuilder,
+};
+use micro_quest::game::{GameBuilder, GameError};
+
+use super::settings::GameSettings;
 
 #[derive(Debug, Clone)]
 pub(super) enum Message {
     NameChange(String),
-    RaceChange(String),
-    ClassChange(String),
+    RaceSelect(String),
+    ClassSelect(String),
+    SeedChange(String),
+    ApplySeed,
+    Randomize,
+    RandomizeName,
+    RandomizeRace,
+    RandomizeClass,
+    RandomizeAbilities,
+    BackstoryEdit(text_editor::Action),
+    PersonalityChange(String),
+    IdealsChange(String),
+    FlawsChange(String),
+    AppearanceChange(String),
+    Draft,
+    Drafted(Result<CharacterDetails, GameError>),
     Submit,
+    Back,
 }
 
 pub(super) enum Action {
     Run(Task<Message>),
-    Submit(PlayerCharacter),
+    Submit(Box<PlayerCharacter>),
+    Back,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub(super) struct CharacterCreate {
+    catalog: Catalog,
+    /// How to reach the GM for drafting backstories.
+    game_settings: GameSettings,
+    race_names: Vec<String>,
+    class_names: Vec<String>,
     name_field: String,
-    race_field: String,
-    class_field: String,
+    race: Option<String>,
+    class: Option<String>,
+    abilities: AbilityScores,
+    seed_field: String,
+    backstory: text_editor::Content,
+    personality_field: String,
+    ideals_field: String,
+    flaws_field: String,
+    appearance_field: String,
+    drafting: bool,
+    error: Option<String>,
 }
 
 impl CharacterCreate {
-    pub(super) fn new() -> (Self, Task<Message>) {
-        (Self::default(), Task::none())
+    pub(super) fn new(catalog: Catalog, game_settings: GameSettings) -> (Self, Task<Message>) {
+        let race_names = catalog.races().iter().map(|r| r.name.clone()).collect();
+        let class_names = catalog.classes().iter().map(|c| c.name.clone()).collect();
+        (
+            Self {
+                catalog,
+                game_settings,
+                race_names,
+                class_names,
+                name_field: String::new(),
+                race: None,
+                class: None,
+                abilities: AbilityScores::default(),
+                seed_field: String::new(),
+                backstory: text_editor::Content::new(),
+                personality_field: String::new(),
+                ideals_field: String::new(),
+                flaws_field: String::new(),
+                appearance_field: String::new(),
+                drafting: false,
+                error: None,
+            },
+            Task::none(),
+        )
     }
 
     pub(super) fn update(&mut self, message: Message) -> Option<Action> {
@@ -41,65 +103,366 @@ impl CharacterCreate {
                 self.name_field = content;
                 None
             }
-            Message::RaceChange(content) => {
-                self.race_field = content;
+            Message::RaceSelect(race) => {
+                self.race = Some(race);
                 None
             }
-            Message::ClassChange(content) => {
-                self.class_field = content;
+            Message::ClassSelect(class) => {
+                self.class = Some(class);
                 None
             }
-            Message::Submit => {
-                let name = if self.name_field.trim().is_empty() {
-                    "Jim".to_owned()
-                } else {
-                    self.name_field.trim().to_owned()
-                };
-                let race = if self.race_field.trim().is_empty() {
-                    "Human".to_owned()
-                } else {
-                    self.race_field.trim().to_owned()
-                };
-                let class = if self.class_field.trim().is_empty() {
-                    "Fighter".to_owned()
-                } else {
-                    self.class_field.trim().to_owned()
-                };
-                let pc = PlayerCharacterBuilder::new(name)
-                    .with_race(race)
-                    .with_class(class)
-                    .build();
-                Some(Action::Submit(pc))
+            Message::SeedChange(content) => {
+                self.seed_field = content;
+                None
+            }
+            Message::ApplySeed => {
+                match self.seed_field.trim().parse() {
+                    Ok(seed) => self.apply_seed(seed),
+                    Err(_) => self.error = Some("The seed must be a whole number".to_owned()),
+                }
+                None
+            }
+            Message::Randomize => {
+                self.apply_seed(rand::random());
+                None
+            }
+            Message::RandomizeName => {
+                let race = self.catalog.race(self.race.as_deref().unwrap_or("human"));
+                if let Some(name) = race.and_then(|r| r.random_name(&mut rand::thread_rng())) {
+                    self.name_field = name;
+                    self.seed_field.clear();
+                }
+                None
+            }
+            Message::RandomizeRace => {
+                if let Some(race) = self.catalog.random_race(&mut rand::thread_rng()) {
+                    self.race = Some(race.name.clone());
+                    self.seed_field.clear();
+                }
+                None
+            }
+            Message::RandomizeClass => {
+                if let Some(class) = self.catalog.random_class(&mut rand::thread_rng()) {
+                    self.class = Some(class.name.clone());
+                    self.seed_field.clear();
+                }
+                None
+            }
+            Message::RandomizeAbilities => {
+                self.abilities = AbilityScores::roll(&mut rand::thread_rng());
+                self.seed_field.clear();
+                None
             }
+            Message::BackstoryEdit(action) => {
+                self.backstory.perform(action);
+                None
+            }
+            Message::PersonalityChange(content) => {
+                self.personality_field = content;
+                None
+            }
+            Message::IdealsChange(content) => {
+                self.ideals_field = content;
+                None
+            }
+            Message::FlawsChange(content) => {
+                self.flaws_field = content;
+                None
+            }
+            Message::AppearanceChange(content) => {
+                self.appearance_field = content;
+                None
+            }
+            Message::Draft => match self.builder().build(&self.catalog) {
+                Ok(pc) => {
+                    self.drafting = true;
+                    self.error = None;
+                    let game_builder = self
+                        .game_settings
+                        .apply(GameBuilder::new(pc).with_catalog(self.catalog.clone()));
+                    Some(Action::Run(Task::perform(
+                        game_builder.draft_backstory(),
+                        Message::Drafted,
+                    )))
+                }
+                Err(error) => {
+                    self.error = Some(error.to_string());
+                    None
+                }
+            },
+            Message::Drafted(result) => {
+                self.drafting = false;
+                match result {
+                    Ok(details) => self.apply_details(details),
+                    Err(error) => self.error = Some(format!("Drafting failed: {}", error)),
+                }
+                None
+            }
+            Message::Submit => match self.builder().build(&self.catalog) {
+                Ok(pc) => Some(Action::Submit(Box::new(pc))),
+                Err(error) => {
+                    self.error = Some(error.to_string());
+                    None
+                }
+            },
+            Message::Back => Some(Action::Back),
         }
     }
 
-    pub(super) fn view(&self) -> Element<Message> {
-        column![
-            vertical_space().height(100),
+    fn builder(&self) -> PlayerCharacterBuilder {
+        let name = if self.name_field.trim().is_empty() {
+            "Jim".to_owned()
+        } else {
+            self.name_field.trim().to_owned()
+        };
+        let mut builder = PlayerCharacterBuilder::new(name)
+            .with_abilities(self.abilities.clone())
+            .with_details(self.details());
+        if let Some(race) = &self.race {
+            builder = builder.with_race(race.clone());
+        }
+        if let Some(class) = &self.class {
+            builder = builder.with_class(class.clone());
+        }
+        builder
+    }
+
+    fn details(&self) -> CharacterDetails {
+        let field = |content: &str| {
+            let content = content.trim();
+            (!content.is_empty()).then(|| content.to_owned())
+        };
+        CharacterDetails {
+            backstory: field(&self.backstory.text()),
+            personality_traits: field(&self.personality_field),
+            ideals: field(&self.ideals_field),
+            flaws: field(&self.flaws_field),
+            appearance: field(&self.appearance_field),
+        }
+    }
+
+    fn apply_details(&mut self, details: CharacterDetails) {
+        self.backstory = text_editor::Content::with_text(&details.backstory.unwrap_or_default());
+        self.personality_field = details.personality_traits.unwrap_or_default();
+        self.ideals_field = details.ideals.unwrap_or_default();
+        self.flaws_field = details.flaws.unwrap_or_default();
+        self.appearance_field = details.appearance.unwrap_or_default();
+    }
+
+    /// Fills every field from `PlayerCharacterBuilder::random`, keeping the seed
+    /// on screen so the character can be shared.
+    fn apply_seed(&mut self, seed: u64) {
+        let builder = PlayerCharacterBuilder::random(seed, &self.catalog);
+        self.name_field = builder.name().to_owned();
+        self.race = Some(builder.race().to_owned());
+        self.class = Some(builder.class().to_owned());
+        self.abilities = builder.abilities().clone();
+        self.seed_field = seed.to_string();
+        self.error = None;
+    }
+
+    pub(super) fn view(&self) -> Element<'_, Message> {
+        let race = self.catalog.race(self.race.as_deref().unwrap_or("human"));
+        let class = self
+            .catalog
+            .class(self.class.as_deref().unwrap_or("fighter"));
+        let content = column![
+            vertical_space().height(40),
             row![
                 text("Name:").align_x(Horizontal::Left).width(60),
                 text_input("Jim", &self.name_field).on_input(Message::NameChange),
+                button("Randomize").on_press(Message::RandomizeName),
             ]
+            .spacing(10)
             .align_y(Vertical::Center),
             vertical_space().height(40),
             row![
-                text("Race:").align_x(Horizontal::Left).width(60),
-                text_input("Human", &self.race_field).on_input(Message::RaceChange),
+                column![
+                    row![
+                        text("Race:").width(Fill),
+                        button("Randomize").on_press(Message::RandomizeRace),
+                    ]
+                    .align_y(Vertical::Center),
+                    vertical_space().height(10),
+                    Self::selection_list(&self.race_names, &self.race, Message::RaceSelect),
+                    vertical_space().height(10),
+                    Self::view_race_preview(race),
+                ]
+                .width(Fill),
+                column![
+                    row![
+                        text("Class:").width(Fill),
+                        button("Randomize").on_press(Message::RandomizeClass),
+                    ]
+                    .align_y(Vertical::Center),
+                    vertical_space().height(10),
+                    Self::selection_list(&self.class_names, &self.class, Message::ClassSelect),
+                    vertical_space().height(10),
+                    Self::view_class_preview(class),
+                ]
+                .width(Fill),
+            ]
+            .spacing(40),
+            vertical_space().height(20),
+            row![
+                text("Abilities:").align_x(Horizontal::Left).width(80),
+                Row::with_children(Ability::ALL.iter().map(|ability| {
+                    text(format!(
+                        "{} {}",
+                        ability.abbreviation(),
+                        self.abilities.get(*ability)
+                    ))
+                    .into()
+                }))
+                .spacing(15)
+                .width(Fill),
+                button("Roll").on_press(Message::RandomizeAbilities),
             ]
+            .spacing(10)
             .align_y(Vertical::Center),
-            vertical_space().height(40),
+            vertical_space().height(20),
             row![
-                text("Class:").align_x(Horizontal::Left).width(60),
-                text_input("Fighter", &self.class_field).on_input(Message::ClassChange),
+                text("Seed:").align_x(Horizontal::Left).width(80),
+                text_input("Random", &self.seed_field)
+                    .on_input(Message::SeedChange)
+                    .on_submit(Message::ApplySeed),
+                button("Apply").on_press(Message::ApplySeed),
+                button("Randomize all").on_press(Message::Randomize),
             ]
+            .spacing(10)
             .align_y(Vertical::Center),
             vertical_space().height(40),
-            button("Submit").width(100).on_press(Message::Submit),
+            self.view_details(),
+            vertical_space().height(40),
+            row![
+                button("Back").width(100).on_press(Message::Back),
+                button("Submit").width(100).on_press(Message::Submit),
+            ]
+            .spacing(20),
+            vertical_space().height(10),
+            text(self.error.clone().unwrap_or_default()).color(color!(0xcc4444)),
         ]
         .width(Fill)
         .align_x(Center)
-        .padding(20)
+        .padding(20);
+        scrollable(content).height(Fill).into()
+    }
+
+    fn view_details(&self) -> Element<'_, Message> {
+        let field = |label, placeholder, value, on_input: fn(String) -> Message| {
+            row![
+                text(label).align_x(Horizontal::Left).width(100),
+                text_input(placeholder, value).on_input_maybe((!self.drafting).then_some(on_input)),
+            ]
+            .align_y(Vertical::Center)
+        };
+        column![
+            row![
+                text("Details (optional)").size(20).width(Fill),
+                if self.drafting {
+                    Element::from(Spinner::default())
+                } else {
+                    Element::from(button("Draft with AI").on_press(Message::Draft))
+                },
+            ]
+            .align_y(Vertical::Center),
+            vertical_space().height(10),
+            row![
+                text("Backstory:").align_x(Horizontal::Left).width(100),
+                text_editor(&self.backstory)
+                    .placeholder("Where does your hero come from?")
+                    .height(120)
+                    .on_action(Message::BackstoryEdit),
+            ],
+            field(
+                "Personality:",
+                "Curious, blunt, fond of bad jokes",
+                &self.personality_field,
+                Message::PersonalityChange
+            ),
+            field(
+                "Ideals:",
+                "Freedom above all",
+                &self.ideals_field,
+                Message::IdealsChange
+            ),
+            field(
+                "Flaws:",
+                "Can't resist a wager",
+                &self.flaws_field,
+                Message::FlawsChange
+            ),
+            field(
+                "Appearance:",
+                "Tall, scarred, with a braided red beard",
+                &self.appearance_field,
+                Message::AppearanceChange
+            ),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn selection_list<'a>(
+        options: &'a [String],
+        selected: &Option<String>,
+        on_selected: fn(String) -> Message,
+    ) -> Element<'a, Message> {
+        let selected = selected
+            .as_ref()
+            .and_then(|s| options.iter().position(|o| o == s));
+        SelectionList::new_with(
+            options,
+            move |_, option| on_selected(option),
+            12.0,
+            5.0,
+            selection_list::primary,
+            selected,
+            Font::default(),
+        )
+        .width(Fill)
+        .height(150)
+        .into()
+    }
+
+    fn view_race_preview(race: Option<&Race>) -> Element<'_, Message> {
+        let Some(race) = race else {
+            return vertical_space().height(0).into();
+        };
+        let adjustments = race
+            .ability_adjustments
+            .iter()
+            .map(|(ability, delta)| format!("{} {:+}", ability, delta))
+            .collect::<Vec<_>>()
+            .join(", ");
+        container(column![
+            text(race.name.clone()).size(20),
+            text(race.description.clone()),
+            text(adjustments).color(color!(0x999999)),
+        ])
+        .padding(10)
+        .width(Fill)
+        .style(container::bordered_box)
+        .into()
+    }
+
+    fn view_class_preview(class: Option<&Class>) -> Element<'_, Message> {
+        let Some(class) = class else {
+            return vertical_space().height(0).into();
+        };
+        container(column![
+            text(class.name.clone()).size(20),
+            text(class.description.clone()),
+            text(format!("Starting HP: {}", class.starting_hp)).color(color!(0x999999)),
+            text(format!(
+                "Equipment: {}",
+                class.starting_equipment.join(", ")
+            ))
+            .color(color!(0x999999)),
+        ])
+        .padding(10)
+        .width(Fill)
+        .style(container::bordered_box)
         .into()
     }
 }
@@ -1,58 +1,490 @@
+use std::time::Duration;
+
+use iced::keyboard::{self, Key, Modifiers};
 use iced::task::Task;
 use iced::theme::Theme;
-use iced::Element;
+use iced::widget::{button, column, container, horizontal_space, row, text};
+use iced::{Element, Fill, Subscription};
+use iced_aw::menu::{Item, Menu, MenuBar};
 
+mod campaign;
 mod character;
 mod quest;
+mod roster;
+mod settings;
+mod setup;
+mod title;
+
+use micro_quest::campaign::{Campaign, CampaignEntry, Campaigns};
+use micro_quest::catalog::Catalog;
+use micro_quest::character::PlayerCharacter;
+use micro_quest::export::ExportFormat;
+use micro_quest::game::GameError;
+use micro_quest::roster::{Roster, RosterEntry};
+use micro_quest::save::{SaveEntry, Saves};
+use micro_quest::schema::{AdventureSettings, QuestConclusion};
 
+use campaign::CampaignScreen;
 use character::CharacterCreate;
 use quest::QuestLog;
+use roster::RosterSelect;
+use settings::{Settings, SettingsScreen, TextSize};
+use setup::AdventureSetup;
+use title::TitleScreen;
 
 pub fn main() -> iced::Result {
     iced::application("uQuest", update, view)
-        .theme(|_| Theme::Dark)
+        .subscription(subscription)
+        .theme(|state| state.settings.theme.clone())
+        .scale_factor(|state| state.settings.text_size.scale())
         .exit_on_close_request(true)
         .run_with(State::new)
 }
 
 struct State {
     screen: Screen,
+    settings: Settings,
+    catalog: Catalog,
+    roster: Roster,
+    saves: Saves,
+    /// Roster ids of the party on the current quest, in party order. `None` for
+    /// characters that couldn't be saved to the roster.
+    active_entries: Vec<Option<String>>,
+    /// The save the current quest was loaded from or last saved to, which
+    /// saving again overwrites.
+    save_id: Option<String>,
+    campaigns: Campaigns,
+    /// The campaign the current quest is part of, if any.
+    campaign: Option<CampaignEntry>,
 }
 
 impl State {
     fn new() -> (Self, Task<Message>) {
-        let (screen, task) = CharacterCreate::new();
+        let catalog = Catalog::load();
+        let roster = Roster::open_default().unwrap_or_else(|error| {
+            log::error!("{}, using ./roster", error);
+            Roster::new("roster".into())
+        });
+        let saves = Saves::open_default().unwrap_or_else(|error| {
+            log::error!("{}, using ./saves", error);
+            Saves::new("saves".into())
+        });
+        let campaigns = Campaigns::open_default().unwrap_or_else(|error| {
+            log::error!("{}, using ./campaigns", error);
+            Campaigns::new("campaigns".into())
+        });
         (
             Self {
-                screen: Screen::CharacterCreate(screen),
+                screen: Screen::Title(TitleScreen::new(saves.clone(), false)),
+                settings: Settings::load(),
+                catalog,
+                roster,
+                saves,
+                active_entries: Vec::new(),
+                save_id: None,
+                campaigns,
+                campaign: None,
+            },
+            Task::none(),
+        )
+    }
+
+    fn show_title(&mut self, show_saves: bool) {
+        self.screen = Screen::Title(TitleScreen::new(self.saves.clone(), show_saves));
+    }
+
+    fn show_roster(&mut self) -> Task<Message> {
+        let (screen, task) = RosterSelect::new(self.roster.clone());
+        self.screen = Screen::Roster(screen);
+        task.map(Message::Roster)
+    }
+
+    /// Writes the party from the quest that just ended back to the roster.
+    fn save_party(&mut self, party: Vec<PlayerCharacter>) {
+        let ids = std::mem::take(&mut self.active_entries);
+        for (id, character) in ids.into_iter().zip(party) {
+            let Some(id) = id else {
+                continue;
+            };
+            let entry = RosterEntry { id, character };
+            if let Err(error) = self.roster.save(&entry) {
+                log::error!("Could not save character: {}", error);
+            }
+        }
+    }
+
+    /// Moves on to choosing the adventure for a party from the roster.
+    fn setup_quest(&mut self, party: Vec<RosterEntry>) {
+        let (ids, party): (Vec<_>, Vec<_>) = party
+            .into_iter()
+            .map(|entry| (Some(entry.id), entry.character))
+            .unzip();
+        self.active_entries = ids;
+        let setup = AdventureSetup::new(party, self.settings.adventure.clone());
+        self.screen = Screen::Setup(setup);
+    }
+
+    /// Starts a quest for the party, as the first of a new campaign called
+    /// `campaign` if given.
+    fn start_quest(
+        &mut self,
+        party: Vec<PlayerCharacter>,
+        adventure: AdventureSettings,
+        campaign: Option<String>,
+    ) -> Task<Message> {
+        // Lines and veils are for this table only, so the next quest asks
+        // again rather than having them on file.
+        let remembered = AdventureSettings {
+            lines: Vec::new(),
+            veils: Vec::new(),
+            ..adventure.clone()
+        };
+        if self.settings.adventure != remembered {
+            self.settings.adventure = remembered;
+            self.save_settings();
+        }
+        self.campaign = campaign.and_then(|name| {
+            let campaign = Campaign::new(
+                name,
+                party.clone(),
+                self.active_entries.clone(),
+                adventure.clone(),
+            );
+            self.campaigns
+                .add(campaign)
+                .inspect_err(|error| log::error!("Could not create campaign: {}", error))
+                .ok()
+        });
+        let context = self.campaign.as_ref().map(|entry| entry.campaign.context());
+        let (quest, task) = QuestLog::new(
+            party,
+            self.catalog.clone(),
+            self.settings.game.clone(),
+            adventure,
+            context,
+        );
+        self.save_id = None;
+        self.screen = Screen::Quest(Box::new(quest));
+        task.map(Message::Quest)
+    }
+
+    /// Starts the campaign's next quest with the party as they are in the
+    /// roster, which may have changed since the last quest, or as they left
+    /// the last quest if they're not in it.
+    fn continue_campaign(&mut self, entry: CampaignEntry) -> Task<Message> {
+        let campaign = &entry.campaign;
+        let party = campaign
+            .party
+            .iter()
+            .enumerate()
+            .map(|(index, character)| {
+                campaign
+                    .roster_ids
+                    .get(index)
+                    .and_then(Option::as_ref)
+                    .and_then(|id| {
+                        self.roster
+                            .load(id)
+                            .inspect_err(|error| {
+                                log::error!("Could not load character {}: {}", id, error)
+                            })
+                            .ok()
+                    })
+                    .map_or_else(|| character.clone(), |entry| entry.character)
+            })
+            .collect();
+        let (quest, task) = QuestLog::new(
+            party,
+            self.catalog.clone(),
+            self.settings.game.clone(),
+            campaign.adventure.clone(),
+            Some(campaign.context()),
+        );
+        self.active_entries = campaign.roster_ids.clone();
+        self.save_id = None;
+        self.campaign = Some(entry);
+        self.screen = Screen::Quest(Box::new(quest));
+        task.map(Message::Quest)
+    }
+
+    fn show_campaigns(&mut self, selected: Option<String>) {
+        let screen = CampaignScreen::new(self.campaigns.clone(), self.saves.clone(), selected);
+        self.screen = Screen::Campaigns(Box::new(screen));
+    }
+
+    /// Ends a campaign's quest: the GM sums it up for the campaign before the
+    /// party leaves.
+    fn conclude_quest(&mut self) -> Task<Message> {
+        let Screen::Quest(quest) = &mut self.screen else {
+            return Task::none();
+        };
+        let Some(game) = quest.game().cloned() else {
+            return Task::none();
+        };
+        quest.wait();
+        quest.notify("The GM is wrapping up the quest...".to_owned());
+        Task::perform(async move { game.conclude().await }, Message::Concluded)
+    }
+
+    /// Records the quest that just ended in its campaign and shows the
+    /// campaign.
+    fn finish_campaign_quest(&mut self, conclusion: QuestConclusion) {
+        let (Screen::Quest(quest), Some(mut entry)) = (&self.screen, self.campaign.take()) else {
+            return;
+        };
+        let Some(game) = quest.game() else {
+            return;
+        };
+        entry
+            .campaign
+            .record(&game.state().read().unwrap(), conclusion);
+        if let Err(error) = self.campaigns.save(&entry) {
+            log::error!("Could not save campaign: {}", error);
+        }
+        // The quest is over, so there's nothing left to continue.
+        if let Some(id) = self.save_id.take() {
+            if let Err(error) = self.saves.delete(&id) {
+                log::error!("Could not delete finished quest: {}", error);
+            }
+        }
+        let party = quest.own_characters();
+        self.save_party(party);
+        self.show_campaigns(Some(entry.id));
+    }
+
+    fn resume_quest(&mut self, entry: SaveEntry) -> Task<Message> {
+        self.campaign = entry.campaign_id.and_then(|id| {
+            self.campaigns
+                .load(&id)
+                .inspect_err(|error| log::error!("Could not load campaign {}: {}", id, error))
+                .ok()
+        });
+        let (quest, task) =
+            QuestLog::resume(entry.game, self.catalog.clone(), self.settings.game.clone());
+        self.active_entries = entry.roster_ids;
+        self.save_id = Some(entry.id);
+        self.screen = Screen::Quest(Box::new(quest));
+        task.map(Message::Quest)
+    }
+
+    /// Saves the quest being played, over its last save if it has one.
+    fn save_quest(&self) -> Task<Message> {
+        let Screen::Quest(quest) = &self.screen else {
+            return Task::none();
+        };
+        let Some(game) = quest.game().cloned() else {
+            return Task::none();
+        };
+        let saves = self.saves.clone();
+        let id = self.save_id.clone();
+        let roster_ids = self.active_entries.clone();
+        let campaign_id = self.campaign.as_ref().map(|entry| entry.id.clone());
+        Task::perform(
+            async move {
+                let saved = game.save().await?;
+                saves
+                    .save(id, roster_ids, campaign_id, saved)
+                    .map(|entry| entry.id)
+                    .map_err(|error| GameError::Custom(error.to_string()))
             },
-            task.map(Message::CharacterCreate),
+            Message::Saved,
         )
     }
+
+    /// Writes the settings changed from the menu bar to the config file.
+    fn save_settings(&self) {
+        if let Err(error) = self.settings.save() {
+            log::error!("Could not save settings: {}", error);
+        }
+    }
+
+    /// Leaves the quest for the title screen. A game hosted here is saved on
+    /// the way out so it can be continued.
+    fn quit_to_title(&mut self, show_saves: bool) -> Task<Message> {
+        let Screen::Quest(quest) = &self.screen else {
+            return Task::none();
+        };
+        let task = if quest.remote() {
+            Task::none()
+        } else {
+            self.save_quest()
+        };
+        let party = quest.own_characters();
+        self.save_party(party);
+        self.save_id = None;
+        self.campaign = None;
+        self.show_title(show_saves);
+        task
+    }
 }
 
 enum Screen {
-    CharacterCreate(character::CharacterCreate),
-    Quest(quest::QuestLog),
+    Title(title::TitleScreen),
+    Settings(settings::SettingsScreen),
+    Roster(roster::RosterSelect),
+    CharacterCreate(Box<character::CharacterCreate>),
+    Setup(setup::AdventureSetup),
+    Campaigns(Box<campaign::CampaignScreen>),
+    Quest(Box<quest::QuestLog>),
 }
 
 #[derive(Debug, Clone)]
 enum Message {
+    Title(title::Message),
+    Settings(settings::Message),
+    Roster(roster::Message),
     CharacterCreate(character::Message),
+    Setup(setup::Message),
+    Campaigns(campaign::Message),
     Quest(quest::Message),
+    Menu(MenuItem),
+    AutoSave,
+    /// A quest was saved, under the given id.
+    Saved(Result<String, GameError>),
+    /// The GM summed up a campaign's quest that the party ended.
+    Concluded(Result<QuestConclusion, GameError>),
+}
+
+/// An entry in the menu bar shown during a quest.
+#[derive(Debug, Clone)]
+enum MenuItem {
+    Save,
+    Load,
+    Export(ExportFormat),
+    QuitToTitle,
+    Theme(Theme),
+    TextSize(TextSize),
 }
 
 fn update(state: &mut State, message: Message) -> Task<Message> {
     match message {
+        Message::Title(message) => {
+            if let Screen::Title(title) = &mut state.screen {
+                if let Some(action) = title.update(message) {
+                    match action {
+                        title::Action::NewGame => state.show_roster(),
+                        title::Action::Load(entry) => state.resume_quest(*entry),
+                        title::Action::Campaigns => {
+                            state.show_campaigns(None);
+                            Task::none()
+                        }
+                        title::Action::Settings => {
+                            let screen = SettingsScreen::new(state.settings.clone());
+                            state.screen = Screen::Settings(screen);
+                            Task::none()
+                        }
+                        title::Action::Quit => iced::exit(),
+                    }
+                } else {
+                    Task::none()
+                }
+            } else {
+                Task::none()
+            }
+        }
+        Message::Settings(message) => {
+            if let Screen::Settings(screen) = &mut state.screen {
+                if let Some(action) = screen.update(message) {
+                    match action {
+                        settings::Action::Apply(settings) => state.settings = settings,
+                        settings::Action::Back => state.show_title(false),
+                    }
+                }
+            }
+            Task::none()
+        }
+        Message::Roster(message) => {
+            if let Screen::Roster(select) = &mut state.screen {
+                if let Some(action) = select.update(message) {
+                    match action {
+                        roster::Action::Play(party) => {
+                            state.setup_quest(party);
+                            Task::none()
+                        }
+                        roster::Action::Join(addr, entry) => {
+                            let (quest, task) = QuestLog::join(addr, entry.character);
+                            state.active_entries = vec![Some(entry.id)];
+                            state.save_id = None;
+                            state.screen = Screen::Quest(Box::new(quest));
+                            task.map(Message::Quest)
+                        }
+                        roster::Action::Create => {
+                            let (create, task) = CharacterCreate::new(
+                                state.catalog.clone(),
+                                state.settings.game.clone(),
+                            );
+                            state.screen = Screen::CharacterCreate(Box::new(create));
+                            task.map(Message::CharacterCreate)
+                        }
+                        roster::Action::Back => {
+                            state.show_title(false);
+                            Task::none()
+                        }
+                    }
+                } else {
+                    Task::none()
+                }
+            } else {
+                Task::none()
+            }
+        }
         Message::CharacterCreate(message) => {
             if let Screen::CharacterCreate(create) = &mut state.screen {
                 if let Some(action) = create.update(message) {
                     match action {
                         character::Action::Run(task) => task.map(Message::CharacterCreate),
                         character::Action::Submit(pc) => {
-                            let (quest, task) = QuestLog::new(pc);
-                            state.screen = Screen::Quest(quest);
-                            task.map(Message::Quest)
+                            match state.roster.add((*pc).clone()) {
+                                Ok(entry) => state.setup_quest(vec![entry]),
+                                Err(error) => {
+                                    log::error!("Could not save character: {}", error);
+                                    state.active_entries = vec![None];
+                                    let setup = AdventureSetup::new(
+                                        vec![*pc],
+                                        state.settings.adventure.clone(),
+                                    );
+                                    state.screen = Screen::Setup(setup);
+                                }
+                            }
+                            Task::none()
+                        }
+                        character::Action::Back => state.show_roster(),
+                    }
+                } else {
+                    Task::none()
+                }
+            } else {
+                Task::none()
+            }
+        }
+        Message::Setup(message) => {
+            if let Screen::Setup(setup) = &mut state.screen {
+                if let Some(action) = setup.update(message) {
+                    match action {
+                        setup::Action::Begin(party, adventure, campaign) => {
+                            state.start_quest(party, adventure, campaign)
+                        }
+                        setup::Action::Back => {
+                            state.active_entries.clear();
+                            state.show_roster()
+                        }
+                    }
+                } else {
+                    Task::none()
+                }
+            } else {
+                Task::none()
+            }
+        }
+        Message::Campaigns(message) => {
+            if let Screen::Campaigns(screen) = &mut state.screen {
+                if let Some(action) = screen.update(message) {
+                    match action {
+                        campaign::Action::NextQuest(entry) => state.continue_campaign(*entry),
+                        campaign::Action::Load(entry) => state.resume_quest(*entry),
+                        campaign::Action::Back => {
+                            state.show_title(false);
+                            Task::none()
                         }
                     }
                 } else {
@@ -67,6 +499,23 @@ fn update(state: &mut State, message: Message) -> Task<Message> {
                 if let Some(action) = quest.update(message) {
                     match action {
                         quest::Action::Run(task) => task.map(Message::Quest),
+                        quest::Action::Finish(_) if state.campaign.is_some() => {
+                            state.conclude_quest()
+                        }
+                        quest::Action::Finish(party) => {
+                            state.save_party(party);
+                            state.show_title(false);
+                            Task::none()
+                        }
+                        quest::Action::Back(party) => {
+                            state.save_party(party);
+                            let (create, task) = CharacterCreate::new(
+                                state.catalog.clone(),
+                                state.settings.game.clone(),
+                            );
+                            state.screen = Screen::CharacterCreate(Box::new(create));
+                            task.map(Message::CharacterCreate)
+                        }
                     }
                 } else {
                     Task::none()
@@ -75,12 +524,177 @@ fn update(state: &mut State, message: Message) -> Task<Message> {
                 Task::none()
             }
         }
+        Message::Menu(item) => match item {
+            MenuItem::Save => state.save_quest(),
+            MenuItem::Load => state.quit_to_title(true),
+            MenuItem::Export(format) => Task::done(Message::Quest(quest::Message::Export(format))),
+            MenuItem::QuitToTitle => state.quit_to_title(false),
+            MenuItem::Theme(theme) => {
+                state.settings.theme = theme;
+                state.save_settings();
+                Task::none()
+            }
+            MenuItem::TextSize(text_size) => {
+                state.settings.text_size = text_size;
+                state.save_settings();
+                Task::none()
+            }
+        },
+        Message::AutoSave => state.save_quest(),
+        Message::Saved(result) => {
+            match (&mut state.screen, result) {
+                (Screen::Quest(quest), Ok(id)) => {
+                    state.save_id = Some(id);
+                    quest.notify("Game saved".to_owned());
+                }
+                (Screen::Quest(quest), Err(error)) => quest.show_error(error),
+                // Saved on the way out of the quest.
+                (Screen::Title(title), Ok(_)) => title.refresh(),
+                (_, Ok(_)) => {}
+                (_, Err(error)) => log::error!("Could not save the game: {}", error),
+            }
+            Task::none()
+        }
+        Message::Concluded(Ok(conclusion)) => {
+            state.finish_campaign_quest(conclusion);
+            Task::none()
+        }
+        Message::Concluded(Err(error)) => {
+            if let Screen::Quest(quest) = &mut state.screen {
+                quest.finish_request(Err(error));
+            }
+            Task::none()
+        }
+    }
+}
+
+fn subscription(state: &State) -> Subscription<Message> {
+    match &state.screen {
+        Screen::Quest(quest) => {
+            let minutes = state.settings.autosave_minutes;
+            let autosave = if minutes > 0 && !quest.remote() {
+                iced::time::every(Duration::from_secs(u64::from(minutes) * 60))
+                    .map(|_| Message::AutoSave)
+            } else {
+                Subscription::none()
+            };
+            Subscription::batch([
+                quest.subscription().map(Message::Quest),
+                autosave,
+                keyboard::on_key_press(shortcut),
+            ])
+        }
+        _ => Subscription::none(),
     }
 }
 
-fn view(state: &State) -> Element<Message> {
+/// Ctrl+S saves the quest.
+fn shortcut(key: Key, modifiers: Modifiers) -> Option<Message> {
+    match key.as_ref() {
+        Key::Character("s") if modifiers.command() => Some(Message::Menu(MenuItem::Save)),
+        _ => None,
+    }
+}
+
+fn view(state: &State) -> Element<'_, Message> {
     match &state.screen {
+        Screen::Title(title) => title.view().map(Message::Title),
+        Screen::Settings(screen) => screen.view().map(Message::Settings),
+        Screen::Roster(select) => select.view().map(Message::Roster),
         Screen::CharacterCreate(create) => create.view().map(Message::CharacterCreate),
-        Screen::Quest(quest) => quest.view().map(Message::Quest),
+        Screen::Setup(setup) => setup.view().map(Message::Setup),
+        Screen::Campaigns(screen) => screen.view().map(Message::Campaigns),
+        Screen::Quest(quest) => column![
+            view_menu_bar(&state.settings),
+            quest.view(&state.settings.theme).map(Message::Quest),
+        ]
+        .into(),
     }
 }
+
+/// The Game and View menus shown above a quest.
+fn view_menu_bar(settings: &Settings) -> Element<'_, Message> {
+    let export = ExportFormat::ALL
+        .iter()
+        .map(|format| menu_button(format.to_string(), MenuItem::Export(*format), false))
+        .collect();
+    let themes = Theme::ALL
+        .iter()
+        .map(|theme| {
+            let selected = *theme == settings.theme;
+            menu_button(theme.to_string(), MenuItem::Theme(theme.clone()), selected)
+        })
+        .collect();
+    let text_sizes = TextSize::ALL
+        .iter()
+        .map(|size| {
+            let selected = *size == settings.text_size;
+            menu_button(size.to_string(), MenuItem::TextSize(*size), selected)
+        })
+        .collect();
+    let bar = MenuBar::new(vec![
+        Item::with_menu(
+            menu_root("Game"),
+            menu(vec![
+                menu_button("Save (Ctrl+S)".to_owned(), MenuItem::Save, false),
+                menu_button("Load".to_owned(), MenuItem::Load, false),
+                Item::with_menu(submenu_label("Export"), submenu(export)),
+                menu_button("Quit to title".to_owned(), MenuItem::QuitToTitle, false),
+            ]),
+        ),
+        Item::with_menu(
+            menu_root("View"),
+            menu(vec![
+                Item::with_menu(submenu_label("Theme"), submenu(themes)),
+                Item::with_menu(submenu_label("Text size"), submenu(text_sizes)),
+            ]),
+        ),
+    ])
+    .spacing(5.0);
+    container(bar)
+        .padding([2, 10])
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
+
+fn menu(
+    items: Vec<Item<'_, Message, Theme, iced::Renderer>>,
+) -> Menu<'_, Message, Theme, iced::Renderer> {
+    Menu::new(items).max_width(180.0).offset(5.0).spacing(2.0)
+}
+
+fn submenu(
+    items: Vec<Item<'_, Message, Theme, iced::Renderer>>,
+) -> Menu<'_, Message, Theme, iced::Renderer> {
+    Menu::new(items).max_width(180.0).offset(0.0).spacing(2.0)
+}
+
+fn menu_root(label: &str) -> Element<'_, Message> {
+    container(text(label)).padding([4, 8]).into()
+}
+
+fn submenu_label(label: &str) -> Element<'_, Message> {
+    container(row![text(label), horizontal_space(), text(">")])
+        .padding([4, 8])
+        .width(Fill)
+        .into()
+}
+
+fn menu_button<'a>(
+    label: String,
+    item: MenuItem,
+    selected: bool,
+) -> Item<'a, Message, Theme, iced::Renderer> {
+    Item::new(
+        button(text(label))
+            .padding([4, 8])
+            .width(Fill)
+            .style(if selected {
+                button::primary
+            } else {
+                button::text
+            })
+            .on_press(Message::Menu(item)),
+    )
+}
@@ -1,168 +1,1453 @@
 use iced::alignment::Horizontal;
+use iced::keyboard::{self, key::Named, Key, Modifiers};
 use iced::task::Task;
 use iced::widget::{
-    column, container, horizontal_space, row, scrollable, text, text_input, vertical_space, Column,
+    button, column, container, horizontal_space, markdown, pick_list, progress_bar, row,
+    scrollable, text, text_editor, text_input, tooltip, vertical_space, Column,
 };
-use iced::{color, Center, Element, Fill};
+use iced::{color, Border, Center, Color, Element, Fill, Font, Subscription, Theme};
 use iced_aw::widgets::spinner::Spinner;
+use iced_aw::widgets::tab_bar;
+use iced_aw::widgets::{Badge, Card, TabBar, TabLabel};
 
-use crate::character::PlayerCharacter;
-use crate::game::{GameBuilder, GameError, GameHandle, GameLogEntry, GamePlayer};
-use crate::schema::QuestDefinition;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+use micro_quest::catalog::Catalog;
+use micro_quest::character::{Ability, PlayerCharacter};
+use micro_quest::export::{self, ExportFormat};
+use micro_quest::game::{
+    CharacterId, GameBuilder, GameError, GameEvent, GameHandle, GameLogEntry, GamePlayer,
+    GameState, SavedGame,
+};
+use micro_quest::progression::Improvement;
+use micro_quest::schema::{AdventureSettings, CampaignContext};
+
+use super::settings::GameSettings;
+
+/// Log label colours for each party member, in party order.
+const PARTY_COLOURS: [Color; 4] = [
+    color!(0x6fa8dc),
+    color!(0x93c47d),
+    color!(0xe69138),
+    color!(0xc27ba0),
+];
 
 #[derive(Debug, Clone)]
 pub(super) enum Message {
     Loaded(Result<GameHandle, GameError>),
+    Joined(Result<(GameHandle, CharacterId), GameError>),
+    Game(GameEvent),
     Started(Result<(), GameError>),
-    InputFieldChange(String),
+    SelectCharacter(CharacterId),
+    InputAction(text_editor::Action),
     InputSubmit,
-    Response,
+    RecallPrevious,
+    RecallNext,
+    FocusInput,
+    Unqueue(usize),
+    CancelTurn,
+    LevelUpAbilityChange(Ability),
+    LevelUpFeatureChange(String),
+    LevelUp(Improvement),
+    Undo,
+    Reroll,
+    XCard,
+    Branch(usize),
+    Branched(Result<(GameHandle, usize), GameError>),
+    SwitchBranch(usize),
+    ToggleBranches,
+    Export(ExportFormat),
+    Exported(Result<PathBuf, GameError>),
+    LinkClicked(markdown::Url),
+    SelectTab(Tab),
+    Finish,
+    Response(Result<(), GameError>),
+    Retry,
+    DismissError,
+    Back,
 }
 
 pub(super) enum Action {
     Run(Task<Message>),
+    Finish(Vec<PlayerCharacter>),
+    /// Leave the quest for character creation, with the party as it stands.
+    Back(Vec<PlayerCharacter>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Tab {
+    Log,
+    Sheet,
+    Inventory,
+    Journal,
+}
+
+impl Tab {
+    const ALL: [Tab; 4] = [Tab::Log, Tab::Sheet, Tab::Inventory, Tab::Journal];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Tab::Log => "Log",
+            Tab::Sheet => "Character Sheet",
+            Tab::Inventory => "Inventory",
+            Tab::Journal => "Journal",
+        }
+    }
+}
+
+/// Where the game comes from, kept so loading can be retried.
+#[derive(Debug)]
+enum Source {
+    Local {
+        party: Vec<PlayerCharacter>,
+        catalog: Catalog,
+        settings: GameSettings,
+        adventure: AdventureSettings,
+        campaign: Option<CampaignContext>,
+    },
+    /// Carrying on a saved game.
+    Saved {
+        game: Box<SavedGame>,
+        catalog: Catalog,
+        settings: GameSettings,
+    },
+    /// Playing a single character in a game hosted elsewhere.
+    Remote {
+        addr: String,
+        character: Box<PlayerCharacter>,
+    },
+}
+
+/// A request to the game that can be sent again if it fails.
+#[derive(Debug, Clone)]
+enum Retry {
+    Load,
+    Start,
+    Input {
+        character: CharacterId,
+        content: String,
+    },
+    Undo,
+    Reroll,
+    XCard,
+    Branch(usize),
+}
+
+/// One timeline of the quest.
+#[derive(Debug)]
+struct Branch {
+    game: GameHandle,
+    /// Identifies the branch's event subscription.
+    game_id: u64,
+    /// The branch this was forked from, and the start of the log entry it
+    /// diverged after, as it read then.
+    parent: Option<(usize, String)>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub(super) struct QuestLog {
+    source: Source,
     game: Option<GameHandle>,
-    input_field: String,
+    /// Identifies the game's event subscription, so a reloaded game gets a
+    /// fresh one.
+    game_id: u64,
+    /// Every timeline of the quest, the first being the original.
+    branches: Vec<Branch>,
+    /// The branch being played, whose game is `game`.
+    branch: usize,
+    show_branches: bool,
+    /// Confirms the last save or export.
+    notice: Option<String>,
+    /// The GM's entries parsed as Markdown, by content, so each is parsed
+    /// once rather than on every redraw.
+    narration: HashMap<String, Vec<markdown::Item>>,
+    tab: Tab,
+    /// Tabs whose content changed since they were last viewed.
+    changed: HashSet<Tab>,
+    /// Each party member's inventory as last seen, to tell inventory changes
+    /// from other updates to the party.
+    inventories: Vec<Vec<String>>,
+    active: CharacterId,
+    /// The action being composed.
+    input: text_editor::Content,
+    /// Actions submitted this session, oldest first, to recall with Up and
+    /// Down.
+    input_history: Vec<String>,
+    /// The history entry being shown, and the draft it replaced.
+    recalled: Option<(usize, String)>,
+    /// Actions submitted while the GM was busy, sent in order once it's done.
+    queued: VecDeque<(CharacterId, String)>,
+    level_up_ability: Option<Ability>,
+    level_up_feature: String,
     waiting: bool,
+    /// The request in flight, to retry if it fails.
+    pending: Option<Retry>,
+    error: Option<(GameError, Option<Retry>)>,
 }
 
 impl QuestLog {
-    pub(super) fn new(character: PlayerCharacter) -> (Self, Task<Message>) {
-        let game_builder = GameBuilder::new(character);
-        (
-            Self {
-                game: None,
-                input_field: String::new(),
-                waiting: true,
+    pub(super) fn new(
+        party: Vec<PlayerCharacter>,
+        catalog: Catalog,
+        settings: GameSettings,
+        adventure: AdventureSettings,
+        campaign: Option<CampaignContext>,
+    ) -> (Self, Task<Message>) {
+        Self::with_source(Source::Local {
+            party,
+            catalog,
+            settings,
+            adventure,
+            campaign,
+        })
+    }
+
+    /// Carries on a saved game where it left off.
+    pub(super) fn resume(
+        game: SavedGame,
+        catalog: Catalog,
+        settings: GameSettings,
+    ) -> (Self, Task<Message>) {
+        Self::with_source(Source::Saved {
+            game: Box::new(game),
+            catalog,
+            settings,
+        })
+    }
+
+    /// Joins a game hosted at `addr`. The quest starts once the host's party is
+    /// complete.
+    pub(super) fn join(addr: String, character: PlayerCharacter) -> (Self, Task<Message>) {
+        Self::with_source(Source::Remote {
+            addr,
+            character: Box::new(character),
+        })
+    }
+
+    fn with_source(source: Source) -> (Self, Task<Message>) {
+        let mut quest = Self {
+            source,
+            game: None,
+            game_id: 0,
+            branches: Vec::new(),
+            branch: 0,
+            show_branches: false,
+            notice: None,
+            narration: HashMap::new(),
+            tab: Tab::Log,
+            changed: HashSet::new(),
+            inventories: Vec::new(),
+            active: 0,
+            input: text_editor::Content::new(),
+            input_history: Vec::new(),
+            recalled: None,
+            queued: VecDeque::new(),
+            level_up_ability: None,
+            level_up_feature: String::new(),
+            waiting: false,
+            pending: None,
+            error: None,
+        };
+        let task = quest.send(Retry::Load);
+        (quest, task)
+    }
+
+    pub(super) fn remote(&self) -> bool {
+        matches!(self.source, Source::Remote { .. })
+    }
+
+    /// The branch being played, once the game has loaded.
+    pub(super) fn game(&self) -> Option<&GameHandle> {
+        self.game.as_ref()
+    }
+
+    pub(super) fn notify(&mut self, notice: String) {
+        self.notice = Some(notice);
+    }
+
+    /// Holds off other requests while one made from outside the quest is
+    /// handled, e.g. concluding it. `finish_request` ends the wait.
+    pub(super) fn wait(&mut self) {
+        self.waiting = true;
+        self.error = None;
+    }
+
+    /// Shows an error from outside the quest, e.g. a failed save.
+    pub(super) fn show_error(&mut self, error: GameError) {
+        log::error!("{}", error);
+        self.error = Some((error, None));
+    }
+
+    /// Makes a request of the game, remembering it in case it fails.
+    fn send(&mut self, request: Retry) -> Task<Message> {
+        self.waiting = true;
+        self.error = None;
+        self.pending = Some(request.clone());
+        match request {
+            Retry::Load => match &self.source {
+                Source::Local {
+                    party,
+                    catalog,
+                    settings,
+                    adventure,
+                    campaign,
+                } => {
+                    let mut party = party.iter().cloned();
+                    let Some(first) = party.next() else {
+                        return Task::done(Message::Loaded(Err(GameError::Custom(
+                            "The party is empty".to_owned(),
+                        ))));
+                    };
+                    let mut game_builder = GameBuilder::new(first)
+                        .with_catalog(catalog.clone())
+                        .with_adventure(adventure.clone());
+                    if let Some(campaign) = campaign {
+                        game_builder = game_builder.with_campaign(campaign.clone());
+                    }
+                    for pc in party {
+                        game_builder = game_builder.with_party_member(pc);
+                    }
+                    Task::perform(settings.apply(game_builder).build(), Message::Loaded)
+                }
+                Source::Saved {
+                    game,
+                    catalog,
+                    settings,
+                } => {
+                    let game_builder =
+                        GameBuilder::resume((**game).clone()).with_catalog(catalog.clone());
+                    Task::perform(settings.apply(game_builder).build(), Message::Loaded)
+                }
+                Source::Remote { addr, character } => Task::perform(
+                    micro_quest::net::join(addr.clone(), (**character).clone()),
+                    Message::Joined,
+                ),
             },
-            Task::perform(game_builder.build(), Message::Loaded),
+            Retry::Start => {
+                let Some(game) = self.game.clone() else {
+                    return Task::none();
+                };
+                Task::perform(async move { game.start().await }, Message::Started)
+            }
+            Retry::Input { character, content } => {
+                let Some(game) = self.game.clone() else {
+                    return Task::none();
+                };
+                Task::perform(
+                    async move { game.input(character, content).await },
+                    Message::Response,
+                )
+            }
+            Retry::Undo => {
+                let Some(game) = self.game.clone() else {
+                    return Task::none();
+                };
+                Task::perform(async move { game.undo().await }, Message::Response)
+            }
+            Retry::Reroll => {
+                let Some(game) = self.game.clone() else {
+                    return Task::none();
+                };
+                Task::perform(async move { game.reroll().await }, Message::Response)
+            }
+            Retry::XCard => {
+                let Some(game) = self.game.clone() else {
+                    return Task::none();
+                };
+                Task::perform(async move { game.x_card().await }, Message::Response)
+            }
+            Retry::Branch(entry) => {
+                let Some(game) = self.game.clone() else {
+                    return Task::none();
+                };
+                Task::perform(
+                    async move { game.branch(entry).await.map(|game| (game, entry)) },
+                    Message::Branched,
+                )
+            }
+        }
+    }
+
+    /// Plays `game`, as a new branch forked from `parent` or as the original.
+    fn add_branch(&mut self, game: GameHandle, parent: Option<(usize, usize)>) {
+        let parent = parent.map(|(parent, entry)| {
+            let state = self.branches[parent].game.state().read().unwrap();
+            let content = state
+                .log
+                .get(entry)
+                .map(|entry| entry.content.as_str())
+                .unwrap_or_default();
+            let mut excerpt: String = content.chars().take(60).collect();
+            if excerpt.len() < content.len() {
+                excerpt.push_str("...");
+            }
+            (parent, excerpt)
+        });
+        self.branches.push(Branch {
+            game,
+            game_id: rand::random(),
+            parent,
+        });
+        self.switch_branch(self.branches.len() - 1);
+    }
+
+    fn switch_branch(&mut self, branch: usize) {
+        let Some(Branch { game, game_id, .. }) = self.branches.get(branch) else {
+            return;
+        };
+        self.game = Some(game.clone());
+        self.game_id = *game_id;
+        self.branch = branch;
+        self.cache_narration();
+        self.changed.clear();
+        self.inventories = self.inventories();
+    }
+
+    fn inventories(&self) -> Vec<Vec<String>> {
+        self.party()
+            .iter()
+            .map(|pc| pc.inventory().to_vec())
+            .collect()
+    }
+
+    /// Badges `tab`, unless it's the one being viewed.
+    fn mark_changed(&mut self, tab: Tab) {
+        if tab != self.tab {
+            self.changed.insert(tab);
+        }
+    }
+
+    /// Parses any GM entries in the log that aren't cached yet, and forgets
+    /// those that are no longer in it.
+    fn cache_narration(&mut self) {
+        let Some(game) = &self.game else {
+            return;
+        };
+        let state = game.state().read().unwrap();
+        let mut narration = HashMap::new();
+        for entry in state
+            .log
+            .iter()
+            .filter(|entry| entry.player == GamePlayer::GM)
+        {
+            let items = self
+                .narration
+                .remove(&entry.content)
+                .unwrap_or_else(|| markdown::parse(&entry.content).collect());
+            narration.insert(entry.content.clone(), items);
+        }
+        self.narration = narration;
+    }
+
+    /// Clears the wait, showing `result`'s error if it failed.
+    pub(super) fn finish_request(&mut self, result: Result<(), GameError>) {
+        self.waiting = false;
+        let request = self.pending.take();
+        if let Err(error) = result {
+            log::error!("{}", error);
+            self.error = Some((error, request));
+        }
+    }
+
+    fn party(&self) -> Vec<PlayerCharacter> {
+        match (&self.game, &self.source) {
+            (Some(game), _) => game.state().read().unwrap().party.clone(),
+            (None, Source::Local { party, .. }) => party.clone(),
+            (None, Source::Saved { game, .. }) => game.state.party.clone(),
+            (None, Source::Remote { character, .. }) => vec![(**character).clone()],
+        }
+    }
+
+    /// The characters this player controls, which are written back to the
+    /// roster when they leave.
+    pub(super) fn own_characters(&self) -> Vec<PlayerCharacter> {
+        let party = self.party();
+        match (&self.game, &self.source) {
+            (Some(_), Source::Remote { .. }) => {
+                party.get(self.active).cloned().into_iter().collect()
+            }
+            _ => party,
+        }
+    }
+
+    /// Whether there's an action to undo or reroll in `state`, the game's
+    /// current state. Hosted games can't take turns back, since other players
+    /// may have acted on them.
+    fn can_take_back(&self, state: &GameState) -> bool {
+        !self.waiting
+            && !self.remote()
+            && state
+                .log
+                .iter()
+                .any(|entry| matches!(entry.player, GamePlayer::PC(_)) && !entry.unsent)
+    }
+
+    /// Whether there's a GM response still in `state` to withdraw. Unlike
+    /// undo, anyone can use the X-card, in hosted games too.
+    fn can_x_card(&self, state: &GameState) -> bool {
+        !self.waiting
+            && state
+                .log
+                .iter()
+                .any(|entry| entry.player == GamePlayer::GM && entry.in_story())
+    }
+
+    fn scroll_to_end() -> Task<Message> {
+        scrollable::snap_to(
+            scrollable::Id::new("game-log"),
+            scrollable::RelativeOffset { x: 0.0, y: 1.0 },
         )
     }
 
+    pub(super) fn subscription(&self) -> Subscription<Message> {
+        match &self.game {
+            Some(game) => Subscription::batch([
+                Subscription::run_with_id(self.game_id, game.subscribe()).map(Message::Game),
+                keyboard::on_key_press(Self::shortcut),
+            ]),
+            None => Subscription::none(),
+        }
+    }
+
+    /// Ctrl+Z undoes the last action, Ctrl+R rerolls the GM's response,
+    /// Ctrl+L focuses the input and Escape cancels the turn in progress.
+    fn shortcut(key: Key, modifiers: Modifiers) -> Option<Message> {
+        if key == Key::Named(Named::Escape) {
+            return Some(Message::CancelTurn);
+        }
+        if !modifiers.command() {
+            return None;
+        }
+        match key.as_ref() {
+            Key::Character("z") => Some(Message::Undo),
+            Key::Character("r") => Some(Message::Reroll),
+            Key::Character("l") => Some(Message::FocusInput),
+            _ => None,
+        }
+    }
+
+    fn input_text(&self) -> String {
+        self.input.text().trim_end_matches('\n').to_owned()
+    }
+
+    /// Replaces the action being composed, with the cursor at its end.
+    fn set_input(&mut self, content: &str) {
+        self.input = text_editor::Content::with_text(content);
+        self.input
+            .perform(text_editor::Action::Move(text_editor::Motion::DocumentEnd));
+    }
+
+    /// Sends the next action queued while the GM was busy.
+    fn send_queued(&mut self) -> Option<Task<Message>> {
+        let (character, content) = self.queued.pop_front()?;
+        Some(self.send(Retry::Input { character, content }))
+    }
+
     pub(super) fn update(&mut self, message: Message) -> Option<Action> {
         match message {
-            Message::Loaded(game) => {
-                let game = game.expect("Error loading quest");
-                self.game = Some(game.clone());
+            Message::Loaded(Ok(game)) => {
+                self.add_branch(game, None);
+                if matches!(self.source, Source::Saved { .. }) {
+                    // The quest is already under way.
+                    self.finish_request(Ok(()));
+                    return Some(Action::Run(Self::scroll_to_end()));
+                }
+                Some(Action::Run(self.send(Retry::Start)))
+            }
+            Message::Joined(Ok((game, character))) => {
+                self.add_branch(game, None);
+                self.active = character;
+                Some(Action::Run(self.send(Retry::Start)))
+            }
+            Message::Loaded(Err(error)) | Message::Joined(Err(error)) => {
+                self.finish_request(Err(error));
+                None
+            }
+            Message::Game(event) => match event {
+                GameEvent::LogEntryAdded { entry, .. } => {
+                    self.mark_changed(Tab::Log);
+                    match entry.player {
+                        GamePlayer::GM => {
+                            self.narration
+                                .entry(entry.content)
+                                .or_insert_with_key(|content| markdown::parse(content).collect());
+                        }
+                        GamePlayer::System => self.mark_changed(Tab::Journal),
+                        GamePlayer::PC(_) => {}
+                    }
+                    Some(Action::Run(Self::scroll_to_end()))
+                }
+                GameEvent::LogChanged => {
+                    self.cache_narration();
+                    self.mark_changed(Tab::Log);
+                    self.mark_changed(Tab::Journal);
+                    Some(Action::Run(Self::scroll_to_end()))
+                }
+                GameEvent::QuestUpdated(_) => {
+                    self.mark_changed(Tab::Journal);
+                    None
+                }
+                GameEvent::PartyUpdated(id) => {
+                    if id == self.active {
+                        self.mark_changed(Tab::Sheet);
+                    }
+                    let inventories = self.inventories();
+                    if inventories.get(id) != self.inventories.get(id) {
+                        self.mark_changed(Tab::Inventory);
+                    }
+                    self.inventories = inventories;
+                    None
+                }
+                _ => None,
+            },
+            Message::Started(result) | Message::Response(result) => {
+                if let (Err(GameError::Cancelled), Some(Retry::Input { content, .. })) =
+                    (&result, &self.pending)
+                {
+                    // Hand the action back to be reworded rather than retried.
+                    if self.input_text().trim().is_empty() {
+                        let content = content.clone();
+                        self.set_input(&content);
+                    }
+                    self.waiting = false;
+                    self.pending = None;
+                    self.notice = Some("Turn cancelled".to_owned());
+                    // Actions queued behind it still go, in the order they were made.
+                    return self.send_queued().map(Action::Run);
+                }
+                let succeeded = result.is_ok();
+                self.finish_request(result);
+                if succeeded {
+                    self.send_queued().map(Action::Run)
+                } else {
+                    None
+                }
+            }
+            Message::SelectCharacter(character) => {
+                self.active = character;
+                self.level_up_ability = None;
+                self.level_up_feature = String::new();
+                None
+            }
+            Message::InputAction(action) => {
+                if matches!(action, text_editor::Action::Edit(_)) {
+                    // The edited entry becomes the draft.
+                    self.recalled = None;
+                }
+                self.input.perform(action);
+                None
+            }
+            Message::InputSubmit => {
+                self.game.as_ref()?;
+                let content = self.input_text().trim().to_owned();
+                if content.is_empty() {
+                    return None;
+                }
+                self.input = text_editor::Content::new();
+                self.recalled = None;
+                if self.input_history.last() != Some(&content) {
+                    self.input_history.push(content.clone());
+                }
+                if self.waiting {
+                    self.queued.push_back((self.active, content));
+                    return None;
+                }
+                Some(Action::Run(self.send(Retry::Input {
+                    character: self.active,
+                    content,
+                })))
+            }
+            Message::RecallPrevious => {
+                let index = match &self.recalled {
+                    None => self.input_history.len().checked_sub(1)?,
+                    Some((index, _)) => index.checked_sub(1)?,
+                };
+                let draft = match self.recalled.take() {
+                    Some((_, draft)) => draft,
+                    None => self.input_text(),
+                };
+                self.recalled = Some((index, draft));
+                let content = self.input_history[index].clone();
+                self.set_input(&content);
+                None
+            }
+            Message::RecallNext => {
+                let (index, draft) = self.recalled.take()?;
+                match self.input_history.get(index + 1).cloned() {
+                    Some(content) => {
+                        self.recalled = Some((index + 1, draft));
+                        self.set_input(&content);
+                    }
+                    None => self.set_input(&draft),
+                }
+                None
+            }
+            Message::FocusInput => Some(Action::Run(iced::widget::focus_next())),
+            Message::Unqueue(index) => {
+                self.queued.remove(index);
+                None
+            }
+            Message::CancelTurn => {
+                if self.waiting {
+                    self.game.as_ref()?.cancel();
+                }
+                None
+            }
+            Message::LevelUpAbilityChange(ability) => {
+                self.level_up_ability = Some(ability);
+                None
+            }
+            Message::LevelUpFeatureChange(content) => {
+                self.level_up_feature = content;
+                None
+            }
+            Message::LevelUp(improvement) => {
+                let game = self.game.clone()?;
+                let character = self.active;
+                self.level_up_ability = None;
+                self.level_up_feature = String::new();
+                self.waiting = true;
+                self.error = None;
+                // The level is taken before the GM is told, so there's nothing
+                // to retry if telling them fails.
+                self.pending = None;
                 Some(Action::Run(Task::perform(
-                    async move { game.start().await },
-                    Message::Started,
+                    async move { game.level_up(character, improvement).await },
+                    Message::Response,
                 )))
             }
-            Message::Started(_) => {
-                self.waiting = false;
+            Message::Undo => {
+                let game = self.game.as_ref()?;
+                if !self.can_take_back(&game.state().read().unwrap()) {
+                    return None;
+                }
+                Some(Action::Run(self.send(Retry::Undo)))
+            }
+            Message::Reroll => {
+                let game = self.game.as_ref()?;
+                if !self.can_take_back(&game.state().read().unwrap()) {
+                    return None;
+                }
+                Some(Action::Run(self.send(Retry::Reroll)))
+            }
+            Message::XCard => {
+                let game = self.game.as_ref()?;
+                if !self.can_x_card(&game.state().read().unwrap()) {
+                    return None;
+                }
+                Some(Action::Run(self.send(Retry::XCard)))
+            }
+            Message::Branch(entry) => {
+                if self.waiting || self.remote() {
+                    return None;
+                }
+                Some(Action::Run(self.send(Retry::Branch(entry))))
+            }
+            Message::Branched(Ok((game, entry))) => {
+                self.finish_request(Ok(()));
+                self.add_branch(game, Some((self.branch, entry)));
+                self.show_branches = true;
+                Some(Action::Run(Self::scroll_to_end()))
+            }
+            Message::Branched(Err(error)) => {
+                self.finish_request(Err(error));
                 None
             }
-            Message::InputFieldChange(content) => {
-                self.input_field = content;
+            Message::SwitchBranch(branch) => {
+                if !self.waiting {
+                    self.switch_branch(branch);
+                }
+                Some(Action::Run(Self::scroll_to_end()))
+            }
+            Message::ToggleBranches => {
+                self.show_branches = !self.show_branches;
                 None
             }
-            Message::InputSubmit => {
-                if let Some(game) = &self.game {
-                    let game = game.clone();
-                    let content = self.input_field.clone();
-                    self.input_field = String::new();
-                    self.waiting = true;
-                    Some(Action::Run(
-                        Task::perform(async move { game.input(content).await }, |_| {
-                            Message::Response
-                        })
-                        .chain(scrollable::snap_to(
-                            scrollable::Id::new("game-log"),
-                            scrollable::RelativeOffset { x: 0.0, y: 1.0 },
-                        )),
-                    ))
+            Message::Export(format) => {
+                let state = self.game.as_ref()?.state().read().unwrap().clone();
+                Some(Action::Run(Task::perform(
+                    async move {
+                        export::export_to_file(&state, format)
+                            .map_err(|error| GameError::Custom(error.to_string()))
+                    },
+                    Message::Exported,
+                )))
+            }
+            Message::Exported(Ok(path)) => {
+                self.notice = Some(format!("Exported to {}", path.display()));
+                None
+            }
+            Message::Exported(Err(error)) => {
+                self.error = Some((error, None));
+                None
+            }
+            Message::LinkClicked(url) => {
+                // Links come from the GM, so only open web pages.
+                if matches!(url.scheme(), "http" | "https") {
+                    if let Err(error) = open::that_detached(url.as_str()) {
+                        log::error!("Could not open {}: {}", url, error);
+                    }
                 } else {
-                    None
+                    log::warn!("Not opening link {}", url);
                 }
+                None
             }
-            Message::Response => {
-                self.waiting = false;
-                Some(Action::Run(scrollable::snap_to(
-                    scrollable::Id::new("game-log"),
-                    scrollable::RelativeOffset { x: 0.0, y: 1.0 },
-                )))
+            Message::SelectTab(tab) => {
+                self.tab = tab;
+                self.changed.remove(&tab);
+                (tab == Tab::Log).then(|| Action::Run(Self::scroll_to_end()))
+            }
+            Message::Finish => {
+                // Already finishing, if concluding the quest for its campaign.
+                if self.waiting {
+                    return None;
+                }
+                self.game.as_ref()?;
+                Some(Action::Finish(self.own_characters()))
             }
+            Message::Retry => {
+                let (_, request) = self.error.take()?;
+                Some(Action::Run(self.send(request?)))
+            }
+            Message::DismissError => {
+                self.error = None;
+                None
+            }
+            Message::Back => Some(Action::Back(self.own_characters())),
         }
     }
 
-    pub(super) fn view(&self) -> Element<Message> {
+    /// Draws the quest, with the GM's narration styled for `theme`.
+    pub(super) fn view(&self, theme: &Theme) -> Element<'_, Message> {
         if let Some(game) = &self.game {
             let state = game.state().read().unwrap();
             column![
-                scrollable(column![
-                    self.view_quest_summary(&state.quest),
-                    vertical_space().height(10),
-                    Column::with_children(
-                        state
-                            .log
-                            .iter()
-                            .map(|entry| column![
-                                self.view_log_entry(&entry),
-                                vertical_space().height(20)
-                            ])
-                            .map(Element::from)
-                    )
-                ])
-                .width(Fill)
-                .height(Fill)
-                .spacing(20)
-                .id(scrollable::Id::new("game-log")),
+                self.view_error(),
+                self.view_branches(),
+                self.view_quest_card(&state),
+                vertical_space().height(10),
+                self.view_tab_bar(),
+                vertical_space().height(10),
+                match self.tab {
+                    Tab::Log => self.view_log(&state, theme),
+                    Tab::Sheet => self.view_sheet(&state),
+                    Tab::Inventory => self.view_inventory(&state),
+                    Tab::Journal => self.view_journal(&state),
+                },
                 vertical_space().height(20),
-                if self.waiting {
-                    Element::from(Spinner::default())
-                } else {
-                    Element::from(
-                        text_input("What would you like to do?", &self.input_field)
+                self.view_party_switcher(&state),
+                self.view_character_status(&state),
+                match &self.notice {
+                    Some(notice) => Element::from(
+                        text(notice)
+                            .size(12)
                             .width(Fill)
-                            .on_input(Message::InputFieldChange)
-                            .on_submit(Message::InputSubmit),
-                    )
+                            .align_x(Horizontal::Right),
+                    ),
+                    None => vertical_space().height(0).into(),
+                },
+                vertical_space().height(10),
+                if !self.waiting
+                    && state.party[self.active].pending_level_ups(&state.level_table) > 0
+                {
+                    self.view_level_up(&state)
+                } else {
+                    self.view_input(&state)
                 },
             ]
             .width(Fill)
             .align_x(Center)
             .padding(20)
             .into()
+        } else if self.error.is_some() {
+            container(self.view_error()).padding(20).into()
         } else {
-            text("Loading...").into()
+            column![text("Loading..."), Spinner::default()]
+                .spacing(10)
+                .padding(20)
+                .into()
         }
     }
 
-    fn view_log_entry(&self, entry: &GameLogEntry) -> Element<Message> {
-        let player_text = match entry.player {
-            GamePlayer::GM => "GM:",
-            GamePlayer::PC => "PC:",
+    fn view_error(&self) -> Element<'_, Message> {
+        let Some((error, retry)) = &self.error else {
+            return vertical_space().height(0).into();
+        };
+        container(column![
+            text(error.class()).size(18),
+            text(error.to_string()),
+            vertical_space().height(10),
+            row![
+                button("Retry").on_press_maybe(retry.is_some().then_some(Message::Retry)),
+                button("Dismiss")
+                    .style(button::secondary)
+                    .on_press_maybe(self.game.is_some().then_some(Message::DismissError)),
+                horizontal_space(),
+                button("Back to character creation")
+                    .style(button::secondary)
+                    .on_press(Message::Back),
+            ]
+            .spacing(10),
+        ])
+        .padding(10)
+        .width(Fill)
+        .style(|theme: &Theme| {
+            let danger = theme.extended_palette().danger.weak;
+            container::Style {
+                background: Some(danger.color.into()),
+                text_color: Some(danger.text),
+                border: Border::default().rounded(4),
+                ..container::Style::default()
+            }
+        })
+        .into()
+    }
+
+    /// Lists every branch under the one it was forked from, with the log
+    /// entry it diverged after.
+    fn view_branches(&self) -> Element<'_, Message> {
+        if !self.show_branches {
+            return vertical_space().height(0).into();
+        }
+        let mut rows = Vec::new();
+        self.push_branch_rows(None, 0, &mut rows);
+        container(
+            column![
+                text("Branches").size(18),
+                scrollable(Column::with_children(rows).spacing(5)).height(150),
+            ]
+            .spacing(10),
+        )
+        .padding(10)
+        .width(Fill)
+        .style(container::bordered_box)
+        .into()
+    }
+
+    fn push_branch_rows(
+        &self,
+        parent: Option<usize>,
+        depth: u16,
+        rows: &mut Vec<Element<'_, Message>>,
+    ) {
+        for (index, branch) in self.branches.iter().enumerate() {
+            if branch.parent.as_ref().map(|(parent, _)| *parent) != parent {
+                continue;
+            }
+            let label = match &branch.parent {
+                None => "Original timeline".to_owned(),
+                Some((_, excerpt)) => format!("Branch {}, after \"{}\"", index, excerpt),
+            };
+            let style = if index == self.branch {
+                button::primary
+            } else {
+                button::secondary
+            };
+            rows.push(
+                row![
+                    horizontal_space().width(20 * depth),
+                    button(text(label).size(14))
+                        .style(style)
+                        .on_press_maybe((!self.waiting).then_some(Message::SwitchBranch(index))),
+                ]
+                .into(),
+            );
+            self.push_branch_rows(Some(index), depth + 1, rows);
+        }
+    }
+
+    fn view_log_entry(
+        &self,
+        state: &GameState,
+        theme: &Theme,
+        index: usize,
+        entry: &GameLogEntry,
+    ) -> Element<'_, Message> {
+        let (player_text, player_colour) = match entry.player {
+            GamePlayer::GM => ("GM:".to_owned(), color!(0x666666)),
+            GamePlayer::PC(id) => (
+                format!("{}:", state.party[id].name()),
+                PARTY_COLOURS[id % PARTY_COLOURS.len()],
+            ),
+            GamePlayer::System => (String::new(), color!(0x666666)),
         };
         row![
             text(player_text)
-                .color(color!(0x666666))
+                .color(player_colour)
                 .align_x(Horizontal::Left)
-                .width(60),
-            text(entry.content.clone()).width(Fill),
+                .width(100),
+            if entry.unsent || entry.retconned {
+                let note = if entry.unsent {
+                    "Not sent"
+                } else {
+                    "Withdrawn with the X-card"
+                };
+                Element::from(
+                    column![
+                        text(entry.content.clone()).color(color!(0x999999)),
+                        text(note).size(12).color(color!(0xcc4125)),
+                    ]
+                    .width(Fill),
+                )
+            } else if let Some(items) = self
+                .narration
+                .get(&entry.content)
+                .filter(|_| entry.player == GamePlayer::GM)
+            {
+                let narration = markdown::view(
+                    items,
+                    markdown::Settings::default(),
+                    markdown::Style::from_palette(theme.palette()),
+                )
+                .map(Message::LinkClicked);
+                let flagged = (!entry.flagged.is_empty()).then(|| {
+                    text(format!(
+                        "May touch on a line or veil: {}",
+                        entry.flagged.join(", ")
+                    ))
+                    .size(12)
+                    .color(color!(0xe69138))
+                });
+                column![narration].push_maybe(flagged).width(Fill).into()
+            } else {
+                Element::from(text(entry.content.clone()).width(Fill))
+            },
+            tooltip(
+                button(text("Branch").size(12))
+                    .style(button::text)
+                    .on_press_maybe(
+                        (!self.waiting && !self.remote() && !entry.unsent)
+                            .then_some(Message::Branch(index))
+                    ),
+                text("Carry on from here in a new timeline").size(12),
+                tooltip::Position::Left,
+            )
+            .style(container::rounded_box),
+        ]
+        .into()
+    }
+
+    fn view_party_switcher(&self, state: &GameState) -> Element<'_, Message> {
+        if state.party.len() < 2 || self.remote() {
+            return vertical_space().height(0).into();
+        }
+        column![
+            row(state.party.iter().enumerate().map(|(id, pc)| {
+                let style = if id == self.active {
+                    button::primary
+                } else {
+                    button::secondary
+                };
+                button(text(pc.name().to_owned()).color(PARTY_COLOURS[id % PARTY_COLOURS.len()]))
+                    .style(style)
+                    .on_press(Message::SelectCharacter(id))
+                    .into()
+            }))
+            .spacing(10),
+            vertical_space().height(10),
         ]
         .into()
     }
 
-    fn view_quest_summary(&self, quest: &QuestDefinition) -> Element<Message> {
+    fn view_character_status(&self, state: &GameState) -> Element<'_, Message> {
+        let pc = &state.party[self.active];
+        let xp = match state.level_table.next_threshold(pc.level()) {
+            Some(next) => format!("XP: {} / {}", pc.xp(), next),
+            None => format!("XP: {}", pc.xp()),
+        };
         row![
-            horizontal_space().width(60),
-            container(column![
-                text(quest.title.clone())
+            text(format!(
+                "{} - Level {} {} {}",
+                pc.name(),
+                pc.level(),
+                pc.race(),
+                pc.class()
+            )),
+            horizontal_space(),
+            text(format!("HP: {} / {}", pc.hp(), pc.max_hp())),
+            horizontal_space().width(20),
+            text(xp),
+            horizontal_space().width(20),
+            self.view_take_back(state, "Undo", "Ctrl+Z", Message::Undo),
+            horizontal_space().width(5),
+            self.view_take_back(state, "Reroll", "Ctrl+R", Message::Reroll),
+            horizontal_space().width(5),
+            tooltip(
+                button("X-card")
+                    .style(button::danger)
+                    .on_press_maybe(self.can_x_card(state).then_some(Message::XCard)),
+                text("Withdraw the last scene, no questions asked").size(12),
+                tooltip::Position::Top,
+            )
+            .style(container::rounded_box),
+            horizontal_space().width(5),
+            button(text(format!("Branches ({})", self.branches.len())))
+                .style(button::secondary)
+                .on_press(Message::ToggleBranches),
+            horizontal_space().width(5),
+            pick_list(ExportFormat::ALL, None::<ExportFormat>, Message::Export)
+                .placeholder("Export"),
+            horizontal_space().width(20),
+            button("End Quest").on_press_maybe((!self.waiting).then_some(Message::Finish)),
+        ]
+        .align_y(Center)
+        .width(Fill)
+        .into()
+    }
+
+    fn view_take_back(
+        &self,
+        state: &GameState,
+        label: &'static str,
+        shortcut: &'static str,
+        message: Message,
+    ) -> Element<'_, Message> {
+        tooltip(
+            button(label)
+                .style(button::secondary)
+                .on_press_maybe(self.can_take_back(state).then_some(message)),
+            text(shortcut).size(12),
+            tooltip::Position::Top,
+        )
+        .style(container::rounded_box)
+        .into()
+    }
+
+    /// The action being composed, with actions queued behind the turn in
+    /// progress above it. Enter submits, Shift+Enter starts a new line and Up
+    /// and Down on the first and last lines recall earlier actions.
+    fn view_input(&self, state: &GameState) -> Element<'_, Message> {
+        let (line, _) = self.input.cursor_position();
+        let last_line = self.input.line_count().saturating_sub(1);
+        let waiting = self.waiting;
+        let editor = text_editor(&self.input)
+            .placeholder(if waiting {
+                "Queue another action while the GM is thinking..."
+            } else {
+                "What would you like to do? (Shift+Enter for a new line)"
+            })
+            .on_action(Message::InputAction)
+            .key_binding(move |key_press| {
+                use text_editor::Binding;
+                let focused = key_press.status == text_editor::Status::Focused;
+                let modifiers = key_press.modifiers;
+                if !focused {
+                    return Binding::from_key_press(key_press);
+                }
+                match key_press.key.as_ref() {
+                    Key::Named(Named::Enter) if !modifiers.shift() => {
+                        Some(Binding::Custom(Message::InputSubmit))
+                    }
+                    Key::Named(Named::ArrowUp) if line == 0 && modifiers.is_empty() => {
+                        Some(Binding::Custom(Message::RecallPrevious))
+                    }
+                    Key::Named(Named::ArrowDown) if line == last_line && modifiers.is_empty() => {
+                        Some(Binding::Custom(Message::RecallNext))
+                    }
+                    Key::Named(Named::Escape) if waiting => {
+                        Some(Binding::Custom(Message::CancelTurn))
+                    }
+                    // Already focused, don't let the shortcut move focus on.
+                    Key::Character("l") if modifiers.command() => {
+                        Some(Binding::Sequence(Vec::new()))
+                    }
+                    _ => Binding::from_key_press(key_press),
+                }
+            });
+        let queued = self
+            .queued
+            .iter()
+            .enumerate()
+            .map(|(index, (character, content))| {
+                row![
+                    text(format!(
+                        "Queued for {}: {}",
+                        state.party[*character].name(),
+                        content
+                    ))
+                    .size(14)
+                    .color(color!(0x999999))
+                    .width(Fill),
+                    button(text("Remove").size(12))
+                        .style(button::text)
+                        .on_press(Message::Unqueue(index)),
+                ]
+                .align_y(Center)
+                .into()
+            });
+        let status: Element<Message> = if waiting {
+            row![
+                Spinner::default().width(20).height(20),
+                text("The GM is thinking...").size(14),
+                horizontal_space(),
+                button(text("Cancel").size(14))
+                    .style(button::secondary)
+                    .on_press_maybe((!self.remote()).then_some(Message::CancelTurn)),
+            ]
+            .spacing(10)
+            .align_y(Center)
+            .into()
+        } else {
+            vertical_space().height(0).into()
+        };
+        Column::with_children(queued)
+            .push(status)
+            .push(editor)
+            .spacing(5)
+            .width(Fill)
+            .into()
+    }
+
+    fn view_level_up(&self, state: &GameState) -> Element<'_, Message> {
+        let pc = &state.party[self.active];
+        let feature = self.level_up_feature.trim();
+        container(column![
+            text(format!(
+                "Level up! Choose an improvement for {}'s level {}:",
+                pc.name(),
+                pc.level() + 1
+            )),
+            vertical_space().height(10),
+            row![
+                button(text(format!("+{} HP", state.level_table.hp_per_level())))
+                    .on_press(Message::LevelUp(Improvement::HitPoints)),
+                horizontal_space().width(20),
+                pick_list(
+                    Ability::ALL,
+                    self.level_up_ability,
+                    Message::LevelUpAbilityChange
+                )
+                .placeholder("Ability"),
+                button(text(format!("+{}", state.level_table.ability_bump()))).on_press_maybe(
+                    self.level_up_ability
+                        .map(|ability| Message::LevelUp(Improvement::AbilityBump(ability)))
+                ),
+                horizontal_space().width(20),
+                text_input("New feature", &self.level_up_feature)
+                    .on_input(Message::LevelUpFeatureChange),
+                button("Learn").on_press_maybe(
+                    (!feature.is_empty())
+                        .then(|| Message::LevelUp(Improvement::Feature(feature.to_owned())))
+                ),
+            ]
+            .spacing(5)
+            .align_y(Center),
+        ])
+        .padding(10)
+        .width(Fill)
+        .style(container::bordered_box)
+        .into()
+    }
+
+    /// The quest, pinned above the tabs so it doesn't scroll away.
+    fn view_quest_card(&self, state: &GameState) -> Element<'_, Message> {
+        let quest = &state.quest;
+        let title = match quest.title.as_str() {
+            "" => "Preparing the quest...",
+            title => title,
+        };
+        Card::new(
+            text(title.to_owned()).size(24),
+            column![
+                text(quest.description.clone()),
+                text(quest.objective_summary.clone()).style(text::secondary),
+                Self::view_progress(state),
+            ]
+            .spacing(5),
+        )
+        .width(Fill)
+        .into()
+    }
+
+    /// How far through its target number of scenes the quest is.
+    fn view_progress<'a>(state: &GameState) -> Element<'a, Message> {
+        let target = state.target_scenes();
+        let scene = match state.scene {
+            0 => "Setting the scene".to_owned(),
+            scene if scene > target => format!("Scene {} (past ~{})", scene, target),
+            scene => format!("Scene {} of ~{}", scene, target),
+        };
+        let scene = match state.scene_title.as_str() {
+            "" => scene,
+            title => format!("{}: {}", scene, title),
+        };
+        row![
+            progress_bar(0.0..=target as f32, state.scene.min(target) as f32)
+                .height(8)
+                .width(160),
+            text(scene).size(14),
+            horizontal_space(),
+            text(format!("Turn {}", state.turn))
+                .size(14)
+                .style(text::secondary),
+        ]
+        .spacing(10)
+        .align_y(Center)
+        .into()
+    }
+
+    fn view_tab_bar(&self) -> Element<'_, Message> {
+        Tab::ALL
+            .iter()
+            .fold(TabBar::new(Message::SelectTab), |bar, tab| {
+                let label = tab.label().to_owned();
+                let label = if self.changed.contains(tab) {
+                    TabLabel::IconText('\u{25cf}', label)
+                } else {
+                    TabLabel::Text(label)
+                };
+                bar.push(*tab, label)
+            })
+            .set_active_tab(&self.tab)
+            .text_size(16.0)
+            // The dot badging changed tabs is in the text font, not an icon font.
+            .icon_font(Font::DEFAULT)
+            .icon_size(10.0)
+            .set_position(tab_bar::Position::Right)
+            .into()
+    }
+
+    fn view_log(&self, state: &GameState, theme: &Theme) -> Element<'_, Message> {
+        scrollable(Column::with_children(
+            state
+                .log
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| {
+                    column![
+                        self.view_log_entry(state, theme, index, entry),
+                        vertical_space().height(20)
+                    ]
+                })
+                .map(Element::from),
+        ))
+        .width(Fill)
+        .height(Fill)
+        .spacing(20)
+        .id(scrollable::Id::new("game-log"))
+        .into()
+    }
+
+    /// The active character in full.
+    fn view_sheet(&self, state: &GameState) -> Element<'_, Message> {
+        let pc = &state.party[self.active];
+        let xp = match state.level_table.next_threshold(pc.level()) {
+            Some(next) => format!("{} / {}", pc.xp(), next),
+            None => pc.xp().to_string(),
+        };
+        let mut sheet = column![
+            row![
+                text(pc.name().to_owned())
                     .size(24)
-                    .align_x(Horizontal::Center),
-                vertical_space().height(5),
-                text(quest.description.clone()).align_x(Horizontal::Left),
-                vertical_space().height(5),
-                text(quest.objective_summary.clone()).align_x(Horizontal::Left),
-            ],)
-            .padding(10)
-            .style(container::bordered_box),
+                    .color(PARTY_COLOURS[self.active % PARTY_COLOURS.len()]),
+                horizontal_space(),
+                if pc.pending_level_ups(&state.level_table) > 0 {
+                    Element::from(Badge::new(text("Level up!").size(12)))
+                } else {
+                    vertical_space().height(0).into()
+                },
+            ]
+            .align_y(Center),
+            text(format!("Level {} {} {}", pc.level(), pc.race(), pc.class()))
+                .style(text::secondary),
+            row(Ability::ALL.iter().map(|ability| {
+                container(
+                    column![
+                        text(ability.abbreviation()).size(12),
+                        text(pc.abilities().get(*ability).to_string()).size(22),
+                    ]
+                    .align_x(Center),
+                )
+                .padding(5)
+                .width(60)
+                .style(container::bordered_box)
+                .into()
+            }))
+            .spacing(10),
+            text(format!("HP: {} / {}    XP: {}", pc.hp(), pc.max_hp(), xp)),
         ]
+        .spacing(10);
+        if !pc.features().is_empty() {
+            sheet = sheet.push(text("Features").size(18));
+            sheet = sheet.push(text(pc.features().join(", ")));
+        }
+        let details = pc.details();
+        for (label, value) in [
+            ("Appearance", &details.appearance),
+            ("Backstory", &details.backstory),
+            ("Personality", &details.personality_traits),
+            ("Ideals", &details.ideals),
+            ("Flaws", &details.flaws),
+        ] {
+            if let Some(value) = value {
+                sheet = sheet.push(text(label).size(18));
+                sheet = sheet.push(text(value.clone()));
+            }
+        }
+        scrollable(sheet.padding(10))
+            .width(Fill)
+            .height(Fill)
+            .into()
+    }
+
+    /// What everyone in the party is carrying.
+    fn view_inventory(&self, state: &GameState) -> Element<'_, Message> {
+        scrollable(
+            Column::with_children(state.party.iter().enumerate().map(|(id, pc)| {
+                let items: Element<'_, Message> = if pc.inventory().is_empty() {
+                    text("Nothing").style(text::secondary).into()
+                } else {
+                    Column::with_children(
+                        pc.inventory()
+                            .iter()
+                            .map(|item| text(format!("\u{2022} {}", item)).into()),
+                    )
+                    .into()
+                };
+                column![
+                    text(pc.name().to_owned())
+                        .size(18)
+                        .color(PARTY_COLOURS[id % PARTY_COLOURS.len()]),
+                    items,
+                ]
+                .spacing(5)
+                .into()
+            }))
+            .spacing(20)
+            .padding(10),
+        )
+        .width(Fill)
+        .height(Fill)
+        .into()
+    }
+
+    /// The objective and everything that happened to the party along the
+    /// way: experience, wounds, items and levels.
+    fn view_journal(&self, state: &GameState) -> Element<'_, Message> {
+        let events: Vec<_> = state
+            .log
+            .iter()
+            .filter(|entry| entry.player == GamePlayer::System && entry.in_story())
+            .map(|entry| Element::from(text(format!("\u{2022} {}", entry.content))))
+            .collect();
+        scrollable(
+            column![
+                text("Objective").size(18),
+                text(state.quest.objective_summary.clone()),
+                vertical_space().height(10),
+                text("Along the way").size(18),
+                if events.is_empty() {
+                    Element::from(text("Nothing yet").style(text::secondary))
+                } else {
+                    Column::with_children(events).spacing(5).into()
+                },
+            ]
+            .spacing(5)
+            .padding(10),
+        )
+        .width(Fill)
+        .height(Fill)
         .into()
     }
 }
@@ -0,0 +1,223 @@
+use iced::alignment::Horizontal;
+use iced::task::Task;
+use iced::widget::{
+    button, checkbox, column, container, row, scrollable, text, text_input, vertical_space, Column,
+};
+use iced::{color, Center, Element, Fill};
+
+use micro_quest::net::DEFAULT_PORT;
+use micro_quest::roster::{Roster, RosterEntry};
+
+#[derive(Debug, Clone)]
+pub(super) enum Message {
+    Play(String),
+    TogglePartyMember(String, bool),
+    PlayParty,
+    HostAddressChange(String),
+    Join(String),
+    Duplicate(String),
+    Delete(String),
+    New,
+    Back,
+}
+
+pub(super) enum Action {
+    Play(Vec<RosterEntry>),
+    /// Join a game hosted at the address with a character.
+    Join(String, Box<RosterEntry>),
+    Create,
+    /// Return to the title screen.
+    Back,
+}
+
+#[derive(Debug)]
+pub(super) struct RosterSelect {
+    roster: Roster,
+    entries: Vec<RosterEntry>,
+    /// Roster ids chosen for a hot-seat party, in the order they were picked.
+    party: Vec<String>,
+    host_address: String,
+    error: Option<String>,
+}
+
+impl RosterSelect {
+    pub(super) fn new(roster: Roster) -> (Self, Task<Message>) {
+        let mut select = Self {
+            roster,
+            entries: Vec::new(),
+            party: Vec::new(),
+            host_address: String::new(),
+            error: None,
+        };
+        select.refresh();
+        (select, Task::none())
+    }
+
+    fn refresh(&mut self) {
+        match self.roster.list() {
+            Ok(entries) => {
+                self.party
+                    .retain(|id| entries.iter().any(|entry| &entry.id == id));
+                self.entries = entries;
+            }
+            Err(error) => self.error = Some(error.to_string()),
+        }
+    }
+
+    pub(super) fn update(&mut self, message: Message) -> Option<Action> {
+        self.error = None;
+        match message {
+            Message::Play(id) => match self.roster.load(&id) {
+                Ok(entry) => Some(Action::Play(vec![entry])),
+                Err(error) => {
+                    self.error = Some(error.to_string());
+                    None
+                }
+            },
+            Message::TogglePartyMember(id, selected) => {
+                self.party.retain(|member| member != &id);
+                if selected {
+                    self.party.push(id);
+                }
+                None
+            }
+            Message::PlayParty => {
+                let party: Result<Vec<_>, _> =
+                    self.party.iter().map(|id| self.roster.load(id)).collect();
+                match party {
+                    Ok(party) if !party.is_empty() => Some(Action::Play(party)),
+                    Ok(_) => None,
+                    Err(error) => {
+                        self.error = Some(error.to_string());
+                        None
+                    }
+                }
+            }
+            Message::HostAddressChange(content) => {
+                self.host_address = content;
+                None
+            }
+            Message::Join(id) => match self.roster.load(&id) {
+                Ok(entry) => {
+                    let addr = self.host_address.trim();
+                    let addr = if addr.is_empty() {
+                        format!("127.0.0.1:{}", DEFAULT_PORT)
+                    } else if addr.contains(':') {
+                        addr.to_owned()
+                    } else {
+                        format!("{}:{}", addr, DEFAULT_PORT)
+                    };
+                    Some(Action::Join(addr, Box::new(entry)))
+                }
+                Err(error) => {
+                    self.error = Some(error.to_string());
+                    None
+                }
+            },
+            Message::Duplicate(id) => {
+                if let Err(error) = self.roster.duplicate(&id) {
+                    self.error = Some(error.to_string());
+                }
+                self.refresh();
+                None
+            }
+            Message::Delete(id) => {
+                if let Err(error) = self.roster.delete(&id) {
+                    self.error = Some(error.to_string());
+                }
+                self.refresh();
+                None
+            }
+            Message::New => Some(Action::Create),
+            Message::Back => Some(Action::Back),
+        }
+    }
+
+    pub(super) fn view(&self) -> Element<'_, Message> {
+        let entries: Element<Message> = if self.entries.is_empty() {
+            text("No saved characters yet.")
+                .color(color!(0x999999))
+                .into()
+        } else {
+            scrollable(
+                Column::with_children(
+                    self.entries
+                        .iter()
+                        .map(|entry| self.view_entry(entry))
+                        .collect::<Vec<_>>(),
+                )
+                .spacing(10),
+            )
+            .height(Fill)
+            .into()
+        };
+        column![
+            vertical_space().height(40),
+            text("Choose your hero").size(24),
+            vertical_space().height(20),
+            entries,
+            vertical_space().height(20),
+            row![
+                button("Back")
+                    .style(button::secondary)
+                    .on_press(Message::Back),
+                button("New character").on_press(Message::New),
+                button(text(format!("Play as party ({})", self.party.len())))
+                    .on_press_maybe((self.party.len() > 1).then_some(Message::PlayParty)),
+            ]
+            .spacing(20),
+            vertical_space().height(20),
+            row![
+                text("Host address:").width(120),
+                text_input(&format!("127.0.0.1:{}", DEFAULT_PORT), &self.host_address)
+                    .on_input(Message::HostAddressChange),
+            ]
+            .align_y(Center),
+            vertical_space().height(10),
+            text(self.error.clone().unwrap_or_default()).color(color!(0xcc4444)),
+        ]
+        .width(Fill)
+        .align_x(Center)
+        .padding(20)
+        .into()
+    }
+
+    fn view_entry<'a>(&self, entry: &'a RosterEntry) -> Element<'a, Message> {
+        let pc = &entry.character;
+        container(
+            row![
+                column![
+                    text(pc.name().to_owned()).size(20),
+                    text(format!(
+                        "Level {} {} {} - {} XP, {}/{} HP",
+                        pc.level(),
+                        pc.race(),
+                        pc.class(),
+                        pc.xp(),
+                        pc.hp(),
+                        pc.max_hp()
+                    ))
+                    .color(color!(0x999999)),
+                ]
+                .width(Fill)
+                .align_x(Horizontal::Left),
+                checkbox("Party", self.party.contains(&entry.id)).on_toggle({
+                    let id = entry.id.clone();
+                    move |selected| Message::TogglePartyMember(id.clone(), selected)
+                }),
+                button("Play").on_press(Message::Play(entry.id.clone())),
+                button("Join").on_press(Message::Join(entry.id.clone())),
+                button("Duplicate").on_press(Message::Duplicate(entry.id.clone())),
+                button("Delete")
+                    .style(button::danger)
+                    .on_press(Message::Delete(entry.id.clone())),
+            ]
+            .spacing(10)
+            .align_y(Center),
+        )
+        .padding(10)
+        .width(Fill)
+        .style(container::bordered_box)
+        .into()
+    }
+}
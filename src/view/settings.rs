@@ -0,0 +1,371 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use iced::widget::{button, checkbox, column, pick_list, row, text, text_input, vertical_space};
+use iced::{color, Center, Element, Fill, Theme};
+use serde::{Deserialize, Serialize};
+
+use micro_quest::game::{GameBuilder, Verbosity};
+use micro_quest::schema::AdventureSettings;
+
+/// The longest auto-save interval, in minutes.
+const MAX_AUTOSAVE_MINUTES: u32 = 120;
+
+/// How large the interface is drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum TextSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl TextSize {
+    pub(super) const ALL: [TextSize; 3] = [TextSize::Small, TextSize::Medium, TextSize::Large];
+
+    /// The scale factor the whole window is drawn at.
+    pub(super) fn scale(&self) -> f64 {
+        match self {
+            TextSize::Small => 0.85,
+            TextSize::Medium => 1.0,
+            TextSize::Large => 1.25,
+        }
+    }
+}
+
+impl std::fmt::Display for TextSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextSize::Small => write!(f, "Small"),
+            TextSize::Medium => write!(f, "Medium"),
+            TextSize::Large => write!(f, "Large"),
+        }
+    }
+}
+
+/// How new games reach the GM. Empty fields fall back to the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct GameSettings {
+    /// Empty to use `OPENAI_API_KEY`.
+    pub(super) api_key: String,
+    pub(super) model: String,
+    pub(super) endpoint: String,
+    pub(super) verbosity: Verbosity,
+}
+
+impl GameSettings {
+    pub(super) fn apply(&self, mut builder: GameBuilder) -> GameBuilder {
+        if !self.api_key.is_empty() {
+            builder = builder.with_api_key(self.api_key.clone());
+        }
+        if !self.model.is_empty() {
+            builder = builder.with_model(self.model.clone());
+        }
+        if !self.endpoint.is_empty() {
+            builder = builder.with_endpoint(self.endpoint.clone());
+        }
+        builder.with_verbosity(self.verbosity)
+    }
+}
+
+/// Preferences that apply across screens, kept in `settings.json` in the
+/// config directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Settings {
+    #[serde(with = "theme_name")]
+    pub(super) theme: Theme,
+    pub(super) text_size: TextSize,
+    /// Minutes between saves during a quest, 0 for none.
+    pub(super) autosave_minutes: u32,
+    pub(super) game: GameSettings,
+    /// The adventure chosen for the last new quest, offered again for the next.
+    /// Without its lines and veils, which aren't kept.
+    pub(super) adventure: AdventureSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::Dark,
+            text_size: TextSize::default(),
+            autosave_minutes: 0,
+            game: GameSettings::default(),
+            adventure: AdventureSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// `<config dir>/uquest/settings.json`, e.g. `~/.config/uquest/settings.json`
+    /// on Linux.
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("uquest").join("settings.json"))
+    }
+
+    /// The saved settings, or the defaults if there are none or they can't be
+    /// read.
+    pub(super) fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(error) => {
+                log::error!("Could not read {}: {}", path.display(), error);
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&content).unwrap_or_else(|error| {
+            log::error!("Invalid settings in {}: {}", path.display(), error);
+            Self::default()
+        })
+    }
+
+    pub(super) fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No config directory available")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        write_private(&path, content.as_bytes()).map_err(|error| error.to_string())
+    }
+}
+
+/// Writes `path` readable by the user only where that's possible, since the
+/// settings may hold an API key.
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files.
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(content)
+}
+
+/// Stores a theme by its name, falling back to the default for unknown names.
+mod theme_name {
+    use iced::Theme;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        theme: &Theme,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&theme.to_string())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Theme, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Theme::ALL
+            .iter()
+            .find(|theme| theme.to_string() == name)
+            .cloned()
+            .unwrap_or(Theme::Dark))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Message {
+    ApiKeyChange(String),
+    ToggleShowApiKey(bool),
+    ModelChange(String),
+    EndpointChange(String),
+    ThemeSelected(Theme),
+    TextSizeSelected(TextSize),
+    AutosaveChange(String),
+    VerbositySelected(Verbosity),
+    Save,
+    Back,
+}
+
+pub(super) enum Action {
+    /// The settings were saved and should be applied straight away.
+    Apply(Settings),
+    Back,
+}
+
+#[derive(Debug)]
+pub(super) struct SettingsScreen {
+    settings: Settings,
+    show_api_key: bool,
+    autosave_field: String,
+    error: Option<String>,
+    saved: bool,
+}
+
+impl SettingsScreen {
+    pub(super) fn new(settings: Settings) -> Self {
+        Self {
+            autosave_field: settings.autosave_minutes.to_string(),
+            settings,
+            show_api_key: false,
+            error: None,
+            saved: false,
+        }
+    }
+
+    /// The settings as entered, or why they can't be used.
+    fn validate(&self) -> Result<Settings, String> {
+        let mut settings = self.settings.clone();
+        let game = &mut settings.game;
+        game.api_key = game.api_key.trim().to_owned();
+        game.model = game.model.trim().to_owned();
+        game.endpoint = game.endpoint.trim().trim_end_matches('/').to_owned();
+        if game.api_key.contains(char::is_whitespace) {
+            return Err("The API key can't contain spaces".to_owned());
+        }
+        if game.model.contains(char::is_whitespace) {
+            return Err("The model name can't contain spaces".to_owned());
+        }
+        if !game.endpoint.is_empty() {
+            let host = game
+                .endpoint
+                .strip_prefix("https://")
+                .or_else(|| game.endpoint.strip_prefix("http://"));
+            if host.is_none_or(|host| host.is_empty() || host.contains(char::is_whitespace)) {
+                return Err("The endpoint must be an http:// or https:// URL".to_owned());
+            }
+        }
+        settings.autosave_minutes = self
+            .autosave_field
+            .trim()
+            .parse()
+            .ok()
+            .filter(|minutes| *minutes <= MAX_AUTOSAVE_MINUTES)
+            .ok_or(format!(
+                "The auto-save interval must be a whole number of minutes from 0 to {}",
+                MAX_AUTOSAVE_MINUTES
+            ))?;
+        Ok(settings)
+    }
+
+    pub(super) fn update(&mut self, message: Message) -> Option<Action> {
+        self.saved = false;
+        match message {
+            Message::ApiKeyChange(api_key) => self.settings.game.api_key = api_key,
+            Message::ToggleShowApiKey(show) => self.show_api_key = show,
+            Message::ModelChange(model) => self.settings.game.model = model,
+            Message::EndpointChange(endpoint) => self.settings.game.endpoint = endpoint,
+            Message::ThemeSelected(theme) => self.settings.theme = theme,
+            Message::TextSizeSelected(text_size) => self.settings.text_size = text_size,
+            Message::AutosaveChange(minutes) => self.autosave_field = minutes,
+            Message::VerbositySelected(verbosity) => self.settings.game.verbosity = verbosity,
+            Message::Save => {
+                let settings = match self.validate() {
+                    Ok(settings) => settings,
+                    Err(error) => {
+                        self.error = Some(error);
+                        return None;
+                    }
+                };
+                if let Err(error) = settings.save() {
+                    self.error = Some(format!("Could not save settings: {}", error));
+                    return None;
+                }
+                self.error = None;
+                self.saved = true;
+                self.settings = settings.clone();
+                return Some(Action::Apply(settings));
+            }
+            Message::Back => return Some(Action::Back),
+        }
+        None
+    }
+
+    pub(super) fn view(&self) -> Element<'_, Message> {
+        let game = &self.settings.game;
+        let status = match (&self.error, self.saved) {
+            (Some(error), _) => text(error.clone()).color(color!(0xcc4444)),
+            (None, true) => text("Settings saved").color(color!(0x999999)),
+            (None, false) => text(""),
+        };
+        column![
+            vertical_space().height(40),
+            text("Settings").size(24),
+            vertical_space().height(20),
+            row![
+                text("API key").width(160),
+                text_input("From OPENAI_API_KEY", &game.api_key)
+                    .secure(!self.show_api_key)
+                    .on_input(Message::ApiKeyChange),
+                checkbox("Show", self.show_api_key).on_toggle(Message::ToggleShowApiKey),
+            ]
+            .spacing(10)
+            .align_y(Center),
+            row![
+                text("Model").width(160),
+                text_input("Default", &game.model).on_input(Message::ModelChange),
+            ]
+            .align_y(Center),
+            row![
+                text("Endpoint").width(160),
+                text_input("https://api.openai.com/v1", &game.endpoint)
+                    .on_input(Message::EndpointChange),
+            ]
+            .align_y(Center),
+            row![
+                text("GM verbosity").width(160),
+                pick_list(
+                    Verbosity::ALL,
+                    Some(game.verbosity),
+                    Message::VerbositySelected
+                ),
+            ]
+            .align_y(Center),
+            row![
+                text("Theme").width(160),
+                pick_list(
+                    Theme::ALL,
+                    Some(self.settings.theme.clone()),
+                    Message::ThemeSelected
+                ),
+            ]
+            .align_y(Center),
+            row![
+                text("Text size").width(160),
+                pick_list(
+                    TextSize::ALL,
+                    Some(self.settings.text_size),
+                    Message::TextSizeSelected
+                ),
+            ]
+            .align_y(Center),
+            row![
+                text("Auto-save (minutes)").width(160),
+                text_input("0 for never", &self.autosave_field)
+                    .width(100)
+                    .on_input(Message::AutosaveChange),
+            ]
+            .align_y(Center),
+            text("Changes to the GM apply to games started after saving.")
+                .size(12)
+                .color(color!(0x999999)),
+            vertical_space().height(20),
+            row![
+                button("Back")
+                    .style(button::secondary)
+                    .on_press(Message::Back),
+                button("Save").on_press(Message::Save),
+            ]
+            .spacing(20),
+            status,
+        ]
+        .spacing(10)
+        .width(Fill)
+        .align_x(Center)
+        .padding(20)
+        .into()
+    }
+}
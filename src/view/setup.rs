@@ -0,0 +1,193 @@
+use iced::widget::{button, checkbox, column, pick_list, row, text, text_input, vertical_space};
+use iced::{color, Center, Element, Fill};
+
+use micro_quest::character::PlayerCharacter;
+use micro_quest::schema::{AdventureSettings, ContentRating, Difficulty, Genre, QuestLength, Tone};
+
+#[derive(Debug, Clone)]
+pub(super) enum Message {
+    GenreSelected(Genre),
+    ToneSelected(Tone),
+    DifficultySelected(Difficulty),
+    LengthSelected(QuestLength),
+    ContentRatingSelected(ContentRating),
+    LinesChange(String),
+    VeilsChange(String),
+    ToggleCampaign(bool),
+    CampaignNameChange(String),
+    Begin,
+    Back,
+}
+
+pub(super) enum Action {
+    /// Starts the quest, as the first of a new campaign if it has a name.
+    Begin(Vec<PlayerCharacter>, AdventureSettings, Option<String>),
+    Back,
+}
+
+/// Chooses what kind of adventure the party is about to set out on.
+#[derive(Debug)]
+pub(super) struct AdventureSetup {
+    party: Vec<PlayerCharacter>,
+    adventure: AdventureSettings,
+    /// Lines and veils as typed, separated by commas.
+    lines_field: String,
+    veils_field: String,
+    campaign: bool,
+    campaign_name: String,
+}
+
+impl AdventureSetup {
+    pub(super) fn new(party: Vec<PlayerCharacter>, adventure: AdventureSettings) -> Self {
+        Self {
+            party,
+            lines_field: adventure.lines.join(", "),
+            veils_field: adventure.veils.join(", "),
+            adventure,
+            campaign: false,
+            campaign_name: String::new(),
+        }
+    }
+
+    pub(super) fn update(&mut self, message: Message) -> Option<Action> {
+        match message {
+            Message::GenreSelected(genre) => self.adventure.genre = genre,
+            Message::ToneSelected(tone) => self.adventure.tone = tone,
+            Message::DifficultySelected(difficulty) => self.adventure.difficulty = difficulty,
+            Message::LengthSelected(length) => self.adventure.length = length,
+            Message::ContentRatingSelected(rating) => self.adventure.content_rating = rating,
+            Message::LinesChange(lines) => self.lines_field = lines,
+            Message::VeilsChange(veils) => self.veils_field = veils,
+            Message::ToggleCampaign(campaign) => self.campaign = campaign,
+            Message::CampaignNameChange(name) => self.campaign_name = name,
+            Message::Begin => {
+                let mut adventure = self.adventure.clone();
+                adventure.lines = split_terms(&self.lines_field);
+                adventure.veils = split_terms(&self.veils_field);
+                let campaign = self.campaign.then(|| match self.campaign_name.trim() {
+                    "" => self.default_campaign_name(),
+                    name => name.to_owned(),
+                });
+                return Some(Action::Begin(
+                    std::mem::take(&mut self.party),
+                    adventure,
+                    campaign,
+                ));
+            }
+            Message::Back => return Some(Action::Back),
+        }
+        None
+    }
+
+    fn default_campaign_name(&self) -> String {
+        match self.party.first() {
+            Some(pc) => format!("The Adventures of {}", pc.name()),
+            None => "Campaign".to_owned(),
+        }
+    }
+
+    pub(super) fn view(&self) -> Element<'_, Message> {
+        let adventure = &self.adventure;
+        let party: Vec<_> = self.party.iter().map(|pc| pc.name()).collect();
+        column![
+            vertical_space().height(40),
+            text("New Adventure").size(24),
+            text(party.join(", ")).color(color!(0x999999)),
+            vertical_space().height(20),
+            row![
+                text("Genre").width(160),
+                pick_list(Genre::ALL, Some(adventure.genre), Message::GenreSelected),
+            ]
+            .align_y(Center),
+            row![
+                text("Tone").width(160),
+                pick_list(Tone::ALL, Some(adventure.tone), Message::ToneSelected),
+            ]
+            .align_y(Center),
+            row![
+                text("Difficulty").width(160),
+                pick_list(
+                    Difficulty::ALL,
+                    Some(adventure.difficulty),
+                    Message::DifficultySelected
+                ),
+            ]
+            .align_y(Center),
+            row![
+                text("Quest length").width(160),
+                pick_list(
+                    QuestLength::ALL,
+                    Some(adventure.length),
+                    Message::LengthSelected
+                ),
+            ]
+            .align_y(Center),
+            row![
+                text("Content rating").width(160),
+                pick_list(
+                    ContentRating::ALL,
+                    Some(adventure.content_rating),
+                    Message::ContentRatingSelected
+                ),
+            ]
+            .align_y(Center),
+            text("Races and classes are reinterpreted by the GM to fit the genre.")
+                .size(12)
+                .color(color!(0x999999)),
+            vertical_space().height(10),
+            row![
+                text("Lines").width(160),
+                text_input(
+                    "Never in the story, e.g. spiders, torture",
+                    &self.lines_field
+                )
+                .on_input(Message::LinesChange),
+            ]
+            .align_y(Center),
+            row![
+                text("Veils").width(160),
+                text_input("Off-screen only, e.g. romance", &self.veils_field)
+                    .on_input(Message::VeilsChange),
+            ]
+            .align_y(Center),
+            text("Separate subjects with commas. The X-card withdraws the last scene at any time.")
+                .size(12)
+                .color(color!(0x999999)),
+            vertical_space().height(10),
+            row![
+                checkbox("Start a campaign", self.campaign)
+                    .on_toggle(Message::ToggleCampaign)
+                    .width(160),
+                text_input(&self.default_campaign_name(), &self.campaign_name)
+                    .on_input_maybe(self.campaign.then_some(Message::CampaignNameChange)),
+            ]
+            .align_y(Center),
+            text("A campaign carries the party, allies, enemies and loose ends on to further quests.")
+                .size(12)
+                .color(color!(0x999999)),
+            vertical_space().height(20),
+            row![
+                button("Back")
+                    .style(button::secondary)
+                    .on_press(Message::Back),
+                button("Begin").on_press(Message::Begin),
+            ]
+            .spacing(20),
+        ]
+        .spacing(10)
+        .width(Fill)
+        .align_x(Center)
+        .padding(20)
+        .into()
+    }
+}
+
+/// The non-empty, comma-separated terms in `field`.
+fn split_terms(field: &str) -> Vec<String> {
+    field
+        .split(',')
+        .map(str::trim)
+        .filter(|term| !term.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
@@ -0,0 +1,189 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use iced::alignment::Horizontal;
+use iced::widget::{button, column, container, row, scrollable, text, vertical_space, Column};
+use iced::{color, Center, Element, Fill};
+
+use micro_quest::save::{SaveEntry, Saves};
+
+#[derive(Debug, Clone)]
+pub(super) enum Message {
+    NewGame,
+    Continue,
+    ToggleSaves,
+    Load(String),
+    Delete(String),
+    Campaigns,
+    Settings,
+    Quit,
+}
+
+pub(super) enum Action {
+    NewGame,
+    Load(Box<SaveEntry>),
+    Campaigns,
+    Settings,
+    Quit,
+}
+
+#[derive(Debug)]
+pub(super) struct TitleScreen {
+    saves: Saves,
+    /// Saved games, newest first.
+    entries: Vec<SaveEntry>,
+    show_saves: bool,
+    error: Option<String>,
+}
+
+impl TitleScreen {
+    pub(super) fn new(saves: Saves, show_saves: bool) -> Self {
+        let mut title = Self {
+            saves,
+            entries: Vec::new(),
+            show_saves,
+            error: None,
+        };
+        title.refresh();
+        title
+    }
+
+    pub(super) fn refresh(&mut self) {
+        match self.saves.list() {
+            Ok(entries) => self.entries = entries,
+            Err(error) => self.error = Some(error.to_string()),
+        }
+    }
+
+    pub(super) fn update(&mut self, message: Message) -> Option<Action> {
+        self.error = None;
+        match message {
+            Message::NewGame => Some(Action::NewGame),
+            Message::Continue => self
+                .entries
+                .first()
+                .map(|entry| Action::Load(Box::new(entry.clone()))),
+            Message::ToggleSaves => {
+                self.show_saves = !self.show_saves;
+                None
+            }
+            Message::Load(id) => match self.saves.load(&id) {
+                Ok(entry) => Some(Action::Load(Box::new(entry))),
+                Err(error) => {
+                    self.error = Some(error.to_string());
+                    None
+                }
+            },
+            Message::Delete(id) => {
+                if let Err(error) = self.saves.delete(&id) {
+                    self.error = Some(error.to_string());
+                }
+                self.refresh();
+                None
+            }
+            Message::Campaigns => Some(Action::Campaigns),
+            Message::Settings => Some(Action::Settings),
+            Message::Quit => Some(Action::Quit),
+        }
+    }
+
+    pub(super) fn view(&self) -> Element<'_, Message> {
+        let has_saves = !self.entries.is_empty();
+        let menu = column![
+            button(text("New Game").width(Fill).align_x(Center))
+                .width(200)
+                .on_press(Message::NewGame),
+            button(text("Continue").width(Fill).align_x(Center))
+                .width(200)
+                .on_press_maybe(has_saves.then_some(Message::Continue)),
+            button(text("Load").width(Fill).align_x(Center))
+                .width(200)
+                .on_press_maybe(has_saves.then_some(Message::ToggleSaves)),
+            button(text("Campaigns").width(Fill).align_x(Center))
+                .width(200)
+                .on_press(Message::Campaigns),
+            button(text("Settings").width(Fill).align_x(Center))
+                .width(200)
+                .on_press(Message::Settings),
+            button(text("Quit").width(Fill).align_x(Center))
+                .width(200)
+                .style(button::secondary)
+                .on_press(Message::Quit),
+        ]
+        .spacing(10);
+        let saves: Element<Message> = if self.show_saves && has_saves {
+            scrollable(
+                Column::with_children(
+                    self.entries
+                        .iter()
+                        .map(|entry| self.view_entry(entry))
+                        .collect::<Vec<_>>(),
+                )
+                .spacing(10),
+            )
+            .height(Fill)
+            .into()
+        } else {
+            vertical_space().into()
+        };
+        column![
+            vertical_space().height(60),
+            text("uQuest").size(48),
+            vertical_space().height(40),
+            menu,
+            vertical_space().height(20),
+            saves,
+            text(self.error.clone().unwrap_or_default()).color(color!(0xcc4444)),
+        ]
+        .width(Fill)
+        .align_x(Center)
+        .padding(20)
+        .into()
+    }
+
+    fn view_entry<'a>(&self, entry: &'a SaveEntry) -> Element<'a, Message> {
+        let state = &entry.game.state;
+        let party: Vec<_> = state.party.iter().map(|pc| pc.name()).collect();
+        container(
+            row![
+                column![
+                    text(state.quest.title.clone()).size(20),
+                    text(format!(
+                        "{} - saved {}",
+                        party.join(", "),
+                        saved_ago(entry.saved_at)
+                    ))
+                    .color(color!(0x999999)),
+                ]
+                .width(Fill)
+                .align_x(Horizontal::Left),
+                button("Load").on_press(Message::Load(entry.id.clone())),
+                button("Delete")
+                    .style(button::danger)
+                    .on_press(Message::Delete(entry.id.clone())),
+            ]
+            .spacing(10)
+            .align_y(Center),
+        )
+        .padding(10)
+        .width(Fill)
+        .style(container::bordered_box)
+        .into()
+    }
+}
+
+/// How long ago `saved_at`, in seconds since the Unix epoch, was.
+fn saved_ago(saved_at: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let minutes = now.saturating_sub(saved_at) / 60;
+    match minutes {
+        0 => "just now".to_owned(),
+        1 => "a minute ago".to_owned(),
+        2..=59 => format!("{} minutes ago", minutes),
+        60..=119 => "an hour ago".to_owned(),
+        120..=1439 => format!("{} hours ago", minutes / 60),
+        1440..=2879 => "yesterday".to_owned(),
+        _ => format!("{} days ago", minutes / 1440),
+    }
+}
@@ -0,0 +1,773 @@
+//! Undo, reroll, level-ups, the X-card, branching and saves against a
+//! scripted GM: a loopback stand-in for the parts of the Assistants API the
+//! game uses, so the GM's memory can be checked alongside the game's state.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use micro_quest::schema::{AIInput, AIOutput, QuestDefinition, QuestUpdate};
+use micro_quest::{
+    Catalog, GameBuilder, GameHandle, GameLogEntry, GamePlayer, GameState, Improvement,
+    PlayerCharacter, PlayerCharacterBuilder, SavedGame,
+};
+
+fn character(name: &str) -> PlayerCharacter {
+    PlayerCharacterBuilder::new(name.to_owned())
+        .with_race("Elf".to_owned())
+        .with_class("Ranger".to_owned())
+        .build(&Catalog::bundled())
+        .unwrap()
+}
+
+struct Message {
+    id: String,
+    role: &'static str,
+    text: String,
+    run_id: Option<String>,
+}
+
+#[derive(Default)]
+struct Thread {
+    messages: Vec<Message>,
+    runs: Vec<String>,
+}
+
+#[derive(Default)]
+struct Script {
+    threads: Vec<(String, Thread)>,
+    next_id: usize,
+    /// Responses to use instead of the default for the next runs.
+    replies: Vec<AIOutput>,
+    /// How many of the next runs fail.
+    failed_runs: usize,
+    /// How many deletes succeed before the next one fails.
+    deletes_before_failure: Option<usize>,
+}
+
+impl Script {
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}_{}", prefix, self.next_id)
+    }
+
+    fn thread(&mut self, id: &str) -> Option<&mut Thread> {
+        self.threads
+            .iter_mut()
+            .find(|(thread_id, _)| thread_id == id)
+            .map(|(_, thread)| thread)
+    }
+
+    /// The GM's response to the last command on a thread.
+    fn reply(&mut self, thread: &Thread) -> AIOutput {
+        if !self.replies.is_empty() {
+            return self.replies.remove(0);
+        }
+        let command = thread.messages.iter().rev().find(|msg| msg.role == "user");
+        let command = command.and_then(|msg| serde_json::from_str(&msg.text).ok());
+        let updates = match command {
+            Some(AIInput::Start { .. }) => vec![
+                QuestUpdate::QuestDefinition(quest("The Caves")),
+                QuestUpdate::SceneChange {
+                    title: "The cave mouth".to_owned(),
+                },
+                QuestUpdate::Description("You stand at the cave mouth.".to_owned()),
+            ],
+            Some(AIInput::UserInput { actions, .. }) => vec![QuestUpdate::Description(format!(
+                "You {}.",
+                actions[0].content
+            ))],
+            Some(AIInput::XCard) => {
+                vec![QuestUpdate::Description("Instead, all is calm.".to_owned())]
+            }
+            _ => vec![QuestUpdate::Description("Noted.".to_owned())],
+        };
+        AIOutput { updates }
+    }
+}
+
+type Gm = Arc<Mutex<Script>>;
+
+fn quest(title: &str) -> QuestDefinition {
+    QuestDefinition {
+        title: title.to_owned(),
+        ..QuestDefinition::default()
+    }
+}
+
+fn description(text: &str) -> AIOutput {
+    AIOutput {
+        updates: vec![QuestUpdate::Description(text.to_owned())],
+    }
+}
+
+fn not_found() -> Response {
+    api_error(StatusCode::NOT_FOUND, "No such object")
+}
+
+fn api_error(status: StatusCode, message: &str) -> Response {
+    let error = json!({
+        "error": {"message": message, "type": "invalid_request_error", "param": null, "code": null}
+    });
+    (status, Json(error)).into_response()
+}
+
+fn message_json(thread_id: &str, msg: &Message) -> Value {
+    json!({
+        "id": msg.id,
+        "object": "thread.message",
+        "created_at": 0,
+        "thread_id": thread_id,
+        "role": msg.role,
+        "content": [{"type": "text", "text": {"value": msg.text, "annotations": []}}],
+        "run_id": msg.run_id,
+        "attachments": [],
+        "metadata": {},
+    })
+}
+
+fn run_json(thread_id: &str, run_id: &str, status: &str) -> Value {
+    json!({
+        "id": run_id,
+        "object": "thread.run",
+        "created_at": 0,
+        "thread_id": thread_id,
+        "assistant_id": "asst_gm",
+        "status": status,
+        "model": "gpt-4o",
+        "instructions": "",
+        "tools": [],
+        "parallel_tool_calls": false,
+    })
+}
+
+fn thread_json(thread_id: &str) -> Value {
+    json!({"id": thread_id, "object": "thread", "created_at": 0, "metadata": {}})
+}
+
+async fn list_assistants() -> Json<Value> {
+    Json(json!({"object": "list", "data": [], "has_more": false}))
+}
+
+async fn create_assistant() -> Json<Value> {
+    Json(json!({
+        "id": "asst_gm",
+        "object": "assistant",
+        "created_at": 0,
+        "name": "uQuest GM",
+        "model": "gpt-4o",
+        "tools": [],
+    }))
+}
+
+async fn create_thread(State(gm): State<Gm>) -> Json<Value> {
+    let mut gm = gm.lock().unwrap();
+    let id = gm.id("thread");
+    gm.threads.push((id.clone(), Thread::default()));
+    Json(thread_json(&id))
+}
+
+async fn get_thread(State(gm): State<Gm>, Path(thread_id): Path<String>) -> Response {
+    match gm.lock().unwrap().thread(&thread_id) {
+        Some(_) => Json(thread_json(&thread_id)).into_response(),
+        None => not_found(),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewMessage {
+    role: String,
+    content: String,
+}
+
+async fn create_message(
+    State(gm): State<Gm>,
+    Path(thread_id): Path<String>,
+    Json(new): Json<NewMessage>,
+) -> Response {
+    let mut gm = gm.lock().unwrap();
+    let id = gm.id("msg");
+    let Some(thread) = gm.thread(&thread_id) else {
+        return not_found();
+    };
+    let msg = Message {
+        id,
+        role: if new.role == "assistant" {
+            "assistant"
+        } else {
+            "user"
+        },
+        text: new.content,
+        run_id: None,
+    };
+    let body = message_json(&thread_id, &msg);
+    thread.messages.push(msg);
+    Json(body).into_response()
+}
+
+async fn list_messages(
+    State(gm): State<Gm>,
+    Path(thread_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let mut gm = gm.lock().unwrap();
+    let Some(thread) = gm.thread(&thread_id) else {
+        return not_found();
+    };
+    let data: Vec<_> = thread
+        .messages
+        .iter()
+        .rev()
+        .filter(|msg| {
+            query
+                .get("run_id")
+                .is_none_or(|run| msg.run_id.as_ref() == Some(run))
+        })
+        .map(|msg| message_json(&thread_id, msg))
+        .collect();
+    Json(json!({"object": "list", "data": data, "has_more": false})).into_response()
+}
+
+async fn get_message(
+    State(gm): State<Gm>,
+    Path((thread_id, message_id)): Path<(String, String)>,
+) -> Response {
+    let mut gm = gm.lock().unwrap();
+    let msg = gm
+        .thread(&thread_id)
+        .and_then(|thread| thread.messages.iter().find(|msg| msg.id == message_id));
+    match msg {
+        Some(msg) => Json(message_json(&thread_id, msg)).into_response(),
+        None => not_found(),
+    }
+}
+
+async fn delete_message(
+    State(gm): State<Gm>,
+    Path((thread_id, message_id)): Path<(String, String)>,
+) -> Response {
+    let mut gm = gm.lock().unwrap();
+    match gm.deletes_before_failure {
+        Some(0) => {
+            gm.deletes_before_failure = None;
+            return api_error(StatusCode::INTERNAL_SERVER_ERROR, "Delete failed");
+        }
+        Some(deletes) => gm.deletes_before_failure = Some(deletes - 1),
+        None => {}
+    }
+    let Some(thread) = gm.thread(&thread_id) else {
+        return not_found();
+    };
+    let Some(index) = thread.messages.iter().position(|msg| msg.id == message_id) else {
+        return not_found();
+    };
+    thread.messages.remove(index);
+    Json(json!({"id": message_id, "object": "thread.message.deleted", "deleted": true}))
+        .into_response()
+}
+
+async fn create_run(State(gm): State<Gm>, Path(thread_id): Path<String>) -> Response {
+    let mut gm = gm.lock().unwrap();
+    let run_id = gm.id("run");
+    let message_id = gm.id("msg");
+    if gm.failed_runs > 0 {
+        gm.failed_runs -= 1;
+        return Json(run_json(&thread_id, &run_id, "failed")).into_response();
+    }
+    let Some((_, mut thread)) = gm
+        .threads
+        .iter()
+        .position(|(id, _)| *id == thread_id)
+        .map(|index| gm.threads.remove(index))
+    else {
+        return not_found();
+    };
+    let reply = gm.reply(&thread);
+    thread.messages.push(Message {
+        id: message_id,
+        role: "assistant",
+        text: serde_json::to_string(&reply).unwrap(),
+        run_id: Some(run_id.clone()),
+    });
+    thread.runs.push(run_id.clone());
+    gm.threads.push((thread_id.clone(), thread));
+    Json(run_json(&thread_id, &run_id, "queued")).into_response()
+}
+
+async fn get_run(
+    State(gm): State<Gm>,
+    Path((thread_id, run_id)): Path<(String, String)>,
+) -> Response {
+    match gm.lock().unwrap().thread(&thread_id) {
+        Some(thread) if thread.runs.contains(&run_id) => {
+            Json(run_json(&thread_id, &run_id, "completed")).into_response()
+        }
+        Some(_) => Json(run_json(&thread_id, &run_id, "failed")).into_response(),
+        None => not_found(),
+    }
+}
+
+/// Serves a scripted GM on loopback, returning its script and API base URL.
+async fn serve_gm() -> (Gm, String) {
+    let gm = Gm::default();
+    let app = Router::new()
+        .route(
+            "/v1/assistants",
+            get(list_assistants).post(create_assistant),
+        )
+        .route("/v1/threads", post(create_thread))
+        .route("/v1/threads/{thread}", get(get_thread))
+        .route(
+            "/v1/threads/{thread}/messages",
+            get(list_messages).post(create_message),
+        )
+        .route(
+            "/v1/threads/{thread}/messages/{message}",
+            get(get_message).delete(delete_message),
+        )
+        .route("/v1/threads/{thread}/runs", post(create_run))
+        .route("/v1/threads/{thread}/runs/{run}", get(get_run))
+        .with_state(gm.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (gm, format!("http://{}/v1", addr))
+}
+
+/// What the GM remembers on each thread, oldest first: the party's actions
+/// and commands as `> ...`, and the GM's narration.
+fn memory(gm: &Gm) -> Vec<Vec<String>> {
+    gm.lock()
+        .unwrap()
+        .threads
+        .iter()
+        .map(|(_, thread)| {
+            thread
+                .messages
+                .iter()
+                .map(|msg| match msg.role {
+                    "user" => match serde_json::from_str(&msg.text).unwrap() {
+                        AIInput::UserInput { actions, .. } => format!("> {}", actions[0].content),
+                        AIInput::Start { .. } => "> Start".to_owned(),
+                        AIInput::LevelUp { .. } => "> LevelUp".to_owned(),
+                        AIInput::XCard => "> XCard".to_owned(),
+                        command => format!("> {:?}", command),
+                    },
+                    _ => {
+                        let reply: AIOutput = serde_json::from_str(&msg.text).unwrap();
+                        reply
+                            .updates
+                            .iter()
+                            .filter_map(|update| match update {
+                                QuestUpdate::Description(text) => Some(text.as_str()),
+                                _ => None,
+                            })
+                            .collect()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn builder(character: PlayerCharacter, endpoint: &str) -> GameBuilder {
+    GameBuilder::new(character)
+        .with_api_key("test".to_owned())
+        .with_endpoint(endpoint.to_owned())
+}
+
+async fn started_game() -> (Gm, String, GameHandle) {
+    let (gm, endpoint) = serve_gm().await;
+    let game = builder(character("Ann"), &endpoint).build().await.unwrap();
+    game.start().await.unwrap();
+    (gm, endpoint, game)
+}
+
+fn state(game: &GameHandle) -> GameState {
+    game.state().read().unwrap().clone()
+}
+
+fn story(game: &GameHandle) -> Vec<(GamePlayer, String)> {
+    state(game)
+        .log
+        .into_iter()
+        .filter(GameLogEntry::in_story)
+        .map(|entry| (entry.player, entry.content))
+        .collect()
+}
+
+fn narration(content: &str) -> (GamePlayer, String) {
+    (GamePlayer::GM, content.to_owned())
+}
+
+fn action(content: &str) -> (GamePlayer, String) {
+    (GamePlayer::PC(0), content.to_owned())
+}
+
+fn system(content: &str) -> (GamePlayer, String) {
+    (GamePlayer::System, content.to_owned())
+}
+
+fn opening() -> Vec<(GamePlayer, String)> {
+    vec![
+        system("Scene 1: The cave mouth"),
+        narration("You stand at the cave mouth."),
+    ]
+}
+
+#[tokio::test]
+async fn undo_takes_back_actions_and_the_level_ups_since() {
+    let (gm, _, game) = started_game().await;
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+    gm.lock().unwrap().replies.push(AIOutput {
+        updates: vec![
+            QuestUpdate::Description("You find gold.".to_owned()),
+            QuestUpdate::AwardXp {
+                character: None,
+                amount: 300,
+                reason: "Treasure".to_owned(),
+            },
+        ],
+    });
+    game.input(0, "search".to_owned()).await.unwrap();
+    game.level_up(0, Improvement::HitPoints).await.unwrap();
+    assert_eq!(state(&game).party[0].level(), 2);
+    assert_eq!(state(&game).turn, 2);
+
+    game.undo().await.unwrap();
+    let after = state(&game);
+    assert_eq!(after.turn, 1);
+    assert_eq!(after.party[0], character("Ann"));
+    let mut expected = opening();
+    expected.extend([action("light a torch"), narration("You light a torch.")]);
+    assert_eq!(story(&game), expected);
+    assert_eq!(
+        memory(&gm),
+        [[
+            "> Start",
+            "You stand at the cave mouth.",
+            "> light a torch",
+            "You light a torch."
+        ]]
+    );
+
+    game.undo().await.unwrap();
+    assert_eq!(story(&game), opening());
+    assert_eq!(state(&game).turn, 0);
+    assert_eq!(state(&game).quest, quest("The Caves"));
+    // The opening isn't an action.
+    assert_eq!(
+        game.undo().await.unwrap_err().to_string(),
+        "Nothing to undo"
+    );
+    assert_eq!(memory(&gm), [["> Start", "You stand at the cave mouth."]]);
+}
+
+#[tokio::test]
+async fn undo_that_fails_part_way_can_be_retried() {
+    let (gm, _, game) = started_game().await;
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+    gm.lock().unwrap().replies.push(AIOutput {
+        updates: vec![
+            QuestUpdate::Description("You find gold.".to_owned()),
+            QuestUpdate::AwardXp {
+                character: None,
+                amount: 300,
+                reason: "Treasure".to_owned(),
+            },
+        ],
+    });
+    game.input(0, "search".to_owned()).await.unwrap();
+    game.level_up(0, Improvement::HitPoints).await.unwrap();
+
+    // The level up's reply and command go, then the search's reply fails.
+    gm.lock().unwrap().deletes_before_failure = Some(2);
+    assert!(game.undo().await.is_err());
+    // Back to where the GM's memory now ends.
+    assert_eq!(state(&game).party[0].level(), 1);
+    assert_eq!(
+        state(&game).party[0].pending_level_ups(&state(&game).level_table),
+        1
+    );
+    assert_eq!(memory(&gm)[0].len(), 6);
+
+    // Nothing already deleted is asked for again.
+    game.undo().await.unwrap();
+    let mut expected = opening();
+    expected.extend([action("light a torch"), narration("You light a torch.")]);
+    assert_eq!(story(&game), expected);
+    assert_eq!(memory(&gm)[0].len(), 4);
+}
+
+#[tokio::test]
+async fn reroll_replaces_the_response_and_keeps_the_action() {
+    let (gm, _, game) = started_game().await;
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+    gm.lock()
+        .unwrap()
+        .replies
+        .push(description("The torch sputters out."));
+
+    game.reroll().await.unwrap();
+    let mut expected = opening();
+    expected.extend([
+        action("light a torch"),
+        narration("The torch sputters out."),
+    ]);
+    assert_eq!(story(&game), expected);
+    assert_eq!(state(&game).turn, 1);
+    assert_eq!(
+        memory(&gm),
+        [[
+            "> Start",
+            "You stand at the cave mouth.",
+            "> light a torch",
+            "The torch sputters out."
+        ]]
+    );
+}
+
+#[tokio::test]
+async fn reroll_that_cannot_delete_the_response_keeps_the_turn() {
+    let (gm, _, game) = started_game().await;
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+    let before = story(&game);
+
+    gm.lock().unwrap().deletes_before_failure = Some(0);
+    assert!(game.reroll().await.is_err());
+    assert_eq!(story(&game), before);
+    assert_eq!(state(&game).turn, 1);
+
+    // Still the last action, so it can be rerolled or undone.
+    gm.lock().unwrap().replies.push(description("It's damp."));
+    game.reroll().await.unwrap();
+    assert_eq!(story(&game).last(), Some(&narration("It's damp.")));
+    game.undo().await.unwrap();
+    assert_eq!(story(&game), opening());
+    assert_eq!(memory(&gm), [["> Start", "You stand at the cave mouth."]]);
+}
+
+#[tokio::test]
+async fn reroll_is_only_for_the_last_action() {
+    let (gm, _, game) = started_game().await;
+    assert!(game.reroll().await.is_err());
+    gm.lock().unwrap().replies.push(AIOutput {
+        updates: vec![QuestUpdate::AwardXp {
+            character: None,
+            amount: 300,
+            reason: "Treasure".to_owned(),
+        }],
+    });
+    game.input(0, "search".to_owned()).await.unwrap();
+    game.level_up(0, Improvement::HitPoints).await.unwrap();
+    let before = story(&game);
+    assert!(game.reroll().await.is_err());
+    assert_eq!(story(&game), before);
+    assert_eq!(state(&game).party[0].level(), 2);
+}
+
+#[tokio::test]
+async fn failed_turns_are_unsent_until_the_next_action() {
+    let (gm, _, game) = started_game().await;
+    gm.lock().unwrap().failed_runs = 1;
+    assert!(game.input(0, "light a torch".to_owned()).await.is_err());
+    let after = state(&game);
+    assert_eq!(after.turn, 0);
+    let last = after.log.last().unwrap();
+    assert_eq!(last.player, GamePlayer::PC(0));
+    assert!(last.unsent);
+    // The GM never sees an unsent action.
+    assert_eq!(memory(&gm), [["> Start", "You stand at the cave mouth."]]);
+    assert!(game.branch(after.log.len() - 1).await.is_err());
+
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+    let mut expected = opening();
+    expected.extend([action("light a torch"), narration("You light a torch.")]);
+    assert_eq!(state(&game).log.len(), expected.len());
+    assert_eq!(story(&game), expected);
+    assert_eq!(state(&game).turn, 1);
+}
+
+#[tokio::test]
+async fn failed_level_ups_can_be_taken_again() {
+    let (gm, _, game) = started_game().await;
+    gm.lock().unwrap().replies.push(AIOutput {
+        updates: vec![
+            QuestUpdate::Description("You find gold.".to_owned()),
+            QuestUpdate::AwardXp {
+                character: Some("Ann".to_owned()),
+                amount: 300,
+                reason: "Treasure".to_owned(),
+            },
+        ],
+    });
+    game.input(0, "search".to_owned()).await.unwrap();
+    let before = state(&game);
+    assert_eq!(before.party[0].pending_level_ups(&before.level_table), 1);
+    let remembered = [
+        "> Start",
+        "You stand at the cave mouth.",
+        "> search",
+        "You find gold.",
+    ];
+
+    gm.lock().unwrap().failed_runs = 1;
+    assert!(game.level_up(0, Improvement::HitPoints).await.is_err());
+    let after = state(&game);
+    assert_eq!(after.party, before.party);
+    assert_eq!(after.log, before.log);
+    assert_eq!(memory(&gm), [remembered]);
+
+    game.level_up(0, Improvement::HitPoints).await.unwrap();
+    assert_eq!(state(&game).party[0].level(), 2);
+    let mut expected = remembered.to_vec();
+    expected.extend(["> LevelUp", "Noted."]);
+    assert_eq!(memory(&gm), [expected]);
+}
+
+#[tokio::test]
+async fn x_card_withdraws_the_last_scene() {
+    let (gm, _, game) = started_game().await;
+    gm.lock().unwrap().replies.push(AIOutput {
+        updates: vec![
+            QuestUpdate::Description("Spiders!".to_owned()),
+            QuestUpdate::ItemGained {
+                character: "Ann".to_owned(),
+                item: "Web".to_owned(),
+            },
+        ],
+    });
+    game.input(0, "go deeper".to_owned()).await.unwrap();
+
+    game.x_card().await.unwrap();
+    let after = state(&game);
+    assert_eq!(after.party[0], character("Ann"));
+    let withdrawn: Vec<_> = after
+        .log
+        .iter()
+        .filter(|entry| entry.retconned)
+        .map(|entry| entry.content.as_str())
+        .collect();
+    assert_eq!(withdrawn, ["Spiders!", "Ann gained Web"]);
+    let mut expected = opening();
+    expected.extend([action("go deeper"), narration("Instead, all is calm.")]);
+    assert_eq!(story(&game), expected);
+    assert_eq!(
+        memory(&gm),
+        [[
+            "> Start",
+            "You stand at the cave mouth.",
+            "> go deeper",
+            "> XCard",
+            "Instead, all is calm."
+        ]]
+    );
+}
+
+#[tokio::test]
+async fn branches_copy_the_story_up_to_their_entry() {
+    let (gm, _, game) = started_game().await;
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+    let at = state(&game);
+    game.input(0, "go deeper".to_owned()).await.unwrap();
+    let main = story(&game);
+
+    // From the GM's response to the torch, the last entry of that turn.
+    let branch = game.branch(at.log.len() - 1).await.unwrap();
+    assert_eq!(state(&branch).log, at.log);
+    assert_eq!(state(&branch).turn, 1);
+    assert_eq!(story(&game), main);
+    let memory = memory(&gm);
+    assert_eq!(memory[1], memory[0][..4]);
+
+    // The branch takes back turns on its own thread.
+    branch.undo().await.unwrap();
+    assert_eq!(story(&branch), opening());
+    assert_eq!(story(&game), main);
+    let memory = self::memory(&gm);
+    assert_eq!(memory[0].len(), 6);
+    assert_eq!(memory[1], ["> Start", "You stand at the cave mouth."]);
+}
+
+#[tokio::test]
+async fn branches_from_the_opening_start_after_it() {
+    let (gm, _, game) = started_game().await;
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+
+    let branch = game.branch(0).await.unwrap();
+    assert_eq!(story(&branch), opening());
+    assert_eq!(state(&branch).quest, quest("The Caves"));
+    assert_eq!(memory(&gm)[1], ["> Start", "You stand at the cave mouth."]);
+    assert!(game.branch(99).await.is_err());
+}
+
+async fn resume(saved: SavedGame, endpoint: &str) -> GameHandle {
+    // Through JSON, as the saves store keeps them.
+    let saved = serde_json::to_string(&saved).unwrap();
+    let saved: SavedGame = serde_json::from_str(&saved).unwrap();
+    GameBuilder::resume(saved)
+        .with_api_key("test".to_owned())
+        .with_endpoint(endpoint.to_owned())
+        .build()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn resumed_games_can_undo_turns_played_before_the_save() {
+    let (gm, endpoint, game) = started_game().await;
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+    let at = state(&game);
+    game.input(0, "go deeper".to_owned()).await.unwrap();
+
+    let saved = game.save().await.unwrap();
+    let resumed = resume(saved, &endpoint).await;
+    assert_eq!(state(&resumed).log, state(&game).log);
+    assert_eq!(state(&resumed).turn, 2);
+
+    resumed.undo().await.unwrap();
+    assert_eq!(state(&resumed).log, at.log);
+    assert_eq!(state(&resumed).turn, 1);
+    resumed.input(0, "wait".to_owned()).await.unwrap();
+    assert_eq!(
+        memory(&gm),
+        [[
+            "> Start",
+            "You stand at the cave mouth.",
+            "> light a torch",
+            "You light a torch.",
+            "> wait",
+            "You wait."
+        ]]
+    );
+}
+
+#[tokio::test]
+async fn saves_that_kept_the_whole_log_per_turn_still_resume() {
+    let (_, endpoint, game) = started_game().await;
+    game.input(0, "light a torch".to_owned()).await.unwrap();
+    let at = state(&game);
+    game.input(0, "go deeper".to_owned()).await.unwrap();
+
+    // Saved before only each turn's log length was kept.
+    let saved = serde_json::to_value(game.save().await.unwrap()).unwrap();
+    let mut old = saved.clone();
+    let log = &saved["state"]["log"];
+    for turn in old["history"].as_array_mut().unwrap() {
+        let len = turn["log_len"].as_u64().unwrap() as usize;
+        turn["before"]["log"] = Value::from(log.as_array().unwrap()[..len].to_vec());
+        turn.as_object_mut().unwrap().remove("log_len");
+    }
+    let resumed = resume(serde_json::from_value(old).unwrap(), &endpoint).await;
+
+    resumed.undo().await.unwrap();
+    assert_eq!(state(&resumed).log, at.log);
+}